@@ -3,12 +3,14 @@
 use criterion::{criterion_group, criterion_main, Criterion, Throughput};
 use std::sync::Arc;
 use tokio::runtime::Runtime;
-use veddb_client::Client;
+use veddb_client::{Client, Command, Connection, Document};
 
 const NUM_KEYS: usize = 10_000;
+const BATCH_SIZE: usize = 100;
+const BENCH_COLLECTION: &str = "bench_docs";
 
 async fn setup_client() -> Client {
-    let addr = "127.0.0.1:50051".parse().unwrap();
+    let addr = "127.0.0.1:50051";
     Client::with_pool_size(addr, 10)
         .await
         .expect("Failed to create client")
@@ -20,6 +22,13 @@ async fn cleanup_keys(client: &Client, prefix: &str) {
     }
 }
 
+fn bench_document(i: usize) -> Document {
+    let mut doc = Document::new();
+    doc.insert("index", i as i64);
+    doc.insert("name", format!("doc_{}", i));
+    doc
+}
+
 fn benchmark_set(c: &mut Criterion) {
     let rt = Runtime::new().unwrap();
     let client = rt.block_on(setup_client());
@@ -41,6 +50,47 @@ fn benchmark_set(c: &mut Criterion) {
         })
     });
 
+    // The same writes pipelined on one connection
+    group.bench_function("pipelined", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let cmds = (0..100)
+                    .map(|i| Command::set(0, format!("bench_set_key_{}", i), format!("value_{}", i)))
+                    .collect();
+                for result in client.pipeline(cmds).await.unwrap() {
+                    result.unwrap();
+                }
+            });
+        })
+    });
+
+    // Concurrent writes sharing one connection, with and without coalescing
+    for write_buffer in [0, 64 * 1024] {
+        let conn = rt.block_on(async {
+            let mut conn = Connection::connect(([127, 0, 0, 1], 50051)).await.unwrap();
+            conn.set_write_buffer(write_buffer);
+            Arc::new(conn)
+        });
+        let name = if write_buffer == 0 { "shared_connection" } else { "shared_connection_buffered" };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                rt.block_on(async {
+                    let handles: Vec<_> = (0..100)
+                        .map(|i| {
+                            let conn = conn.clone();
+                            tokio::spawn(async move {
+                                conn.set(format!("bench_set_key_{}", i), format!("value_{}", i)).await.unwrap();
+                            })
+                        })
+                        .collect();
+                    for handle in handles {
+                        handle.await.unwrap();
+                    }
+                });
+            })
+        });
+    }
+
     group.finish();
     rt.block_on(cleanup_keys(&client, "bench_set_"));
 }
@@ -75,6 +125,16 @@ fn benchmark_get(c: &mut Criterion) {
         })
     });
 
+    // The same reads in one round trip
+    group.bench_function("mget", |b| {
+        let keys: Vec<String> = (0..100).map(|i| format!("bench_get_key_{}", i)).collect();
+        b.iter(|| {
+            rt.block_on(async {
+                client.mget(&keys).await.unwrap();
+            });
+        })
+    });
+
     group.finish();
     rt.block_on(cleanup_keys(&client, "bench_get_"));
 }
@@ -121,11 +181,76 @@ fn benchmark_concurrent(c: &mut Criterion) {
     rt.block_on(cleanup_keys(&client, "bench_conc_"));
 }
 
+fn benchmark_insert(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let client = Arc::new(rt.block_on(setup_client()));
+
+    let mut group = c.benchmark_group("client_insert");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+
+    // One round trip per document
+    group.bench_function("serial", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                for i in 0..BATCH_SIZE {
+                    client
+                        .insert_document(BENCH_COLLECTION, bench_document(i))
+                        .await
+                        .unwrap();
+                }
+            });
+        })
+    });
+
+    // One round trip for the whole batch
+    group.bench_function("batched", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let docs = (0..BATCH_SIZE).map(bench_document).collect();
+                client.insert_many(BENCH_COLLECTION, docs).await.unwrap();
+            });
+        })
+    });
+
+    // Several tasks each sending their share as one batch
+    for num_tasks in [4, 8].iter() {
+        group.bench_with_input(
+            format!("batched_{}_tasks", num_tasks),
+            num_tasks,
+            |b, &num_tasks| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        let mut handles = Vec::new();
+
+                        for _ in 0..num_tasks {
+                            let client = client.clone();
+                            let handle = tokio::spawn(async move {
+                                let docs = (0..(BATCH_SIZE / num_tasks)).map(bench_document).collect();
+                                client.insert_many(BENCH_COLLECTION, docs).await.unwrap();
+                            });
+                            handles.push(handle);
+                        }
+
+                        for handle in handles {
+                            handle.await.unwrap();
+                        }
+                    });
+                })
+            },
+        );
+    }
+
+    group.finish();
+    rt.block_on(async {
+        let _ = client.drop_collection(BENCH_COLLECTION).await;
+    });
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default()
         .sample_size(10)
         .measurement_time(std::time::Duration::from_secs(10));
-    targets = benchmark_set, benchmark_get, benchmark_concurrent
+    targets = benchmark_set, benchmark_get, benchmark_concurrent, benchmark_insert
 );
 criterion_main!(benches);
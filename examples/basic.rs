@@ -8,7 +8,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
     // Connect to the VedDB server
-    let addr = "127.0.0.1:50051".parse()?;
+    let addr = "127.0.0.1:50051";
     let client = Client::connect(addr).await?;
 
     // Ping the server
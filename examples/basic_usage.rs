@@ -9,7 +9,7 @@ async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
     println!("Connecting to VedDB server...");
-    let addr = "127.0.0.1:50051".parse()?;
+    let addr = "127.0.0.1:50051";
     let client = Client::connect(addr).await?;
 
     // Basic key-value operations
@@ -31,7 +31,7 @@ async fn main() -> Result<()> {
 
     // Connection Pooling Example
     println!("\n=== Connection Pooling Example ===");
-    let pool_addr = "127.0.0.1:50051".parse()?;
+    let pool_addr = "127.0.0.1:50051";
     let pool = Client::with_pool_size(pool_addr, 5).await?;
 
     // This will use one of the 5 connections from the pool
@@ -14,7 +14,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
     // Create a client with a connection pool
-    let addr = "127.0.0.1:50051".parse()?;
+    let addr = "127.0.0.1:50051";
     let client = Client::with_pool_size(addr, 4).await?;
     let client = Arc::new(client);
 
@@ -10,38 +10,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
     // Connect to the VedDB server
-    let addr = "127.0.0.1:50051".parse()?;
+    let addr: std::net::SocketAddr = "127.0.0.1:50051".parse()?;
     let client = Client::connect(addr).await?;
 
-    // Subscribe to a channel in a separate task
-    let subscriber = client.clone();
-    let subscribe_task = tokio::spawn(async move {
-        let mut subscription = subscriber.subscribe("news").await.unwrap();
-        println!("Subscribed to 'news' channel");
-
-        // Listen for messages
-        while let Ok(message) = subscription.recv().await {
-            println!("Received: {}", String::from_utf8_lossy(&message));
+    // Subscribe on a dedicated connection
+    let mut subscription = client.subscribe("news").await?;
+    println!("Subscribed to 'news' channel, press Ctrl-C to stop");
+
+    // Publish some messages in the background
+    let publisher = client.clone();
+    tokio::spawn(async move {
+        for i in 0..5 {
+            let message = format!("Breaking news #{}", i + 1);
+            println!("Publishing: {}", message);
+            if let Err(e) = publisher.publish("news", message.as_bytes()).await {
+                eprintln!("Publish failed: {}", e);
+                return;
+            }
+            time::sleep(Duration::from_secs(1)).await;
         }
     });
 
-    // Give the subscriber time to subscribe
-    time::sleep(Duration::from_secs(1)).await;
-
-    // Publish some messages
-    for i in 0..5 {
-        let message = format!("Breaking news #{}", i + 1);
-        println!("Publishing: {}", message);
-        client.publish("news", message.as_bytes()).await?;
-        time::sleep(Duration::from_secs(1)).await;
+    // Print messages until Ctrl-C
+    loop {
+        tokio::select! {
+            message = subscription.recv() => {
+                let message = message?;
+                println!(
+                    "[{}] {}: {}",
+                    message.received_at.format("%H:%M:%S%.3f"),
+                    message.channel,
+                    String::from_utf8_lossy(&message.payload)
+                );
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
     }
 
-    // Wait a bit for all messages to be received
-    time::sleep(Duration::from_secs(1)).await;
-
-    // Unsubscribe and clean up
-    client.unsubscribe("news").await?;
-    subscribe_task.abort();
+    // Unsubscribe before exiting
+    subscription.unsubscribe_all().await?;
+    println!("Unsubscribed");
 
     Ok(())
 }
@@ -19,7 +19,7 @@ async fn main() -> Result<()> {
     // Test 1: Basic Connection
     println!("1. Testing basic connection...");
     let start = Instant::now();
-    let addr = "127.0.0.1:50051".parse()?;
+    let addr = "127.0.0.1:50051";
     let client = Client::connect(addr).await?;
     println!("   ✅ Connected in {:?}", start.elapsed());
 
@@ -75,7 +75,7 @@ async fn test_basic_operations(client: &Client) -> Result<()> {
 
 async fn test_connection_pooling() -> Result<()> {
     let pool_size = 5;
-    let addr = "127.0.0.1:50051".parse()?;
+    let addr = "127.0.0.1:50051";
     let client = Client::with_pool_size(addr, pool_size).await?;
     println!(
         "   ✅ Created connection pool with {} connections",
@@ -98,7 +98,7 @@ async fn test_connection_pooling() -> Result<()> {
 }
 
 async fn test_concurrent_operations() -> Result<()> {
-    let addr = "127.0.0.1:50051".parse()?;
+    let addr = "127.0.0.1:50051";
     let client = Arc::new(Client::with_pool_size(addr, 8).await?);
     let num_tasks = 20;
     let ops_per_task = 10;
@@ -151,7 +151,7 @@ async fn test_error_handling(client: &Client) -> Result<()> {
     }
 
     // Test with invalid server (should fail during connection)
-    let bad_addr = "127.0.0.1:99999".parse().unwrap();
+    let bad_addr = "127.0.0.1:99999";
     match Client::connect(bad_addr).await {
         Ok(_) => println!("   ❌ Expected connection error"),
         Err(e) => println!("   ✅ Correctly handled connection error: {}", e),
@@ -161,7 +161,7 @@ async fn test_error_handling(client: &Client) -> Result<()> {
 }
 
 async fn run_performance_benchmark() -> Result<()> {
-    let addr = "127.0.0.1:50051".parse()?;
+    let addr = "127.0.0.1:50051";
     let client = Client::with_pool_size(addr, 10).await?;
     let num_operations = 1000;
 
@@ -205,14 +205,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_client_creation() {
-        let addr = "127.0.0.1:50051".parse().unwrap();
+        let addr = "127.0.0.1:50051";
         let result = Client::connect(addr).await;
         assert!(result.is_ok(), "Should be able to create client");
     }
 
     #[tokio::test]
     async fn test_client_builder() {
-        let addr = "127.0.0.1:50051".parse().unwrap();
+        let addr = "127.0.0.1:50051";
         let result = ClientBuilder::new()
             .addr(addr)
             .pool_size(3)
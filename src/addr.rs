@@ -0,0 +1,195 @@
+//! Server addresses, given as socket addresses or hostnames
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tracing::debug;
+
+use crate::{Error, Result};
+
+/// Address of a VedDB server
+///
+/// Built from anything that converts into a [`SocketAddr`], or from a
+/// `"host:port"` string or `(host, port)` pair. Hostnames are resolved each
+/// time a connection is opened. Pooled connections stay on the address they
+/// were opened to; set
+/// [`max_connection_lifetime`](crate::ClientBuilder::max_connection_lifetime)
+/// to have them replaced, and so pick up DNS changes, periodically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerAddr(Target);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    /// An already resolved address
+    Socket(SocketAddr),
+    /// A hostname to resolve
+    Host { host: String, port: u16 },
+    /// A string that isn't `host:port`, reported when connecting
+    Invalid(String),
+}
+
+impl ServerAddr {
+    /// Get the hostname, if the address was given as one rather than an IP
+    pub fn host(&self) -> Option<&str> {
+        match &self.0 {
+            Target::Host { host, .. } => Some(host),
+            _ => None,
+        }
+    }
+
+    /// Resolve to every address the server may be reached at
+    pub(crate) async fn resolve(&self) -> Result<Vec<SocketAddr>> {
+        match &self.0 {
+            Target::Socket(addr) => Ok(vec![*addr]),
+            Target::Host { host, port } => {
+                let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), *port))
+                    .await
+                    .map_err(|e| Error::Connection(format!("Failed to resolve {}: {}", self, e)))?
+                    .collect();
+                if addrs.is_empty() {
+                    return Err(Error::Connection(format!("{} resolved to no addresses", self)));
+                }
+                Ok(addrs)
+            }
+            Target::Invalid(s) => Err(Error::InvalidArgument(format!(
+                "Invalid server address '{}': expected host:port",
+                s
+            ))),
+        }
+    }
+
+    /// Open a TCP connection, trying each resolved address in turn
+    ///
+    /// `connect_timeout` bounds resolution and each attempt. Returns the
+    /// stream and the address it connected to.
+    pub(crate) async fn connect(&self, connect_timeout: Duration) -> Result<(TcpStream, SocketAddr)> {
+        let addrs = timeout(connect_timeout, self.resolve()).await.map_err(Error::Timeout)??;
+        let mut last_error = None;
+        for addr in addrs {
+            match timeout(connect_timeout, TcpStream::connect(addr)).await {
+                Ok(Ok(stream)) => return Ok((stream, addr)),
+                Ok(Err(e)) => {
+                    debug!("Failed to connect to {} at {}: {}", self, addr, e);
+                    last_error = Some(Error::Io(e));
+                }
+                Err(e) => {
+                    debug!("Timed out connecting to {} at {}", self, addr);
+                    last_error = Some(Error::Timeout(e));
+                }
+            }
+        }
+        Err(last_error.unwrap_or(Error::NotConnected))
+    }
+}
+
+impl std::fmt::Display for ServerAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Target::Socket(addr) => addr.fmt(f),
+            Target::Host { host, port } => write!(f, "{}:{}", host, port),
+            Target::Invalid(s) => f.write_str(s),
+        }
+    }
+}
+
+/// Addresses that are already resolved
+macro_rules! from_socket_addr {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for ServerAddr {
+                fn from(addr: $ty) -> Self {
+                    Self(Target::Socket(addr.into()))
+                }
+            }
+        )*
+    };
+}
+
+from_socket_addr!(
+    SocketAddr,
+    SocketAddrV4,
+    SocketAddrV6,
+    (IpAddr, u16),
+    (Ipv4Addr, u16),
+    (Ipv6Addr, u16),
+    ([u8; 4], u16),
+    ([u16; 8], u16)
+);
+
+impl From<(&str, u16)> for ServerAddr {
+    fn from((host, port): (&str, u16)) -> Self {
+        match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            Ok(ip) => Self(Target::Socket(SocketAddr::new(ip, port))),
+            Err(_) => Self(Target::Host { host: host.to_string(), port }),
+        }
+    }
+}
+
+impl From<(String, u16)> for ServerAddr {
+    fn from((host, port): (String, u16)) -> Self {
+        Self::from((host.as_str(), port))
+    }
+}
+
+impl From<&str> for ServerAddr {
+    fn from(s: &str) -> Self {
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Self(Target::Socket(addr));
+        }
+        match s.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() => match port.parse() {
+                Ok(port) => Self::from((host, port)),
+                Err(_) => Self(Target::Invalid(s.to_string())),
+            },
+            _ => Self(Target::Invalid(s.to_string())),
+        }
+    }
+}
+
+impl From<String> for ServerAddr {
+    fn from(s: String) -> Self {
+        Self::from(s.as_str())
+    }
+}
+
+impl From<&String> for ServerAddr {
+    fn from(s: &String) -> Self {
+        Self::from(s.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_server_addr() {
+        let ip: SocketAddr = "127.0.0.1:50051".parse().unwrap();
+        assert_eq!(ServerAddr::from("127.0.0.1:50051"), ServerAddr::from(ip));
+        assert_eq!(ServerAddr::from(("127.0.0.1", 50051)), ServerAddr::from(ip));
+        assert_eq!(ServerAddr::from("[::1]:50051"), ServerAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 50051)));
+        assert_eq!(ServerAddr::from(ip).host(), None);
+
+        let host = ServerAddr::from("db.internal:50051");
+        assert_eq!(host.host(), Some("db.internal"));
+        assert_eq!(host.to_string(), "db.internal:50051");
+        assert_eq!(ServerAddr::from(("db.internal".to_string(), 50051)), host);
+
+        for invalid in ["db.internal", "db.internal:port", ":50051"] {
+            assert_eq!(ServerAddr::from(invalid).host(), None);
+            assert_eq!(ServerAddr::from(invalid).to_string(), invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let addrs = ServerAddr::from("localhost:50051").resolve().await.unwrap();
+        assert!(addrs.iter().all(|addr| addr.ip().is_loopback() && addr.port() == 50051));
+        assert!(matches!(
+            ServerAddr::from("localhost").resolve().await,
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+}
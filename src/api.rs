@@ -0,0 +1,318 @@
+//! A trait over the core client operations, for swapping in test doubles
+
+use std::future::Future;
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use crate::connection::Client;
+use crate::types::{DeleteDocRequest, Document, DocumentId, QueryRequest, UpdateDocRequest, UpdateResult};
+use crate::Result;
+
+/// The core key-value, document and pub/sub operations of a VedDB client
+///
+/// [`Client`] implements this by delegating to its inherent methods, which
+/// remain the primary API. Code that accepts `impl VedDbApi` instead of
+/// `Client` can be unit tested against an in-memory implementation, without
+/// a running server. The returned futures are `Send`, so implementations can
+/// be used from spawned tasks.
+pub trait VedDbApi: Send + Sync {
+    /// Check that the server is reachable
+    fn ping(&self) -> impl Future<Output = Result<()>> + Send;
+
+    /// Set a key-value pair
+    fn set<K, V>(&self, key: K, value: V) -> impl Future<Output = Result<()>> + Send
+    where
+        K: Into<Bytes> + Send,
+        V: Into<Bytes> + Send;
+
+    /// Set a key-value pair that expires after `ttl`
+    fn set_with_ttl<K, V>(&self, key: K, value: V, ttl: Duration) -> impl Future<Output = Result<()>> + Send
+    where
+        K: Into<Bytes> + Send,
+        V: Into<Bytes> + Send;
+
+    /// Get a value by key, failing with [`Error::KeyNotFound`](crate::Error::KeyNotFound)
+    /// if it doesn't exist
+    fn get<K>(&self, key: K) -> impl Future<Output = Result<Bytes>> + Send
+    where
+        K: Into<Bytes> + Send;
+
+    /// Get a value by key, or `None` if the key doesn't exist
+    fn get_opt<K>(&self, key: K) -> impl Future<Output = Result<Option<Bytes>>> + Send
+    where
+        K: Into<Bytes> + Send;
+
+    /// Get several keys, with `None` for keys that don't exist
+    fn mget<K>(&self, keys: &[K]) -> impl Future<Output = Result<Vec<Option<Bytes>>>> + Send
+    where
+        K: Clone + Into<Bytes> + Sync;
+
+    /// Set several key-value pairs
+    fn mset<K, V>(&self, pairs: &[(K, V)]) -> impl Future<Output = Result<()>> + Send
+    where
+        K: Clone + Into<Bytes> + Sync,
+        V: Clone + Into<Bytes> + Sync;
+
+    /// Delete a key
+    fn delete<K>(&self, key: K) -> impl Future<Output = Result<()>> + Send
+    where
+        K: Into<Bytes> + Send;
+
+    /// Check whether a key exists
+    fn exists<K>(&self, key: K) -> impl Future<Output = Result<bool>> + Send
+    where
+        K: Into<Bytes> + Send;
+
+    /// Add `delta` to the integer stored at a key, returning the new value
+    fn incr_by<K>(&self, key: K, delta: i64) -> impl Future<Output = Result<i64>> + Send
+    where
+        K: Into<Bytes> + Send;
+
+    /// Query documents in a collection
+    fn query(&self, request: QueryRequest) -> impl Future<Output = Result<Vec<Document>>> + Send;
+
+    /// Insert a document into a collection, returning its id
+    fn insert_document(&self, collection: &str, document: Document) -> impl Future<Output = Result<DocumentId>> + Send;
+
+    /// Update the documents matching a filter
+    fn update_document(&self, request: UpdateDocRequest) -> impl Future<Output = Result<UpdateResult>> + Send;
+
+    /// Delete the documents matching a filter, returning how many were deleted
+    fn delete_document(&self, request: DeleteDocRequest) -> impl Future<Output = Result<u64>> + Send;
+
+    /// Publish a message to a channel
+    fn publish(&self, channel: &str, message: &[u8]) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl VedDbApi for Client {
+    fn ping(&self) -> impl Future<Output = Result<()>> + Send {
+        Client::ping(self)
+    }
+
+    fn set<K, V>(&self, key: K, value: V) -> impl Future<Output = Result<()>> + Send
+    where
+        K: Into<Bytes> + Send,
+        V: Into<Bytes> + Send,
+    {
+        Client::set(self, key, value)
+    }
+
+    fn set_with_ttl<K, V>(&self, key: K, value: V, ttl: Duration) -> impl Future<Output = Result<()>> + Send
+    where
+        K: Into<Bytes> + Send,
+        V: Into<Bytes> + Send,
+    {
+        Client::set_with_ttl(self, key, value, ttl)
+    }
+
+    fn get<K>(&self, key: K) -> impl Future<Output = Result<Bytes>> + Send
+    where
+        K: Into<Bytes> + Send,
+    {
+        Client::get(self, key)
+    }
+
+    fn get_opt<K>(&self, key: K) -> impl Future<Output = Result<Option<Bytes>>> + Send
+    where
+        K: Into<Bytes> + Send,
+    {
+        Client::get_opt(self, key)
+    }
+
+    fn mget<K>(&self, keys: &[K]) -> impl Future<Output = Result<Vec<Option<Bytes>>>> + Send
+    where
+        K: Clone + Into<Bytes> + Sync,
+    {
+        Client::mget(self, keys)
+    }
+
+    fn mset<K, V>(&self, pairs: &[(K, V)]) -> impl Future<Output = Result<()>> + Send
+    where
+        K: Clone + Into<Bytes> + Sync,
+        V: Clone + Into<Bytes> + Sync,
+    {
+        Client::mset(self, pairs)
+    }
+
+    fn delete<K>(&self, key: K) -> impl Future<Output = Result<()>> + Send
+    where
+        K: Into<Bytes> + Send,
+    {
+        Client::delete(self, key)
+    }
+
+    fn exists<K>(&self, key: K) -> impl Future<Output = Result<bool>> + Send
+    where
+        K: Into<Bytes> + Send,
+    {
+        Client::exists(self, key)
+    }
+
+    fn incr_by<K>(&self, key: K, delta: i64) -> impl Future<Output = Result<i64>> + Send
+    where
+        K: Into<Bytes> + Send,
+    {
+        Client::incr_by(self, key, delta)
+    }
+
+    fn query(&self, request: QueryRequest) -> impl Future<Output = Result<Vec<Document>>> + Send {
+        Client::query(self, request)
+    }
+
+    fn insert_document(&self, collection: &str, document: Document) -> impl Future<Output = Result<DocumentId>> + Send {
+        Client::insert_document(self, collection, document)
+    }
+
+    fn update_document(&self, request: UpdateDocRequest) -> impl Future<Output = Result<UpdateResult>> + Send {
+        Client::update_document(self, request)
+    }
+
+    fn delete_document(&self, request: DeleteDocRequest) -> impl Future<Output = Result<u64>> + Send {
+        Client::delete_document(self, request)
+    }
+
+    fn publish(&self, channel: &str, message: &[u8]) -> impl Future<Output = Result<()>> + Send {
+        Client::publish(self, channel, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::Error;
+
+    /// An in-memory key-value store standing in for a server
+    #[derive(Default)]
+    struct MemoryStore {
+        keys: Mutex<HashMap<Bytes, Bytes>>,
+    }
+
+    impl VedDbApi for MemoryStore {
+        async fn ping(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn set<K, V>(&self, key: K, value: V) -> Result<()>
+        where
+            K: Into<Bytes> + Send,
+            V: Into<Bytes> + Send,
+        {
+            self.keys.lock().unwrap().insert(key.into(), value.into());
+            Ok(())
+        }
+
+        async fn set_with_ttl<K, V>(&self, key: K, value: V, _ttl: Duration) -> Result<()>
+        where
+            K: Into<Bytes> + Send,
+            V: Into<Bytes> + Send,
+        {
+            self.set(key, value).await
+        }
+
+        async fn get<K>(&self, key: K) -> Result<Bytes>
+        where
+            K: Into<Bytes> + Send,
+        {
+            self.get_opt(key).await?.ok_or(Error::KeyNotFound)
+        }
+
+        async fn get_opt<K>(&self, key: K) -> Result<Option<Bytes>>
+        where
+            K: Into<Bytes> + Send,
+        {
+            Ok(self.keys.lock().unwrap().get(&key.into()).cloned())
+        }
+
+        async fn mget<K>(&self, keys: &[K]) -> Result<Vec<Option<Bytes>>>
+        where
+            K: Clone + Into<Bytes> + Sync,
+        {
+            let store = self.keys.lock().unwrap();
+            Ok(keys.iter().map(|key| store.get(&key.clone().into()).cloned()).collect())
+        }
+
+        async fn mset<K, V>(&self, pairs: &[(K, V)]) -> Result<()>
+        where
+            K: Clone + Into<Bytes> + Sync,
+            V: Clone + Into<Bytes> + Sync,
+        {
+            let mut store = self.keys.lock().unwrap();
+            for (key, value) in pairs {
+                store.insert(key.clone().into(), value.clone().into());
+            }
+            Ok(())
+        }
+
+        async fn delete<K>(&self, key: K) -> Result<()>
+        where
+            K: Into<Bytes> + Send,
+        {
+            self.keys.lock().unwrap().remove(&key.into());
+            Ok(())
+        }
+
+        async fn exists<K>(&self, key: K) -> Result<bool>
+        where
+            K: Into<Bytes> + Send,
+        {
+            Ok(self.keys.lock().unwrap().contains_key(&key.into()))
+        }
+
+        async fn incr_by<K>(&self, _key: K, _delta: i64) -> Result<i64>
+        where
+            K: Into<Bytes> + Send,
+        {
+            Err(Error::InvalidArgument("not supported".to_string()))
+        }
+
+        async fn query(&self, _request: QueryRequest) -> Result<Vec<Document>> {
+            Ok(Vec::new())
+        }
+
+        async fn insert_document(&self, _collection: &str, document: Document) -> Result<DocumentId> {
+            Ok(document.id)
+        }
+
+        async fn update_document(&self, _request: UpdateDocRequest) -> Result<UpdateResult> {
+            Ok(UpdateResult::default())
+        }
+
+        async fn delete_document(&self, _request: DeleteDocRequest) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn publish(&self, _channel: &str, _message: &[u8]) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Application code written against the trait
+    async fn cache_greeting(db: &impl VedDbApi, name: &str) -> Result<Bytes> {
+        let key = format!("greeting:{}", name);
+        if let Some(cached) = db.get_opt(key.clone()).await? {
+            return Ok(cached);
+        }
+        let greeting = Bytes::from(format!("Hello, {}!", name));
+        db.set(key, greeting.clone()).await?;
+        Ok(greeting)
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_stands_in_for_client() {
+        let store = std::sync::Arc::new(MemoryStore::default());
+        assert_eq!(cache_greeting(&*store, "alice").await.unwrap(), "Hello, alice!");
+        assert!(store.exists("greeting:alice").await.unwrap());
+
+        // Futures are Send, so the trait works from spawned tasks
+        let spawned = store.clone();
+        tokio::spawn(async move { spawned.mset(&[("a", "1"), ("b", "2")]).await })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(store.mget(&["a", "c"]).await.unwrap(), [Some(Bytes::from("1")), None]);
+    }
+}
@@ -0,0 +1,68 @@
+//! zstd compression of command and response payloads
+//!
+//! Compression is negotiated per connection (see
+//! [`Connection::negotiate_compression`](crate::Connection::negotiate_compression)).
+//! Once enabled, command values of at least the threshold are compressed and
+//! flagged with `flags::COMPRESSED`; the server flags compressed responses
+//! with `response_flags::COMPRESSED`.
+
+use bytes::Bytes;
+
+use crate::types::{flags, Command};
+use crate::{Error, Result};
+
+/// Smallest value compressed by default, in bytes
+pub(crate) const DEFAULT_THRESHOLD: usize = 1024;
+
+/// zstd compression level, favouring speed
+const LEVEL: i32 = 3;
+
+/// Compress the command's value if it is at least `threshold` bytes and
+/// compressing actually makes it smaller
+pub(crate) fn compress_command(cmd: &mut Command, threshold: usize) -> Result<()> {
+    if cmd.value.len() < threshold || cmd.header.has_flag(flags::COMPRESSED) {
+        return Ok(());
+    }
+    let compressed = zstd::bulk::compress(&cmd.value, LEVEL)
+        .map_err(|e| Error::Serialization(format!("Failed to compress payload: {}", e)))?;
+    if compressed.len() < cmd.value.len() {
+        cmd.header = cmd
+            .header
+            .with_flag(flags::COMPRESSED)
+            .with_lengths(cmd.key.len() as u32, compressed.len() as u32);
+        cmd.value = Bytes::from(compressed);
+    }
+    Ok(())
+}
+
+/// Decompress a response payload, refusing to expand it past `max` bytes
+pub(crate) fn decompress(payload: &[u8], max: usize) -> Result<Bytes> {
+    zstd::bulk::decompress(payload, max)
+        .map(Bytes::from)
+        .map_err(|e| Error::Protocol(format!("Failed to decompress response: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_round_trip() {
+        let value = "veddb ".repeat(1000);
+        let mut cmd = Command::set(1, "key", value.clone());
+        compress_command(&mut cmd, DEFAULT_THRESHOLD).unwrap();
+        assert!(cmd.header.has_flag(flags::COMPRESSED));
+        assert_eq!(cmd.header.value_len as usize, cmd.value.len());
+        assert!(cmd.value.len() < value.len());
+        assert_eq!(decompress(&cmd.value, value.len()).unwrap(), value.as_bytes());
+
+        // Expanding past the limit is refused
+        assert!(decompress(&cmd.value, value.len() - 1).is_err());
+
+        // Small values are sent as-is
+        let mut cmd = Command::set(2, "key", "small");
+        compress_command(&mut cmd, DEFAULT_THRESHOLD).unwrap();
+        assert!(!cmd.header.has_flag(flags::COMPRESSED));
+        assert_eq!(&cmd.value[..], b"small");
+    }
+}
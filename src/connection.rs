@@ -1,50 +1,75 @@
 //! Connection handling for VedDB client with TLS support and v0.2.0 protocol
 
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
-use bytes::Bytes;
-use rustls::{ClientConfig, RootCertStore, ServerName};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use bytes::{Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf, ReadHalf, WriteHalf};
 use tokio::net::TcpStream;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
-use tokio_rustls::{TlsConnector, client::TlsStream};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn, Instrument};
 
 use crate::types::{
-    Command, Response, AuthRequest, AuthMethod, AuthCredentials, AuthResponse,
-    QueryRequest, InsertDocRequest, UpdateDocRequest, DeleteDocRequest,
+    Command, Response, ResponseHeader, AuthRequest, AuthMethod, AuthCredentials, AuthResponse,
+    QueryRequest, QueryBuilder, InsertDocRequest, InsertManyRequest, TouchRequest, SelectRequest, GetSchemaRequest, AckRequest, KeyType, ScanRequest, UpdateDocRequest, UpdateResult, DeleteDocRequest,
+    TransactionRequest, BulkWriteRequest, BulkWriteResult, WriteOp,
     CreateCollectionRequest, CreateIndexRequest, ListOpRequest, SetOpRequest,
-    SortedSetOpRequest, HashOpRequest, OperationResponse, Document, Value,
+    SortedSetOpRequest, HashOpRequest, OperationResponse, Document, DocumentId, Value,
+    ListOperation, HashOperation, SetOperation, SortedSetOperation, ScoredMember,
     ListCollectionsRequest, DropCollectionRequest, DropIndexRequest, ListIndexesRequest,
-    PROTOCOL_V2
+    DistinctRequest, OpCode, PROTOCOL_V1, PROTOCOL_V2
 };
+use crate::addr::ServerAddr;
+use crate::cursor::{Cursor, KeyScan};
+use crate::retry::RetryPolicy;
+use crate::routing::{NodeRole, ReadPreference, Replicas};
+use crate::schema::SchemaCache;
+use crate::stats::{ClientStats, PoolStats, StatsCounters};
+use crate::subscription::{ResilientSubscription, Subscription};
+use crate::transaction::Transaction;
 use crate::{Error, Result};
 
 /// Default connection timeout
 const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 /// Default request timeout
 const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
-/// Maximum frame size (16MB)
-const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+/// Default time to wait for a free pooled connection
+const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default time before token expiry to re-authenticate
+const DEFAULT_AUTH_REFRESH_THRESHOLD: Duration = Duration::from_secs(60);
+/// Default maximum frame size (16MB)
+const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+/// Hard cap on the configurable frame size (256MB), so a misbehaving server
+/// can't make the client allocate without bound
+const MAX_FRAME_SIZE_LIMIT: usize = 256 * 1024 * 1024;
 
 /// TLS configuration for client connections
 #[derive(Debug, Clone)]
 pub struct TlsConfig {
     /// Enable TLS encryption
     pub enabled: bool,
-    /// Server name for SNI (Server Name Indication)
+    /// Server name for SNI (Server Name Indication), defaulting to the
+    /// hostname connected to, or the IP address if connecting by IP
     pub server_name: Option<String>,
-    /// Path to CA certificate file for server verification
+    /// Path to CA certificate file for server verification, trusted along
+    /// with the platform's root certificates
     pub ca_cert_path: Option<String>,
-    /// Path to client certificate file (for mutual TLS)
+    /// Path to client certificate file (for mutual TLS); with the
+    /// `native-tls` backend its key must be PKCS#8
     pub client_cert_path: Option<String>,
-    /// Path to client private key file (for mutual TLS)
+    /// Path to client private key file (for mutual TLS), PKCS#8, RSA or EC
+    /// with `rustls`, but only PKCS#8 with `native-tls`
     pub client_key_path: Option<String>,
-    /// Accept invalid certificates (for testing only)
+    /// Accept invalid certificates (for testing only, requires the
+    /// `dangerous-tls` feature)
+    #[cfg(feature = "dangerous-tls")]
     pub accept_invalid_certs: bool,
 }
 
@@ -56,6 +81,7 @@ impl Default for TlsConfig {
             ca_cert_path: None,
             client_cert_path: None,
             client_key_path: None,
+            #[cfg(feature = "dangerous-tls")]
             accept_invalid_certs: false,
         }
     }
@@ -89,6 +115,11 @@ impl TlsConfig {
     }
 
     /// Accept invalid certificates (for testing only)
+    ///
+    /// Only available with the `dangerous-tls` feature, so certificate
+    /// validation can't be turned off by accident in a release build. A
+    /// warning is still logged on every connection that uses it.
+    #[cfg(feature = "dangerous-tls")]
     pub fn accept_invalid_certs(mut self) -> Self {
         self.accept_invalid_certs = true;
         self
@@ -119,6 +150,24 @@ impl AuthConfig {
         }
     }
 
+    /// Create username/password authentication with the password read from
+    /// the `VEDDB_PASSWORD` environment variable, keeping it out of command
+    /// lines and shell history
+    pub fn username_password_from_env(username: impl Into<String>) -> Result<Self> {
+        Self::username_password_from_var(username, std::env::var("VEDDB_PASSWORD"))
+    }
+
+    /// Create username/password authentication from the result of looking up
+    /// `VEDDB_PASSWORD`
+    fn username_password_from_var(
+        username: impl Into<String>,
+        password: std::result::Result<String, std::env::VarError>,
+    ) -> Result<Self> {
+        let password = password
+            .map_err(|e| Error::InvalidArgument(format!("Can't read the password from VEDDB_PASSWORD: {}", e)))?;
+        Ok(Self::username_password(username, password))
+    }
+
     /// Create JWT token authentication
     pub fn jwt_token(token: impl Into<String>) -> Self {
         Self {
@@ -128,74 +177,313 @@ impl AuthConfig {
             token: Some(token.into()),
         }
     }
+
+    /// Authenticate with the client certificate of a mutual TLS connection
+    ///
+    /// The TLS configuration must set both
+    /// [`client_cert_path`](TlsConfig::client_cert_path) and
+    /// [`client_key_path`](TlsConfig::client_key_path).
+    pub fn client_certificate() -> Self {
+        Self {
+            method: AuthMethod::ClientCertificate,
+            username: None,
+            password: None,
+            token: None,
+        }
+    }
 }
 
 /// Connection stream type (plain TCP or TLS)
 #[derive(Debug)]
 enum ConnectionStream {
     Plain(TcpStream),
-    Tls(TlsStream<TcpStream>),
+    Tls(crate::tls::TlsStream),
 }
 
-impl ConnectionStream {
-    async fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
-        match self {
-            ConnectionStream::Plain(stream) => {
-                stream.read_exact(buf).await?;
-                Ok(())
-            },
-            ConnectionStream::Tls(stream) => {
-                stream.read_exact(buf).await?;
-                Ok(())
-            },
+impl AsyncRead for ConnectionStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            ConnectionStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            ConnectionStream::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for ConnectionStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            ConnectionStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            ConnectionStream::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            ConnectionStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            ConnectionStream::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    /// Shuts down the write side, sending a TLS `close_notify` first on TLS
+    /// connections
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            ConnectionStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            ConnectionStream::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Where incoming frames go, shared between a connection and its reader task
+#[derive(Debug, Default)]
+struct Demux {
+    /// Requests awaiting a response, by sequence number
+    pending: HashMap<u32, oneshot::Sender<Result<Response>>>,
+    /// Requests given up on whose response may still arrive, kept so their
+    /// sequence numbers aren't reused until it does
+    abandoned: HashSet<u32>,
+    /// Queue for pushed messages, dropped when the reader stops
+    pushes: Option<mpsc::UnboundedSender<Response>>,
+    /// Whether the connection has subscribed, so pushes are expected
+    subscribed: bool,
+    /// Why the reader stopped, once the connection is unusable
+    closed: Option<String>,
+}
+
+/// State shared between a connection and its reader task
+#[derive(Debug)]
+struct Shared {
+    /// Frame routing
+    demux: std::sync::Mutex<Demux>,
+    /// Protocol version used to frame responses
+    protocol_version: AtomicU8,
+    /// Largest request or response frame accepted, in bytes
+    max_frame_size: AtomicUsize,
+}
+
+impl Shared {
+    fn demux(&self) -> std::sync::MutexGuard<'_, Demux> {
+        self.demux.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start waiting for the response to `seq`, or `None` if `seq` still
+    /// belongs to an outstanding request
+    fn register(&self, seq: u32) -> Result<Option<oneshot::Receiver<Result<Response>>>> {
+        let mut demux = self.demux();
+        if let Some(reason) = &demux.closed {
+            return Err(Error::Connection(reason.clone()));
+        }
+        if demux.pending.contains_key(&seq) || demux.abandoned.contains(&seq) {
+            return Ok(None);
+        }
+        let (tx, rx) = oneshot::channel();
+        demux.pending.insert(seq, tx);
+        Ok(Some(rx))
+    }
+
+    /// Stop waiting for the response to `seq`; a late response is discarded
+    fn forget(&self, seq: u32) {
+        let mut demux = self.demux();
+        if demux.pending.remove(&seq).is_some() {
+            demux.abandoned.insert(seq);
+        }
+    }
+
+    /// Route a frame to the request awaiting it, or to the push queue
+    fn dispatch(&self, response: Response) {
+        let mut demux = self.demux();
+        if response.is_push() {
+            match &demux.pushes {
+                Some(pushes) if demux.subscribed => {
+                    let _ = pushes.send(response);
+                }
+                _ => debug!("Discarding pushed message on an unsubscribed connection"),
+            }
+            return;
+        }
+
+        let seq = response.header.seq;
+        match demux.pending.remove(&seq) {
+            // The caller may have given up in the meantime
+            Some(tx) => {
+                let _ = tx.send(Ok(response));
+            }
+            None if demux.abandoned.remove(&seq) => {
+                debug!("Discarding late response seq={} to an abandoned request", seq)
+            }
+            None => debug!("Discarding response seq={} with no waiting request", seq),
+        }
+    }
+
+    /// Fail every waiting request once the stream is unusable
+    ///
+    /// The request whose frame caused the failure, if known, gets the
+    /// original error; the rest get [`Error::Connection`].
+    fn fail(&self, seq: Option<u32>, error: Error) {
+        let mut demux = self.demux();
+        let reason = format!("Connection closed: {}", error);
+        if let Some(tx) = seq.and_then(|seq| demux.pending.remove(&seq)) {
+            let _ = tx.send(Err(error));
         }
+        for (_, tx) in demux.pending.drain() {
+            let _ = tx.send(Err(Error::Connection(reason.clone())));
+        }
+        demux.abandoned.clear();
+        demux.pushes = None;
+        demux.closed = Some(reason);
+    }
+}
+
+/// A running reader task
+#[derive(Debug)]
+struct Reader {
+    /// Asks the task to stop and hand back the read half
+    stop: oneshot::Sender<()>,
+    /// The task, returning the read half when it stops
+    task: JoinHandle<ReadHalf<ConnectionStream>>,
+}
+
+impl Reader {
+    /// Spawn a task reading frames from `stream` and routing them
+    fn spawn(stream: ReadHalf<ConnectionStream>, shared: Arc<Shared>) -> Self {
+        let (stop, stop_rx) = oneshot::channel();
+        let task = tokio::spawn(read_loop(stream, shared, stop_rx));
+        Self { stop, task }
     }
+}
 
-    async fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
-        match self {
-            ConnectionStream::Plain(stream) => stream.write_all(buf).await,
-            ConnectionStream::Tls(stream) => stream.write_all(buf).await,
+/// Read frames until the stream fails or the connection stops the reader
+///
+/// Dropping the connection drops `stop`, which also ends the loop.
+async fn read_loop(
+    mut stream: ReadHalf<ConnectionStream>,
+    shared: Arc<Shared>,
+    mut stop: oneshot::Receiver<()>,
+) -> ReadHalf<ConnectionStream> {
+    loop {
+        let frame = tokio::select! {
+            biased;
+            _ = &mut stop => return stream,
+            frame = read_frame(&mut stream, &shared) => frame,
+        };
+        match frame {
+            Ok(response) => shared.dispatch(response),
+            Err((seq, e)) => {
+                debug!("Connection reader stopped: {}", e);
+                shared.fail(seq, e);
+                return stream;
+            }
         }
     }
+}
 
-    async fn flush(&mut self) -> std::io::Result<()> {
-        match self {
-            ConnectionStream::Plain(stream) => stream.flush().await,
-            ConnectionStream::Tls(stream) => stream.flush().await,
+/// Read a single response frame, returning with any error the sequence
+/// number of the frame it occurred in, if it got that far
+async fn read_frame(
+    stream: &mut ReadHalf<ConnectionStream>,
+    shared: &Shared,
+) -> std::result::Result<Response, (Option<u32>, Error)> {
+    // Read the response header (16 bytes for v0.2.0, 20 bytes for v0.1.x)
+    let version = shared.protocol_version.load(Ordering::SeqCst);
+    let header_size = ResponseHeader::size_for(version).map_err(|e| (None, Error::Protocol(e.to_string())))?;
+    let mut header_buf = vec![0u8; header_size];
+    stream.read_exact(&mut header_buf).await.map_err(|e| (None, e.into()))?;
+
+    // The sequence number and payload length are at the same offsets in
+    // both versions
+    let seq = Some(u32_at(&header_buf, 4));
+    let payload_len = frame_len(u32_at(&header_buf, 8), shared.max_frame_size.load(Ordering::SeqCst))
+        .map_err(|e| (seq, e))?;
+
+    // Read the payload, and its checksum if the server sent one, and parse
+    // the whole frame
+    let checksum_len = if header_buf[1] & crate::types::response_flags::CHECKSUM != 0 { 4 } else { 0 };
+    let mut response_bytes = header_buf;
+    response_bytes.resize(header_size + payload_len + checksum_len, 0);
+    stream
+        .read_exact(&mut response_bytes[header_size..])
+        .await
+        .map_err(|e| (seq, e.into()))?;
+
+    #[allow(unused_mut)]
+    let mut response = Response::from_bytes(&response_bytes, version)
+        .map_err(|e| (seq, Error::Protocol(format!("Invalid response: {}", e))))?;
+
+    // An empty payload is never compressed, e.g. the reply to negotiation
+    if response.header.flags & crate::types::response_flags::COMPRESSED != 0 && !response.payload.is_empty() {
+        #[cfg(feature = "compression")]
+        {
+            let max = shared.max_frame_size.load(Ordering::SeqCst);
+            response.payload = crate::compression::decompress(&response.payload, max).map_err(|e| (seq, e))?;
         }
+        #[cfg(not(feature = "compression"))]
+        return Err((seq, Error::Protocol("Received a compressed response without compression support".to_string())));
     }
+    Ok(response)
 }
 
 /// A connection to a VedDB server
 #[derive(Debug)]
 pub struct Connection {
-    /// The underlying stream (TCP or TLS)
-    stream: Mutex<ConnectionStream>,
+    /// The write side of the stream (TCP or TLS)
+    writer: Mutex<WriteHalf<ConnectionStream>>,
+    /// Task reading and routing responses and pushed messages
+    reader: Option<Reader>,
+    /// Frame routing and settings shared with the reader
+    shared: Arc<Shared>,
+    /// Pushed messages, for subscriptions
+    pushes: Mutex<mpsc::UnboundedReceiver<Response>>,
     /// Server address
     addr: SocketAddr,
+    /// Whether the server is the primary or a replica
+    role: NodeRole,
+    /// When the connection was last returned to its pool, or opened
+    last_used: Instant,
+    /// When the connection was opened
+    opened_at: Instant,
     /// Next sequence number
     next_seq: AtomicU32,
     /// Connection timeout
     connect_timeout: Duration,
     /// Request timeout
     request_timeout: Duration,
-    /// Protocol version (v0.1.x or v0.2.0)
-    protocol_version: u8,
     /// Authentication token (for v0.2.0)
     auth_token: Mutex<Option<String>>,
+    /// Credentials to re-authenticate with when the token expires
+    auth_config: std::sync::Mutex<Option<AuthConfig>>,
+    /// When the token expires, in Unix seconds (0 if it doesn't)
+    auth_expires_at: AtomicU64,
+    /// How long before the token expires to re-authenticate
+    auth_refresh_threshold: Duration,
+    /// The pool's re-authentication round this connection has caught up with
+    auth_epoch: AtomicU64,
     /// TLS configuration
     tls_config: Option<TlsConfig>,
+    /// Request statistics, shared with the rest of the pool
+    stats: Arc<StatsCounters>,
+    /// Whether commands carry a CRC32 checksum
+    checksums: bool,
+    /// Logical database commands run against
+    database: AtomicU32,
+    /// Largest number of bytes coalesced into one write, or 0 to write each
+    /// frame on its own
+    write_buffer: usize,
+    /// Frames waiting for whichever task holds the writer next
+    outbox: std::sync::Mutex<BytesMut>,
+    /// Smallest value compressed, once compression has been negotiated
+    #[cfg(feature = "compression")]
+    compression_threshold: Option<usize>,
 }
 
 impl Connection {
     /// Create a new connection to the specified address
-    pub async fn connect(addr: impl Into<SocketAddr>) -> Result<Self> {
+    pub async fn connect(addr: impl Into<ServerAddr>) -> Result<Self> {
         Self::connect_with_config(addr, None, None).await
     }
 
     /// Create a new connection with TLS configuration
     pub async fn connect_with_tls(
-        addr: impl Into<SocketAddr>,
+        addr: impl Into<ServerAddr>,
         tls_config: TlsConfig,
     ) -> Result<Self> {
         Self::connect_with_config(addr, Some(tls_config), None).await
@@ -203,7 +491,7 @@ impl Connection {
 
     /// Create a new connection with TLS and authentication
     pub async fn connect_with_auth(
-        addr: impl Into<SocketAddr>,
+        addr: impl Into<ServerAddr>,
         tls_config: Option<TlsConfig>,
         auth_config: AuthConfig,
     ) -> Result<Self> {
@@ -212,30 +500,51 @@ impl Connection {
 
     /// Create a new connection with full configuration
     pub async fn connect_with_config(
-        addr: impl Into<SocketAddr>,
+        addr: impl Into<ServerAddr>,
+        tls_config: Option<TlsConfig>,
+        auth_config: Option<AuthConfig>,
+    ) -> Result<Self> {
+        Self::connect_with_timeouts(
+            addr,
+            tls_config,
+            auth_config,
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_REQUEST_TIMEOUT,
+        )
+        .await
+    }
+
+    /// Create a new connection with full configuration and explicit timeouts
+    ///
+    /// `connect_timeout` bounds each of establishing the TCP connection, the
+    /// TLS handshake and authentication; `request_timeout` bounds each write
+    /// and read of a request.
+    pub async fn connect_with_timeouts(
+        addr: impl Into<ServerAddr>,
         tls_config: Option<TlsConfig>,
         auth_config: Option<AuthConfig>,
+        connect_timeout: Duration,
+        request_timeout: Duration,
     ) -> Result<Self> {
-        let addr = addr.into();
-        info!("Connecting to VedDB server at {}", addr);
+        let target = addr.into();
+        info!("Connecting to VedDB server at {}", target);
 
         // Establish TCP connection
-        let tcp_stream = timeout(DEFAULT_CONNECT_TIMEOUT, TcpStream::connect(&addr))
-            .await
-            .map_err(Error::Timeout)??;
+        let (tcp_stream, addr) = target.connect(connect_timeout).await?;
+
+        // Verify the certificate against the hostname we were given, unless
+        // told otherwise
+        let mut tls_config = tls_config;
+        if let (Some(tls_cfg), Some(host)) = (tls_config.as_mut(), target.host()) {
+            tls_cfg.server_name.get_or_insert_with(|| host.to_string());
+        }
 
         // Upgrade to TLS if configured
         let stream = if let Some(ref tls_cfg) = tls_config {
             if tls_cfg.enabled {
-                let tls_connector = Self::create_tls_connector(tls_cfg)?;
-                let server_name = tls_cfg.server_name.as_deref()
-                    .unwrap_or("localhost");
-                let server_name = ServerName::try_from(server_name)
-                    .map_err(|e| Error::Connection(format!("Invalid server name: {}", e)))?;
-                
-                let tls_stream = tls_connector.connect(server_name, tcp_stream).await
-                    .map_err(|e| Error::Connection(format!("TLS handshake failed: {}", e)))?;
-                
+                let tls_stream = timeout(connect_timeout, crate::tls::connect(tls_cfg, tcp_stream, addr.ip()))
+                    .await
+                    .map_err(Error::Timeout)??;
                 info!("TLS connection established to {}", addr);
                 ConnectionStream::Tls(tls_stream)
             } else {
@@ -245,57 +554,110 @@ impl Connection {
             ConnectionStream::Plain(tcp_stream)
         };
 
+        let (read_half, write_half) = tokio::io::split(stream);
+        let (push_tx, push_rx) = mpsc::unbounded_channel();
+        let shared = Arc::new(Shared {
+            demux: std::sync::Mutex::new(Demux {
+                pushes: Some(push_tx),
+                ..Demux::default()
+            }),
+            protocol_version: AtomicU8::new(PROTOCOL_V2), // Default to v0.2.0
+            max_frame_size: AtomicUsize::new(DEFAULT_MAX_FRAME_SIZE),
+        });
+
         let mut connection = Self {
-            stream: Mutex::new(stream),
+            writer: Mutex::new(write_half),
+            reader: Some(Reader::spawn(read_half, shared.clone())),
+            shared,
+            pushes: Mutex::new(push_rx),
             addr,
+            role: NodeRole::Primary,
+            last_used: Instant::now(),
+            opened_at: Instant::now(),
             next_seq: AtomicU32::new(1),
-            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
-            request_timeout: DEFAULT_REQUEST_TIMEOUT,
-            protocol_version: PROTOCOL_V2, // Default to v0.2.0
+            connect_timeout,
+            request_timeout,
             auth_token: Mutex::new(None),
+            auth_config: std::sync::Mutex::new(None),
+            auth_expires_at: AtomicU64::new(0),
+            auth_refresh_threshold: DEFAULT_AUTH_REFRESH_THRESHOLD,
+            auth_epoch: AtomicU64::new(0),
             tls_config,
+            stats: Arc::default(),
+            checksums: false,
+            database: AtomicU32::new(0),
+            write_buffer: 0,
+            outbox: std::sync::Mutex::default(),
+            #[cfg(feature = "compression")]
+            compression_threshold: None,
         };
 
         // Authenticate if configured
         if let Some(auth_cfg) = auth_config {
-            connection.authenticate(auth_cfg).await?;
+            timeout(connect_timeout, connection.authenticate(auth_cfg))
+                .await
+                .map_err(Error::Timeout)??;
         }
 
         info!("Connected to VedDB server at {}", addr);
         Ok(connection)
     }
 
-    /// Create TLS connector from configuration
-    fn create_tls_connector(tls_config: &TlsConfig) -> Result<TlsConnector> {
-        // Configure client certificates if provided
-        if let (Some(cert_path), Some(key_path)) = (&tls_config.client_cert_path, &tls_config.client_key_path) {
-            // Load client certificate and key
-            // This would require additional implementation for loading PEM files
-            warn!("Client certificate authentication not yet implemented");
-        }
+    /// Authenticate with the server
+    ///
+    /// The credentials are kept so that the connection can re-authenticate
+    /// on its own shortly before the server's token expires, or when the
+    /// server reports that authentication is required.
+    pub async fn authenticate(&mut self, auth_config: AuthConfig) -> Result<()> {
+        self.reauthenticate(auth_config).await?;
+        Ok(())
+    }
 
-        let config = if tls_config.accept_invalid_certs {
-            warn!("Accepting invalid certificates - this should only be used for testing!");
-            ClientConfig::builder()
-                .with_safe_defaults()
-                .with_custom_certificate_verifier(Arc::new(AcceptAllVerifier))
-                .with_no_client_auth()
-        } else {
-            // Use system root certificates
-            let mut root_store = RootCertStore::empty();
-            // In a real implementation, we would load system root certificates here
-            ClientConfig::builder()
-                .with_safe_defaults()
-                .with_root_certificates(root_store)
-                .with_no_client_auth()
-        };
+    /// Authenticate again, possibly with other credentials, returning the
+    /// server's response with the new token and its expiry
+    ///
+    /// Unlike [`authenticate`](Connection::authenticate) this only needs a
+    /// shared reference, so it works on a pooled [`ConnectionGuard`]. The
+    /// credentials replace the ones the connection re-authenticates with on
+    /// its own; other connections in the pool keep theirs.
+    pub async fn reauthenticate(&self, auth_config: AuthConfig) -> Result<AuthResponse> {
+        let response = self.login(&auth_config).await?;
+        *self.auth_config.lock().unwrap_or_else(|e| e.into_inner()) = Some(auth_config);
+        Ok(response)
+    }
 
-        Ok(TlsConnector::from(Arc::new(config)))
+    /// Get the credentials the connection re-authenticates with
+    fn auth_config(&self) -> Option<AuthConfig> {
+        self.auth_config.lock().unwrap_or_else(|e| e.into_inner()).clone()
     }
 
-    /// Authenticate with the server
-    pub async fn authenticate(&mut self, auth_config: AuthConfig) -> Result<()> {
+    /// Set how long before the token expires to re-authenticate
+    pub fn set_auth_refresh_threshold(&mut self, threshold: Duration) {
+        self.auth_refresh_threshold = threshold;
+    }
+
+    /// Send the auth request and store the token it returns
+    async fn login(&self, auth_config: &AuthConfig) -> Result<AuthResponse> {
+        let auth_config = auth_config.clone();
         let credentials = match auth_config.method {
+            AuthMethod::ClientCertificate => {
+                // The TLS handshake has already authenticated us
+                let mutual_tls = self.tls_config.as_ref().is_some_and(|tls| {
+                    tls.enabled && tls.client_cert_path.is_some() && tls.client_key_path.is_some()
+                });
+                if !mutual_tls {
+                    return Err(Error::InvalidArgument(
+                        "Client certificate authentication requires TLS with a client certificate and key".to_string(),
+                    ));
+                }
+                debug!("Authenticated by client certificate");
+                return Ok(AuthResponse {
+                    success: true,
+                    token: None,
+                    expires_at: None,
+                    error: None,
+                });
+            }
             AuthMethod::UsernamePassword => {
                 let username = auth_config.username
                     .ok_or_else(|| Error::InvalidArgument("Username required".to_string()))?;
@@ -325,7 +687,10 @@ impl Connection {
             Bytes::from(payload),
         );
 
-        let response = self.execute(cmd).await?;
+        // Sent directly, so that a failed login isn't itself retried
+        let response = self.send_and_receive(cmd, self.request_timeout).await;
+        self.stats.record(&response);
+        let response = response?;
         
         if !response.is_ok() {
             return Err(Error::AuthenticationFailed);
@@ -341,105 +706,499 @@ impl Connection {
         }
 
         // Store authentication token
-        if let Some(token) = auth_response.token {
-            *self.auth_token.lock().await = Some(token);
+        if let Some(token) = &auth_response.token {
+            *self.auth_token.lock().await = Some(token.clone());
         }
+        self.auth_expires_at
+            .store(auth_response.expires_at.unwrap_or(0), Ordering::SeqCst);
 
         info!("Authentication successful");
-        Ok(())
+        Ok(auth_response)
+    }
+
+    /// Whether the token expires within the refresh threshold
+    fn auth_expiring(&self) -> bool {
+        let expires_at = self.auth_expires_at.load(Ordering::SeqCst);
+        if expires_at == 0 || self.auth_config().is_none() {
+            return false;
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        now + self.auth_refresh_threshold >= Duration::from_secs(expires_at)
+    }
+
+    /// Re-authenticate with the stored credentials
+    async fn refresh_auth(&self) -> Result<()> {
+        match self.auth_config() {
+            Some(auth_config) => {
+                debug!("Re-authenticating connection to {}", self.addr);
+                self.login(&auth_config).await?;
+                Ok(())
+            }
+            None => Err(Error::AuthenticationFailed),
+        }
     }
 
     /// Get the next sequence number
+    ///
+    /// The counter wraps at `u32::MAX` and skips 0, which servers treat as
+    /// unset. Sequence numbers still in flight are skipped when a request is
+    /// registered, see [`Connection::register`].
     fn next_seq(&self) -> u32 {
-        self.next_seq.fetch_add(1, Ordering::SeqCst)
+        loop {
+            let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+            if seq != 0 {
+                return seq;
+            }
+        }
+    }
+
+    /// Start waiting for the response to `cmd`, moving it to a fresh
+    /// sequence number if its own is still in use by an outstanding request
+    fn register(&self, cmd: &mut Command) -> Result<PendingResponse<'_>> {
+        loop {
+            if let Some(pending) = PendingResponse::register(&self.shared, cmd.header.seq)? {
+                return Ok(pending);
+            }
+            debug!("Sequence number {} is still in flight, skipping it", cmd.header.seq);
+            cmd.header.seq = self.next_seq();
+        }
+    }
+
+    /// Check, without waiting on the network, whether the connection is
+    /// still open
+    ///
+    /// The connection's reader notices as soon as the server closes the
+    /// socket (idle timeout, restart), so this detects a dead connection
+    /// without sending anything.
+    pub async fn is_alive(&self) -> bool {
+        self.shared.demux().closed.is_none()
+    }
+
+    /// Close the connection cleanly
+    ///
+    /// Pending writes are flushed and the stream is shut down, so the server
+    /// sees an orderly close rather than a reset.
+    pub async fn close(mut self) -> Result<()> {
+        let writer = self.writer.get_mut();
+        timeout(self.request_timeout, async {
+            writer.flush().await?;
+            writer.shutdown().await
+        })
+        .await
+        .map_err(Error::Timeout)??;
+        debug!("Closed connection to {}", self.addr);
+        Ok(())
     }
 
     /// Set protocol version (for compatibility with v0.1.x servers)
     pub fn set_protocol_version(&mut self, version: u8) {
-        self.protocol_version = version;
+        self.shared.protocol_version.store(version, Ordering::SeqCst);
     }
 
-    /// Execute a command and return the response
-    pub async fn execute(&self, mut cmd: Command) -> Result<Response> {
-        // Set protocol version on command header
-        cmd.header.version = self.protocol_version;
-        
-        let seq = cmd.header.seq;
-        debug!("Executing command: {:?} (seq={}, protocol={})", 
-               cmd.header.opcode, seq, cmd.header.version);
+    /// Get the protocol version used for requests
+    pub fn protocol_version(&self) -> u8 {
+        self.shared.protocol_version.load(Ordering::SeqCst)
+    }
+
+    /// Append a CRC32 checksum to every command, to tell corrupted bytes on
+    /// the wire apart from framing bugs
+    ///
+    /// Responses carrying a checksum are always verified; a mismatch fails
+    /// the request with a protocol error noting the checksum mismatch.
+    pub fn set_checksums(&mut self, enabled: bool) {
+        self.checksums = enabled;
+    }
 
-        let mut stream = self.stream.lock().await;
+    /// Coalesce concurrent writes of up to `size` bytes (0, the default,
+    /// writes every frame on its own)
+    ///
+    /// When several tasks send on one connection at once, frames queue up
+    /// while another task is writing and then go out together in a single
+    /// write and flush, saving syscalls. A frame is always written before its
+    /// response is awaited, so latency isn't affected; frames that don't fit
+    /// in the buffer are written directly.
+    pub fn set_write_buffer(&mut self, size: usize) {
+        self.write_buffer = size;
+    }
 
-        // Send the command
-        let cmd_bytes = cmd.to_bytes();
-        debug!("Sending command: {} bytes", cmd_bytes.len());
+    /// Switch this connection to logical database `db`
+    ///
+    /// Every later command on the connection runs against `db`. Connections
+    /// start on database 0.
+    pub async fn select(&self, db: u32) -> Result<()> {
+        let seq = self.next_seq();
+        let payload = serde_json::to_vec(&SelectRequest { db })
+            .map_err(|e| Error::Serialization(format!("Failed to serialize select request: {}", e)))?;
+
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::Select, seq),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+
+        let response = self.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse select response: {}", e)))?;
+
+        if !op_response.success {
+            let error_msg = op_response.error.unwrap_or_else(|| "Select failed".to_string());
+            return Err(Error::Server(error_msg));
+        }
+
+        self.database.store(db, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Get the logical database this connection is on
+    pub fn database(&self) -> u32 {
+        self.database.load(Ordering::SeqCst)
+    }
+
+    /// Get whether the connection is to the primary or a replica
+    ///
+    /// Connections opened directly are assumed to be to the primary; pools
+    /// of replica connections are set up by [`ClientBuilder::replica`].
+    pub fn role(&self) -> NodeRole {
+        self.role
+    }
+
+    /// Ask the server to compress payloads, returning whether it agreed
+    ///
+    /// A ping is sent with the `COMPRESSED` command flag set; a server that
+    /// supports compression echoes the flag in its reply.
+    /// From then on, command values of at least `threshold` bytes are sent
+    /// zstd-compressed and compressed responses are decompressed. Servers
+    /// without compression support are left uncompressed.
+    #[cfg(feature = "compression")]
+    pub async fn negotiate_compression(&mut self, threshold: usize) -> Result<bool> {
+        let seq = self.next_seq();
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::Ping, seq)
+                .with_flag(crate::types::flags::COMPRESSED),
+            Bytes::new(),
+            Bytes::new(),
+        );
+        let response = self.execute(cmd).await?;
+        let enabled = response.header.flags & crate::types::response_flags::COMPRESSED != 0;
+        self.compression_threshold = enabled.then_some(threshold);
+        Ok(enabled)
+    }
+
+    /// Detect the server's protocol version and use it for later requests
+    ///
+    /// v0.1.x and v0.2.0 response headers share their first 12 bytes but are
+    /// 20 and 16 bytes long, so two pings are sent back-to-back and the first
+    /// response is read with v0.2.0 framing. On a v0.2.0 server the next
+    /// bytes are then the second response's header; on a v0.1.x server they
+    /// are the tail of the first response.
+    ///
+    /// The connection's reader is paused while detecting, so this should be
+    /// called before subscribing.
+    pub async fn detect_protocol_version(&mut self) -> Result<u8> {
+        let mut stream = self.stop_reader().await?;
+        match self.detect_with(&mut stream).await {
+            Ok(version) => {
+                debug!("Detected protocol version {}", version);
+                self.set_protocol_version(version);
+                self.reader = Some(Reader::spawn(stream, self.shared.clone()));
+                Ok(version)
+            }
+            Err(e) => {
+                // The stream may be mid-frame, so it can't be read again
+                self.shared.fail(None, Error::Protocol(format!("Protocol detection failed: {}", e)));
+                Err(e)
+            }
+        }
+    }
 
-        timeout(self.request_timeout, stream.write_all(&cmd_bytes))
+    /// Stop the reader and take back the read half of the stream
+    async fn stop_reader(&mut self) -> Result<ReadHalf<ConnectionStream>> {
+        let Some(reader) = self.reader.take() else {
+            return Err(Error::NotConnected);
+        };
+        let _ = reader.stop.send(());
+        let stream = reader
+            .task
             .await
-            .map_err(Error::Timeout)??;
-        
-        timeout(self.request_timeout, stream.flush())
+            .map_err(|e| Error::Connection(format!("Connection reader failed: {}", e)))?;
+        if let Some(reason) = &self.shared.demux().closed {
+            return Err(Error::Connection(reason.clone()));
+        }
+        Ok(stream)
+    }
+
+    /// Send two pings and infer the framing from their responses
+    async fn detect_with(&mut self, stream: &mut ReadHalf<ConnectionStream>) -> Result<u8> {
+        let first_seq = self.next_seq();
+        let second_seq = self.next_seq();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(&Command::ping(first_seq).to_bytes());
+        buf.extend_from_slice(&Command::ping(second_seq).to_bytes());
+
+        let limit = self.request_timeout;
+        let max = self.max_frame_size();
+        let writer = self.writer.get_mut();
+        timeout(limit, writer.write_all(&buf))
             .await
             .map_err(Error::Timeout)??;
-
-        // Read the response header (16 bytes for v0.2.0, 20 bytes for v0.1.x)
-        let header_size = if self.protocol_version == PROTOCOL_V2 { 16 } else { 20 };
-        let mut header_buf = vec![0u8; header_size];
-        timeout(self.request_timeout, stream.read_exact(&mut header_buf))
+        timeout(limit, writer.flush())
             .await
             .map_err(Error::Timeout)??;
 
-        // Parse the header based on protocol version
-        let payload_len = if self.protocol_version == PROTOCOL_V2 {
-            // v0.2.0 format: 16-byte header
-            u32::from_le_bytes([header_buf[8], header_buf[9], header_buf[10], header_buf[11]])
+        // First response, assuming a 16-byte header
+        let mut head = [0u8; 12];
+        read_with_timeout(limit, stream.read_exact(&mut head)).await?;
+        if u32_at(&head, 4) != first_seq {
+            return Err(Error::SequenceMismatch {
+                expected: first_seq,
+                received: u32_at(&head, 4),
+                opcode: crate::types::OpCode::Ping as u8,
+            });
+        }
+        let mut rest = vec![0u8; 4 + frame_len(u32_at(&head, 8), max)?];
+        read_with_timeout(limit, stream.read_exact(&mut rest)).await?;
+
+        // Second response: its sequence number is where v0.2.0 framing puts
+        // it only if that framing was right
+        read_with_timeout(limit, stream.read_exact(&mut head)).await?;
+        let (version, tail) = if u32_at(&head, 4) == second_seq {
+            (PROTOCOL_V2, 4 + frame_len(u32_at(&head, 8), max)?)
         } else {
-            // v0.1.x format: 20-byte header
-            u32::from_le_bytes([header_buf[8], header_buf[9], header_buf[10], header_buf[11]])
+            // The first 4 bytes were left over from the first frame; the
+            // second frame's sequence number and payload length follow
+            let mut len = [0u8; 4];
+            read_with_timeout(limit, stream.read_exact(&mut len)).await?;
+            if u32_at(&head, 8) != second_seq {
+                return Err(Error::Protocol(
+                    "Unrecognized response framing while detecting protocol version".to_string(),
+                ));
+            }
+            (PROTOCOL_V1, 8 + frame_len(u32::from_le_bytes(len), max)?)
         };
+        let mut rest = vec![0u8; tail];
+        read_with_timeout(limit, stream.read_exact(&mut rest)).await?;
+        Ok(version)
+    }
 
-        if payload_len as usize > MAX_FRAME_SIZE {
-            return Err(Error::Protocol(format!(
-                "Response too large: {} bytes (max: {})",
-                payload_len, MAX_FRAME_SIZE
-            )));
+    /// Set the timeout applied to each write and read of a request
+    pub fn set_request_timeout(&mut self, request_timeout: Duration) {
+        self.request_timeout = request_timeout;
+    }
+
+    /// Set the largest request or response frame this connection accepts
+    /// (default 16MB)
+    ///
+    /// Values above 256MB are capped at 256MB.
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.shared
+            .max_frame_size
+            .store(max_frame_size.min(MAX_FRAME_SIZE_LIMIT), Ordering::SeqCst);
+    }
+
+    /// Get the largest request or response frame this connection accepts
+    pub fn max_frame_size(&self) -> usize {
+        self.shared.max_frame_size.load(Ordering::SeqCst)
+    }
+
+    /// Execute a command and return the response
+    pub async fn execute(&self, cmd: Command) -> Result<Response> {
+        self.execute_with_timeout(cmd, self.request_timeout).await
+    }
+
+    /// Execute a command, bounding each write and read by `limit` instead of
+    /// the connection's request timeout
+    ///
+    /// If the response doesn't arrive in time this returns [`Error::Timeout`];
+    /// the late response is discarded by the next request on this connection,
+    /// so the connection stays usable.
+    ///
+    /// The returned future is cancellation-safe: dropping it while waiting
+    /// for the response is handled like a timeout. Dropping it, or timing
+    /// out, partway through writing the command closes the connection, since
+    /// the server would misread whatever followed; a pool replaces it on the
+    /// next checkout.
+    ///
+    /// Each call runs in a `veddb.execute` debug span carrying the `opcode`,
+    /// `seq` and `bytes_sent`, with `bytes_received` and `elapsed_us`
+    /// recorded on completion.
+    pub async fn execute_with_timeout(&self, cmd: Command, limit: Duration) -> Result<Response> {
+        let span = tracing::debug_span!(
+            "veddb.execute",
+            opcode = cmd.header.opcode,
+            seq = cmd.header.seq,
+            bytes_sent = cmd.encoded_len(),
+            bytes_received = tracing::field::Empty,
+            elapsed_us = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing-subscriber")]
+        self.stats.record_opcode(cmd.header.opcode);
+
+        let started = Instant::now();
+        let result = self.send_authenticated(cmd, limit).instrument(span.clone()).await;
+        if let Ok(response) = &result {
+            let header_len = ResponseHeader::size_for(self.protocol_version()).unwrap_or(ResponseHeader::SIZE);
+            span.record("bytes_received", header_len + response.payload.len());
+        }
+        span.record("elapsed_us", started.elapsed().as_micros() as u64);
+
+        self.stats.record(&result);
+        result
+    }
+
+    /// Send a command, re-authenticating first if the token is about to
+    /// expire
+    async fn send_authenticated(&self, cmd: Command, limit: Duration) -> Result<Response> {
+        if self.auth_expiring() {
+            self.refresh_auth().await?;
+        }
+
+        let result = self.send_and_receive(cmd.clone(), limit).await;
+        // The token may have lapsed or been revoked early: log in again and
+        // retry once
+        if matches!(result, Err(Error::AuthenticationFailed | Error::AuthenticationRequired))
+            && self.auth_config().is_some()
+        {
+            self.refresh_auth().await?;
+            return self.send_and_receive(cmd, limit).await;
+        }
+        result
+    }
+
+    /// Write a command and wait for the reader to route its response back
+    async fn send_and_receive(&self, mut cmd: Command, limit: Duration) -> Result<Response> {
+        // Set protocol version on command header
+        cmd.header.version = self.protocol_version();
+        #[cfg(feature = "compression")]
+        self.compress(&mut cmd)?;
+        if self.checksums {
+            cmd.header.flags |= crate::types::flags::CHECKSUM;
+        }
+        
+        // Fail fast rather than sending a frame the server will reject
+        check_request_size(&cmd, self.max_frame_size())?;
+
+        // Register before writing so a fast response can't be missed; a
+        // response arriving after a timeout is discarded by the reader
+        let pending = self.register(&mut cmd)?;
+        debug!("Executing command: {:?} (seq={}, protocol={})", 
+               cmd.header.opcode, cmd.header.seq, cmd.header.version);
+        let cmd_bytes = cmd.to_bytes();
+        debug!("Sending command: {} bytes", cmd_bytes.len());
+        self.write(&cmd_bytes, limit).await?;
+
+        // Check for server errors
+        check_status(pending.wait(limit).await?)
+    }
+
+    /// Compress the command's value if compression was negotiated
+    #[cfg(feature = "compression")]
+    fn compress(&self, cmd: &mut Command) -> Result<()> {
+        match self.compression_threshold {
+            Some(threshold) => crate::compression::compress_command(cmd, threshold),
+            None => Ok(()),
         }
+    }
 
-        // Read the payload
-        let mut payload = vec![0u8; payload_len as usize];
-        if payload_len > 0 {
-            timeout(self.request_timeout, stream.read_exact(&mut payload))
+    /// Write an encoded frame, or several back-to-back
+    async fn write(&self, bytes: &[u8], limit: Duration) -> Result<()> {
+        // Queue the frame so that whoever holds the writer next sends it
+        // along with any others queued meanwhile. Frames are matched to
+        // responses by sequence number, so their order doesn't matter.
+        let queued = self.write_buffer > 0 && {
+            let mut outbox = self.outbox.lock().unwrap_or_else(|e| e.into_inner());
+            let fits = outbox.len() + bytes.len() <= self.write_buffer;
+            if fits {
+                outbox.extend_from_slice(bytes);
+            }
+            fits
+        };
+
+        let mut writer = self.writer.lock().await;
+        let batch = self.outbox.lock().unwrap_or_else(|e| e.into_inner()).split();
+        if batch.is_empty() && queued {
+            // Another task already sent this frame
+            return Ok(());
+        }
+        let mut write = PartialWrite::new(&self.shared);
+        if !batch.is_empty() {
+            timeout(limit, writer.write_all(&batch))
                 .await
                 .map_err(Error::Timeout)??;
         }
+        if !queued {
+            timeout(limit, writer.write_all(bytes))
+                .await
+                .map_err(Error::Timeout)??;
+        }
+        timeout(limit, writer.flush())
+            .await
+            .map_err(Error::Timeout)??;
+        write.complete();
+        Ok(())
+    }
 
-        // Combine header and payload for parsing
-        let mut response_bytes = Vec::with_capacity(header_size + payload_len as usize);
-        response_bytes.extend_from_slice(&header_buf);
-        response_bytes.extend_from_slice(&payload);
-
-        let response = Response::from_bytes(&response_bytes)
-            .map_err(|e| Error::Protocol(format!("Invalid response: {}", e)))?;
+    /// Send several commands back-to-back and then read all of their
+    /// responses, so the batch costs a single round trip
+    ///
+    /// Each command is given a fresh sequence number, so commands can be
+    /// built with any `seq`. Responses are matched to commands by sequence
+    /// number and returned in command order. A non-OK status fails only that
+    /// command's entry; an outer error means the connection itself failed.
+    pub async fn pipeline(&self, cmds: Vec<Command>) -> Result<Vec<Result<Response>>> {
+        let result = self.send_and_receive_many(cmds).await;
+        match &result {
+            Ok(results) => results.iter().for_each(|r| self.stats.record(r)),
+            Err(_) => self.stats.record(&result),
+        }
+        result
+    }
 
-        // Verify sequence number
-        if response.header.seq != seq {
-            return Err(Error::Protocol(format!(
-                "Sequence number mismatch: expected {}, got {}",
-                seq, response.header.seq
-            )));
+    /// Write a batch of commands and wait for all of their responses
+    async fn send_and_receive_many(&self, cmds: Vec<Command>) -> Result<Vec<Result<Response>>> {
+        let mut pending = Vec::with_capacity(cmds.len());
+        let mut buf = BytesMut::new();
+        for mut cmd in cmds {
+            cmd.header.version = self.protocol_version();
+            cmd.header.seq = self.next_seq();
+            #[cfg(feature = "compression")]
+            self.compress(&mut cmd)?;
+            if self.checksums {
+                cmd.header.flags |= crate::types::flags::CHECKSUM;
+            }
+            check_request_size(&cmd, self.max_frame_size())?;
+            pending.push(self.register(&mut cmd)?);
+            buf.extend_from_slice(&cmd.to_bytes());
         }
+        debug!("Sending {} commands in one batch ({} bytes)", pending.len(), buf.len());
 
-        // Check for server errors
-        if !response.is_ok() {
-            let status = response.status();
-            let error_msg = String::from_utf8_lossy(&response.payload).into_owned();
-            return Err(Error::Server(format!(
-                "Server error: {:?}: {}",
-                status, error_msg
-            )));
+        self.write(&buf, self.request_timeout).await?;
+
+        // Responses may arrive in any order; the reader routes each by seq
+        let deadline = tokio::time::Instant::now() + self.request_timeout;
+        let mut results = Vec::with_capacity(pending.len());
+        for response in pending {
+            let response = tokio::time::timeout_at(deadline, response.wait_forever())
+                .await
+                .map_err(Error::Timeout)??;
+            results.push(check_status(response));
         }
+        Ok(results)
+    }
 
-        Ok(response)
+    /// Wait for the next message pushed by the server on a subscribed connection
+    pub(crate) async fn read_push(&self) -> Result<Response> {
+        match self.pushes.lock().await.recv().await {
+            Some(response) => Ok(response),
+            None => Err(Error::Connection(
+                self.shared
+                    .demux()
+                    .closed
+                    .clone()
+                    .unwrap_or_else(|| "Connection closed".to_string()),
+            )),
+        }
     }
 
     /// Ping the server
@@ -462,46 +1221,324 @@ impl Connection {
         Ok(())
     }
 
-    /// Get a value by key
+    /// Get a value by key, failing with [`Error::KeyNotFound`] if it doesn't exist
     pub async fn get<K>(&self, key: K) -> Result<Bytes>
+    where
+        K: Into<Bytes>,
+    {
+        self.get_opt(key).await?.ok_or(Error::KeyNotFound)
+    }
+
+    /// Get a value by key, or `None` if the key doesn't exist
+    pub async fn get_opt<K>(&self, key: K) -> Result<Option<Bytes>>
     where
         K: Into<Bytes>,
     {
         let seq = self.next_seq();
         let cmd = Command::get(seq, key);
-        let response = self.execute(cmd).await?;
-        Ok(response.payload)
+        let value = match self.execute(cmd).await {
+            Ok(response) => Some(response.payload),
+            Err(Error::KeyNotFound) => None,
+            Err(e) => return Err(e),
+        };
+        self.stats.record_lookup(value.is_some());
+        Ok(value)
     }
 
-    /// Delete a key
-    pub async fn delete<K>(&self, key: K) -> Result<()>
+    /// Get a value by key along with its version, for a following
+    /// [`cas`](Connection::cas)
+    ///
+    /// The version travels in the response header's `extra` field, which
+    /// only v0.1.x framing has room for, so key versions are unavailable
+    /// on a v0.2.0 connection and this fails with [`Error::NotSupported`].
+    pub async fn get_versioned<K>(&self, key: K) -> Result<(Bytes, u64)>
     where
         K: Into<Bytes>,
     {
+        if self.protocol_version() != PROTOCOL_V1 {
+            return Err(Error::NotSupported);
+        }
         let seq = self.next_seq();
-        let cmd = Command::delete(seq, key);
-        self.execute(cmd).await?;
-        Ok(())
+        match self.execute(Command::get(seq, key)).await {
+            Ok(response) => {
+                self.stats.record_lookup(true);
+                Ok((response.payload, response.header.extra))
+            }
+            Err(Error::KeyNotFound) => {
+                self.stats.record_lookup(false);
+                Err(Error::KeyNotFound)
+            }
+            Err(e) => Err(e),
+        }
     }
 
-    /// Compare and swap a value
-    pub async fn cas<K, V>(&self, key: K, expected_version: u64, value: V) -> Result<()>
+    /// Check whether a key exists without transferring its value
+    pub async fn exists<K>(&self, key: K) -> Result<bool>
     where
         K: Into<Bytes>,
-        V: Into<Bytes>,
     {
         let seq = self.next_seq();
-        let cmd = Command::cas(seq, key, expected_version, value);
-        self.execute(cmd).await?;
-        Ok(())
+        match self.execute(Command::exists(seq, key)).await {
+            Ok(_) => Ok(true),
+            Err(Error::KeyNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
-    // ============================================================================
-    // v0.2.0 Document Operations
-    // ============================================================================
-
+    /// Check several keys for existence in a single round trip, returning
+    /// the results in key order
+    pub async fn exists_many<K>(&self, keys: &[K]) -> Result<Vec<bool>>
+    where
+        K: Clone + Into<Bytes>,
+    {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let cmds = keys.iter().map(|key| Command::exists(0, key.clone())).collect();
+        self.pipeline(cmds)
+            .await?
+            .into_iter()
+            .map(|result| match result {
+                Ok(_) => Ok(true),
+                Err(Error::KeyNotFound) => Ok(false),
+                Err(e) => Err(e),
+            })
+            .collect()
+    }
+
+    /// Get several keys in a single round trip
+    ///
+    /// Values are returned in key order, with `None` for keys that don't
+    /// exist. Any other failure fails the whole call.
+    pub async fn mget<K>(&self, keys: &[K]) -> Result<Vec<Option<Bytes>>>
+    where
+        K: Clone + Into<Bytes>,
+    {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let cmds = keys.iter().map(|key| Command::get(0, key.clone())).collect();
+        let mut values = Vec::with_capacity(keys.len());
+        for result in self.pipeline(cmds).await? {
+            let value = match result {
+                Ok(response) => Some(response.payload),
+                Err(Error::KeyNotFound) => None,
+                Err(e) => return Err(e),
+            };
+            self.stats.record_lookup(value.is_some());
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// Set several key-value pairs in a single round trip
+    ///
+    /// The writes are independent: if one fails, the others may still have
+    /// been applied.
+    pub async fn mset<K, V>(&self, pairs: &[(K, V)]) -> Result<()>
+    where
+        K: Clone + Into<Bytes>,
+        V: Clone + Into<Bytes>,
+    {
+        if pairs.is_empty() {
+            return Ok(());
+        }
+        let cmds = pairs
+            .iter()
+            .map(|(key, value)| Command::set(0, key.clone(), value.clone()))
+            .collect();
+        for result in self.pipeline(cmds).await? {
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Delete a key
+    pub async fn delete<K>(&self, key: K) -> Result<()>
+    where
+        K: Into<Bytes>,
+    {
+        let seq = self.next_seq();
+        let cmd = Command::delete(seq, key);
+        self.execute(cmd).await?;
+        Ok(())
+    }
+
+    /// Set a key-value pair that expires after `ttl`
+    ///
+    /// A zero `ttl` deletes the key instead.
+    pub async fn set_with_ttl<K, V>(&self, key: K, value: V, ttl: Duration) -> Result<()>
+    where
+        K: Into<Bytes>,
+        V: Into<Bytes>,
+    {
+        if ttl.is_zero() {
+            return match self.delete(key).await {
+                Ok(()) | Err(Error::KeyNotFound) => Ok(()),
+                Err(e) => Err(e),
+            };
+        }
+
+        let seq = self.next_seq();
+        let cmd = Command::set_with_ttl(seq, key, value, ttl);
+        self.execute(cmd).await?;
+        Ok(())
+    }
+
+    /// Make an existing key expire after `ttl`, returning `false` if the key
+    /// doesn't exist
+    ///
+    /// A zero `ttl` deletes the key immediately.
+    pub async fn expire<K>(&self, key: K, ttl: Duration) -> Result<bool>
+    where
+        K: Into<Bytes>,
+    {
+        let result = if ttl.is_zero() {
+            self.delete(key).await
+        } else {
+            let seq = self.next_seq();
+            self.execute(Command::expire(seq, key, ttl)).await.map(|_| ())
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(Error::KeyNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get how long a key has left before it expires, or `None` if it has
+    /// no expiry
+    ///
+    /// Fails with [`Error::KeyNotFound`] if the key doesn't exist.
+    pub async fn ttl<K>(&self, key: K) -> Result<Option<Duration>>
+    where
+        K: Into<Bytes>,
+    {
+        let seq = self.next_seq();
+        let response = self.execute(Command::ttl(seq, key)).await?;
+        match response.payload.len() {
+            0 => Ok(None),
+            8 => {
+                let mut millis = [0u8; 8];
+                millis.copy_from_slice(&response.payload);
+                Ok(Some(Duration::from_millis(u64::from_le_bytes(millis))))
+            }
+            len => Err(Error::InvalidResponse(format!(
+                "Expected an 8-byte TTL, got {} bytes",
+                len
+            ))),
+        }
+    }
+
+    /// Atomically add `delta` to the integer stored at a key, returning the
+    /// new value
+    ///
+    /// A missing key counts as 0. Fails with [`Error::WrongType`] if the key
+    /// holds a non-numeric value, which is left unchanged.
+    pub async fn incr_by<K>(&self, key: K, delta: i64) -> Result<i64>
+    where
+        K: Into<Bytes>,
+    {
+        let seq = self.next_seq();
+        let response = self.execute(Command::incr(seq, key, delta)).await?;
+        let value: [u8; 8] = response.payload[..].try_into().map_err(|_| {
+            Error::InvalidResponse(format!(
+                "Expected an 8-byte counter, got {} bytes",
+                response.payload.len()
+            ))
+        })?;
+        Ok(i64::from_le_bytes(value))
+    }
+
+    /// Compare and swap a value
+    pub async fn cas<K, V>(&self, key: K, expected_version: u64, value: V) -> Result<()>
+    where
+        K: Into<Bytes>,
+        V: Into<Bytes>,
+    {
+        let seq = self.next_seq();
+        let cmd = Command::cas(seq, key, expected_version, value);
+        self.execute(cmd).await?;
+        Ok(())
+    }
+
+    /// Refresh the LRU recency and idle time of existing keys without
+    /// reading them, returning how many of the keys existed
+    pub async fn touch<K>(&self, keys: &[K]) -> Result<u64>
+    where
+        K: Clone + Into<Bytes>,
+    {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        let keys = keys
+            .iter()
+            .map(|key| {
+                String::from_utf8(key.clone().into().to_vec())
+                    .map_err(|_| Error::InvalidArgument("Touched keys must be valid UTF-8".to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let request = TouchRequest { keys };
+
+        let seq = self.next_seq();
+        let payload = serde_json::to_vec(&request)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize touch request: {}", e)))?;
+
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::Touch, seq),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+
+        let response = self.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse touch response: {}", e)))?;
+
+        if !op_response.success {
+            let error_msg = op_response.error.unwrap_or_else(|| "Touch failed".to_string());
+            return Err(Error::Server(error_msg));
+        }
+
+        Ok(op_response.affected_count.unwrap_or(0))
+    }
+
+    /// Fetch one page of a keyspace scan, returning the next cursor (0 when
+    /// the scan is complete) and the keys in this page
+    pub async fn scan(&self, request: &ScanRequest) -> Result<(u64, Vec<String>)> {
+        let seq = self.next_seq();
+        let payload = serde_json::to_vec(request)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize scan request: {}", e)))?;
+
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::Scan, seq),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+
+        let response = self.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse scan response: {}", e)))?;
+
+        if !op_response.success {
+            let error_msg = op_response.error.unwrap_or_else(|| "Scan failed".to_string());
+            return Err(Error::Server(error_msg));
+        }
+
+        scan_page_from_value(op_response.data.unwrap_or(Value::Null))
+    }
+
+    // ============================================================================
+    // v0.2.0 Document Operations
+    // ============================================================================
+
     /// Query documents in a collection
     pub async fn query(&self, request: QueryRequest) -> Result<Vec<Document>> {
+        self.query_with_timeout(request, self.request_timeout).await
+    }
+
+    /// Query documents in a collection, with a timeout for this call only
+    pub async fn query_with_timeout(&self, request: QueryRequest, limit: Duration) -> Result<Vec<Document>> {
         let seq = self.next_seq();
         let payload = serde_json::to_vec(&request)
             .map_err(|e| Error::Serialization(format!("Failed to serialize query: {}", e)))?;
@@ -512,7 +1549,7 @@ impl Connection {
             Bytes::from(payload),
         );
 
-        let response = self.execute(cmd).await?;
+        let response = self.execute_with_timeout(cmd, limit).await?;
         let op_response: OperationResponse = serde_json::from_slice(&response.payload)
             .map_err(|e| Error::Serialization(format!("Failed to parse query response: {}", e)))?;
 
@@ -527,10 +1564,8 @@ impl Connection {
                 let mut documents = Vec::new();
                 for doc_value in docs {
                     if let Value::Object(obj) = doc_value {
-                        // Convert object to Document
-                        let doc_json = serde_json::to_value(obj)
-                            .map_err(|e| Error::Serialization(format!("Failed to convert document: {}", e)))?;
-                        let document: Document = serde_json::from_value(doc_json)
+                        // Documents come back as objects with a string `_id`
+                        let document = Document::try_from(Value::Object(obj))
                             .map_err(|e| Error::Serialization(format!("Failed to parse document: {}", e)))?;
                         documents.push(document);
                     }
@@ -541,8 +1576,42 @@ impl Connection {
         }
     }
 
-    /// Insert a document into a collection
-    pub async fn insert_document(&self, collection: &str, document: Document) -> Result<()> {
+    /// Get the distinct values of `field` across the documents matching a
+    /// filter, deduplicated server-side and sorted by [`Value::total_cmp`]
+    pub async fn distinct(&self, request: DistinctRequest) -> Result<Vec<Value>> {
+        let seq = self.next_seq();
+        let payload = serde_json::to_vec(&request)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize distinct request: {}", e)))?;
+
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::Distinct, seq),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+
+        let response = self.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse distinct response: {}", e)))?;
+
+        if !op_response.success {
+            let error_msg = op_response.error.unwrap_or_else(|| "Distinct failed".to_string());
+            return Err(Error::Server(error_msg));
+        }
+
+        let mut values = match op_response.data {
+            Some(data) => array_from_value(data, "distinct")?,
+            None => Vec::new(),
+        };
+        values.sort_by(Value::total_cmp);
+        values.dedup_by(|a, b| a.total_cmp(b).is_eq());
+        Ok(values)
+    }
+
+    /// Insert a document into a collection, returning its id
+    ///
+    /// This is the document's own id, unless the server assigned another.
+    pub async fn insert_document(&self, collection: &str, document: Document) -> Result<DocumentId> {
+        let id = document.id;
         let request = InsertDocRequest {
             collection: collection.to_string(),
             document,
@@ -567,11 +1636,170 @@ impl Connection {
             return Err(Error::Server(error_msg));
         }
 
-        Ok(())
+        // Servers that assign their own id report it back
+        match op_response.data.as_ref().and_then(|data| data.as_object()).and_then(|data| data.get("_id")) {
+            Some(assigned) => document_id_from_value(assigned),
+            None => Ok(id),
+        }
+    }
+
+    /// Insert several documents into a collection in a single round trip
+    ///
+    /// Returns the ids of the inserted documents in input order, as assigned
+    /// by the server if it reports them. Documents are inserted in order; if
+    /// the server rejects one, [`Error::BulkInsert`] reports how many were
+    /// inserted and the index of the rejected document.
+    pub async fn insert_many(&self, collection: &str, documents: Vec<Document>) -> Result<Vec<DocumentId>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<DocumentId> = documents.iter().map(|doc| doc.id).collect();
+        let request = InsertManyRequest {
+            collection: collection.to_string(),
+            documents,
+        };
+
+        let seq = self.next_seq();
+        let payload = serde_json::to_vec(&request)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize insert many request: {}", e)))?;
+        
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::InsertMany, seq),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+
+        let response = self.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse insert many response: {}", e)))?;
+
+        if !op_response.success {
+            let inserted = op_response.affected_count.unwrap_or(0) as usize;
+            // The server may report the failing index explicitly; otherwise
+            // ordered insertion means it is the first document not inserted
+            let failed_index = op_response.data
+                .as_ref()
+                .and_then(|data| data.as_object())
+                .and_then(|obj| obj.get("failed_index"))
+                .and_then(|v| v.as_usize())
+                .unwrap_or(inserted);
+            return Err(Error::BulkInsert {
+                inserted,
+                failed_index,
+                message: op_response.error.unwrap_or_else(|| "Insert many failed".to_string()),
+            });
+        }
+
+        // Servers that assign their own ids report them back, one per document
+        match op_response.data.as_ref().and_then(|data| data.as_object()).and_then(|data| data.get("_ids")) {
+            Some(Value::Array(assigned)) if assigned.len() == ids.len() => {
+                assigned.iter().map(document_id_from_value).collect()
+            }
+            Some(other) => Err(Error::InvalidResponse(format!("Invalid inserted ids: {:?}", other))),
+            None => Ok(ids),
+        }
+    }
+
+    /// Apply a batch of writes atomically, returning the number of documents
+    /// each operation affected, in order
+    pub async fn commit_transaction(&self, request: TransactionRequest) -> Result<Vec<u64>> {
+        let operations = request.operations.len();
+        let seq = self.next_seq();
+        let payload = serde_json::to_vec(&request)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize transaction: {}", e)))?;
+
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::Transaction, seq),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+
+        let response = self.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse transaction response: {}", e)))?;
+
+        if !op_response.success {
+            let error_msg = op_response.error.unwrap_or_else(|| "Transaction aborted".to_string());
+            return Err(Error::Server(error_msg));
+        }
+
+        let counts = array_from_value(op_response.data.unwrap_or(Value::Null), "transaction")?
+            .into_iter()
+            .map(|count| count_from_value(count, "transaction"))
+            .collect::<Result<Vec<_>>>()?;
+        if counts.len() != operations {
+            return Err(Error::InvalidResponse(format!(
+                "Transaction returned {} results for {} operations",
+                counts.len(),
+                operations
+            )));
+        }
+        Ok(counts)
+    }
+
+    /// Apply mixed inserts, updates and deletes to a collection in one
+    /// round trip
+    ///
+    /// With `ordered` set the writes are applied in order, stopping at the
+    /// first failure; otherwise every write is attempted. If any write fails,
+    /// [`Error::BulkWrite`] reports the first failure along with the outcomes
+    /// of the writes that were attempted.
+    pub async fn bulk_write(&self, collection: &str, operations: Vec<WriteOp>, ordered: bool) -> Result<BulkWriteResult> {
+        if operations.is_empty() {
+            return Ok(BulkWriteResult::default());
+        }
+
+        let request = BulkWriteRequest {
+            collection: collection.to_string(),
+            operations,
+            ordered,
+        };
+        let seq = self.next_seq();
+        let payload = serde_json::to_vec(&request)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize bulk write request: {}", e)))?;
+
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::BulkWrite, seq),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+
+        let response = self.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse bulk write response: {}", e)))?;
+        let result = BulkWriteResult::from_response(&request.operations, op_response.data)?;
+
+        if let Some((failed_index, message)) = result.first_failure() {
+            return Err(Error::BulkWrite {
+                failed_index,
+                message: message.to_string(),
+                result: result.clone(),
+            });
+        }
+        if !op_response.success {
+            // The first write without an outcome is the one that failed
+            return Err(Error::BulkWrite {
+                failed_index: result.results.len(),
+                message: op_response.error.unwrap_or_else(|| "Bulk write failed".to_string()),
+                result,
+            });
+        }
+        if result.results.len() != request.operations.len() {
+            return Err(Error::InvalidResponse(format!(
+                "Bulk write returned {} results for {} operations",
+                result.results.len(),
+                request.operations.len()
+            )));
+        }
+        Ok(result)
     }
 
     /// Update documents in a collection
-    pub async fn update_document(&self, request: UpdateDocRequest) -> Result<u64> {
+    ///
+    /// When an upsert inserts a new document, its id is returned in
+    /// [`UpdateResult::upserted_id`].
+    pub async fn update_document(&self, request: UpdateDocRequest) -> Result<UpdateResult> {
         let seq = self.next_seq();
         let payload = serde_json::to_vec(&request)
             .map_err(|e| Error::Serialization(format!("Failed to serialize update request: {}", e)))?;
@@ -591,7 +1819,7 @@ impl Connection {
             return Err(Error::Server(error_msg));
         }
 
-        Ok(op_response.affected_count.unwrap_or(0))
+        UpdateResult::from_response(op_response.data.as_ref(), op_response.affected_count)
     }
 
     /// Delete documents from a collection
@@ -678,6 +1906,34 @@ impl Connection {
         }
     }
 
+    /// Get the schema of a collection, or `None` if it has none
+    pub async fn get_schema(&self, collection: &str) -> Result<Option<Value>> {
+        let seq = self.next_seq();
+        let request = GetSchemaRequest { collection: collection.to_string() };
+        let payload = serde_json::to_vec(&request)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize get schema request: {}", e)))?;
+
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::GetSchema, seq),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+
+        let response = self.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse get schema response: {}", e)))?;
+
+        if !op_response.success {
+            let error_msg = op_response.error.unwrap_or_else(|| "Get schema failed".to_string());
+            return Err(Error::Server(error_msg));
+        }
+
+        match op_response.data {
+            None | Some(Value::Null) => Ok(None),
+            Some(schema) => Ok(Some(schema)),
+        }
+    }
+
     /// Drop a collection
     pub async fn drop_collection(&self, request: DropCollectionRequest) -> Result<()> {
         let seq = self.next_seq();
@@ -787,14 +2043,7 @@ impl Connection {
         let payload = serde_json::to_vec(&request)
             .map_err(|e| Error::Serialization(format!("Failed to serialize list operation: {}", e)))?;
         
-        let opcode = match &request.operation {
-            crate::types::ListOperation::Push { left: true, .. } => crate::types::OpCode::LPush,
-            crate::types::ListOperation::Push { left: false, .. } => crate::types::OpCode::RPush,
-            crate::types::ListOperation::Pop { left: true } => crate::types::OpCode::LPop,
-            crate::types::ListOperation::Pop { left: false } => crate::types::OpCode::RPop,
-            crate::types::ListOperation::Range { .. } => crate::types::OpCode::LRange,
-            crate::types::ListOperation::Len => crate::types::OpCode::LLen,
-        };
+        let opcode = request.operation.opcode();
 
         let cmd = Command::new(
             crate::types::CommandHeader::new(opcode, seq),
@@ -820,16 +2069,7 @@ impl Connection {
         let payload = serde_json::to_vec(&request)
             .map_err(|e| Error::Serialization(format!("Failed to serialize set operation: {}", e)))?;
         
-        let opcode = match &request.operation {
-            crate::types::SetOperation::Add { .. } => crate::types::OpCode::SAdd,
-            crate::types::SetOperation::Remove { .. } => crate::types::OpCode::SRem,
-            crate::types::SetOperation::Members => crate::types::OpCode::SMembers,
-            crate::types::SetOperation::IsMember { .. } => crate::types::OpCode::SIsMember,
-            crate::types::SetOperation::Card => crate::types::OpCode::SCard,
-            crate::types::SetOperation::Union { .. } => crate::types::OpCode::SUnion,
-            crate::types::SetOperation::Inter { .. } => crate::types::OpCode::SInter,
-            crate::types::SetOperation::Diff { .. } => crate::types::OpCode::SDiff,
-        };
+        let opcode = request.operation.opcode();
 
         let cmd = Command::new(
             crate::types::CommandHeader::new(opcode, seq),
@@ -855,14 +2095,7 @@ impl Connection {
         let payload = serde_json::to_vec(&request)
             .map_err(|e| Error::Serialization(format!("Failed to serialize sorted set operation: {}", e)))?;
         
-        let opcode = match &request.operation {
-            crate::types::SortedSetOperation::Add { .. } => crate::types::OpCode::ZAdd,
-            crate::types::SortedSetOperation::Remove { .. } => crate::types::OpCode::ZRem,
-            crate::types::SortedSetOperation::Range { .. } => crate::types::OpCode::ZRange,
-            crate::types::SortedSetOperation::RangeByScore { .. } => crate::types::OpCode::ZRangeByScore,
-            crate::types::SortedSetOperation::Card => crate::types::OpCode::ZCard,
-            crate::types::SortedSetOperation::Score { .. } => crate::types::OpCode::ZScore,
-        };
+        let opcode = request.operation.opcode();
 
         let cmd = Command::new(
             crate::types::CommandHeader::new(opcode, seq),
@@ -884,19 +2117,16 @@ impl Connection {
 
     /// Execute a hash operation
     pub async fn hash_operation(&self, request: HashOpRequest) -> Result<Value> {
+        Ok(self.hash_operation_data(request).await?.unwrap_or(Value::Null))
+    }
+
+    /// Execute a hash operation, keeping the distinction between no data and `Null` data
+    async fn hash_operation_data(&self, request: HashOpRequest) -> Result<Option<Value>> {
         let seq = self.next_seq();
         let payload = serde_json::to_vec(&request)
             .map_err(|e| Error::Serialization(format!("Failed to serialize hash operation: {}", e)))?;
         
-        let opcode = match &request.operation {
-            crate::types::HashOperation::Set { .. } => crate::types::OpCode::HSet,
-            crate::types::HashOperation::Get { .. } => crate::types::OpCode::HGet,
-            crate::types::HashOperation::Del { .. } => crate::types::OpCode::HDel,
-            crate::types::HashOperation::GetAll => crate::types::OpCode::HGetAll,
-            crate::types::HashOperation::Keys => crate::types::OpCode::HKeys,
-            crate::types::HashOperation::Vals => crate::types::OpCode::HVals,
-            crate::types::HashOperation::Len => crate::types::OpCode::HLen,
-        };
+        let opcode = request.operation.opcode();
 
         let cmd = Command::new(
             crate::types::CommandHeader::new(opcode, seq),
@@ -913,7 +2143,7 @@ impl Connection {
             return Err(Error::Server(error_msg));
         }
 
-        Ok(op_response.data.unwrap_or(Value::Null))
+        Ok(op_response.data)
     }
 
     // ============================================================================
@@ -922,17 +2152,60 @@ impl Connection {
 
     /// Subscribe to a channel
     pub async fn subscribe(&self, channel: &str) -> Result<()> {
+        self.subscribe_with_ack(channel, 0).await
+    }
+
+    /// Subscribe to a channel with at-least-once delivery, allowing at most
+    /// `max_unacked` delivered but unacknowledged messages (0 requests
+    /// fire-and-forget delivery)
+    pub(crate) async fn subscribe_with_ack(&self, channel: &str, max_unacked: u32) -> Result<()> {
+        self.subscribe_command(crate::types::OpCode::Subscribe, channel, max_unacked).await
+    }
+
+    /// Subscribe to every channel matching a glob pattern such as `events.*`
+    pub async fn psubscribe(&self, pattern: &str) -> Result<()> {
+        self.psubscribe_with_ack(pattern, 0).await
+    }
+
+    /// Subscribe to a glob pattern with at-least-once delivery
+    ///
+    /// See [`subscribe_with_ack`](Connection::subscribe_with_ack).
+    pub(crate) async fn psubscribe_with_ack(&self, pattern: &str, max_unacked: u32) -> Result<()> {
+        self.subscribe_command(crate::types::OpCode::PSubscribe, pattern, max_unacked).await
+    }
+
+    /// Send a channel or pattern subscription
+    async fn subscribe_command(&self, opcode: crate::types::OpCode, name: &str, max_unacked: u32) -> Result<()> {
         let seq = self.next_seq();
-        let cmd = Command::new(
-            crate::types::CommandHeader::new(crate::types::OpCode::Subscribe, seq),
-            Bytes::from(channel.as_bytes().to_vec()),
-            Bytes::new(),
-        );
+        let mut header = crate::types::CommandHeader::new(opcode, seq);
+        header.extra = max_unacked as u64;
+        let cmd = Command::new(header, Bytes::from(name.as_bytes().to_vec()), Bytes::new());
 
+        // Messages may be pushed before the reply arrives
+        self.shared.demux().subscribed = true;
         self.execute(cmd).await?;
         Ok(())
     }
 
+    /// Acknowledge delivered messages on a subscribed connection
+    ///
+    /// The server doesn't reply to acknowledgments, so this only writes the
+    /// command.
+    pub(crate) async fn ack(&self, delivery_ids: Vec<u64>) -> Result<()> {
+        let request = AckRequest { delivery_ids };
+        let payload = serde_json::to_vec(&request)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize ack: {}", e)))?;
+
+        let mut cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::Ack, self.next_seq()),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+        cmd.header.version = self.protocol_version();
+        check_request_size(&cmd, self.max_frame_size())?;
+        self.write(&cmd.to_bytes(), self.request_timeout).await
+    }
+
     /// Unsubscribe from a channel
     pub async fn unsubscribe(&self, channel: &str) -> Result<()> {
         let seq = self.next_seq();
@@ -946,6 +2219,19 @@ impl Connection {
         Ok(())
     }
 
+    /// Unsubscribe from a glob pattern
+    pub async fn punsubscribe(&self, pattern: &str) -> Result<()> {
+        let seq = self.next_seq();
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::PUnsubscribe, seq),
+            Bytes::from(pattern.as_bytes().to_vec()),
+            Bytes::new(),
+        );
+
+        self.execute(cmd).await?;
+        Ok(())
+    }
+
     /// Publish a message to a channel
     pub async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
         let seq = self.next_seq();
@@ -969,32 +2255,49 @@ pub struct Client {
     tls_config: Option<TlsConfig>,
     /// Authentication configuration
     auth_config: Option<AuthConfig>,
+    /// Connection reserved for pings, so health checks don't queue behind
+    /// requests; replaced when it breaks
+    ping_conn: Option<Arc<Mutex<Connection>>>,
+    /// How idempotent operations are retried after transient failures
+    retry: Option<RetryPolicy>,
+    /// Replicas that reads may be routed to
+    replicas: Option<Arc<Replicas>>,
+    /// Collection schemas inserts are checked against, if enabled
+    schemas: Option<Arc<SchemaCache>>,
 }
 
 impl Client {
     /// Create a new client connected to the specified address
-    pub async fn connect(addr: impl Into<SocketAddr>) -> Result<Self> {
+    pub async fn connect(addr: impl Into<ServerAddr>) -> Result<Self> {
         let pool = ConnectionPool::new(addr, 1, None, None).await?;
         Ok(Self { 
             pool,
             tls_config: None,
             auth_config: None,
+            ping_conn: None,
+            retry: None,
+            replicas: None,
+            schemas: None,
         })
     }
 
     /// Create a new client with a connection pool of the specified size
-    pub async fn with_pool_size(addr: impl Into<SocketAddr>, pool_size: usize) -> Result<Self> {
+    pub async fn with_pool_size(addr: impl Into<ServerAddr>, pool_size: usize) -> Result<Self> {
         let pool = ConnectionPool::new(addr, pool_size, None, None).await?;
         Ok(Self { 
             pool,
             tls_config: None,
             auth_config: None,
+            ping_conn: None,
+            retry: None,
+            replicas: None,
+            schemas: None,
         })
     }
 
     /// Create a new client with TLS configuration
     pub async fn connect_with_tls(
-        addr: impl Into<SocketAddr>,
+        addr: impl Into<ServerAddr>,
         tls_config: TlsConfig,
     ) -> Result<Self> {
         let pool = ConnectionPool::new(addr, 1, Some(tls_config.clone()), None).await?;
@@ -1002,12 +2305,16 @@ impl Client {
             pool,
             tls_config: Some(tls_config),
             auth_config: None,
+            ping_conn: None,
+            retry: None,
+            replicas: None,
+            schemas: None,
         })
     }
 
     /// Create a new client with TLS and authentication
     pub async fn connect_with_auth(
-        addr: impl Into<SocketAddr>,
+        addr: impl Into<ServerAddr>,
         tls_config: Option<TlsConfig>,
         auth_config: AuthConfig,
     ) -> Result<Self> {
@@ -1016,12 +2323,166 @@ impl Client {
             pool,
             tls_config,
             auth_config: Some(auth_config),
+            ping_conn: None,
+            retry: None,
+            replicas: None,
+            schemas: None,
         })
     }
 
+    /// Check documents against their collection's schema before inserting
+    /// them
+    ///
+    /// Each collection's schema is fetched on the first insert into it and
+    /// cached, shared with clones of the client made afterwards. A document
+    /// that doesn't match fails with [`Error::InvalidArgument`] naming the
+    /// offending field, without a round trip. Collections without a schema
+    /// aren't affected. See [`Connection::get_schema`].
+    pub fn with_schema_cache(mut self) -> Self {
+        self.schemas = Some(Arc::default());
+        self
+    }
+
+    /// Check documents for `collection` against its schema, if schemas are
+    /// being checked
+    async fn check_schema<'a>(
+        &self,
+        collection: &str,
+        documents: impl IntoIterator<Item = &'a Document>,
+    ) -> Result<()> {
+        let Some(schemas) = &self.schemas else {
+            return Ok(());
+        };
+        let schema = match schemas.get(collection) {
+            Some(schema) => schema,
+            None => {
+                let conn = self.read_conn(OpCode::GetSchema).await?;
+                match conn.get_schema(collection).await {
+                    Ok(schema) => {
+                        schemas.insert(collection, schema.clone());
+                        schema
+                    }
+                    // The server reports the missing collection on insert
+                    Err(Error::NotFound(_)) => None,
+                    Err(e) => return Err(e),
+                }
+            }
+        };
+        match schema {
+            Some(schema) => documents
+                .into_iter()
+                .try_for_each(|document| crate::schema::validate(collection, &schema, document)),
+            None => Ok(()),
+        }
+    }
+
+    /// Run an idempotent operation on a pooled connection, retrying it on a
+    /// fresh connection after retryable errors if a [`RetryPolicy`] is set
+    async fn with_retry<T, F, Fut>(&self, op: F) -> Result<T>
+    where
+        F: Fn(ConnectionGuard) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        self.retry_on(&self.pool, op).await
+    }
+
+    /// Run an idempotent operation sent with `opcode`, on a replica if it is
+    /// a read and the read preference allows, otherwise as
+    /// [`with_retry`](Client::with_retry) does
+    async fn route<T, F, Fut>(&self, opcode: OpCode, op: F) -> Result<T>
+    where
+        F: Fn(ConnectionGuard) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let Some(replicas) = self.replicas.as_ref().filter(|_| opcode.is_read()) else {
+            return self.with_retry(op).await;
+        };
+        let server = replicas.pick();
+        let start = Instant::now();
+        let result = self.retry_on(replicas.pool(&self.pool, server), op).await;
+        replicas.record_latency(server, start.elapsed());
+        result
+    }
+
+    /// Check out a connection for a command sent with `opcode`, from a
+    /// replica if it is a read and the read preference allows
+    async fn read_conn(&self, opcode: OpCode) -> Result<ConnectionGuard> {
+        match self.replicas.as_ref().filter(|_| opcode.is_read()) {
+            Some(replicas) => replicas.pool(&self.pool, replicas.pick()).get().await,
+            None => self.pool.get().await,
+        }
+    }
+
+    /// Run an idempotent operation on a connection from `pool`, retrying as
+    /// [`with_retry`](Client::with_retry) does
+    async fn retry_on<T, F, Fut>(&self, pool: &ConnectionPool, op: F) -> Result<T>
+    where
+        F: Fn(ConnectionGuard) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let Some(policy) = &self.retry else {
+            return op(pool.get().await?).await;
+        };
+
+        let mut retry = 0;
+        loop {
+            let result = match pool.get().await {
+                Ok(conn) => op(conn).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(e) if e.is_retryable() && retry + 1 < policy.max_attempts => {
+                    let delay = policy.backoff(retry);
+                    debug!("Retrying after {:?} in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                    pool.stats.record_retry();
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Ping the server
+    ///
+    /// Uses the reserved ping connection if the client was built with
+    /// [`ClientBuilder::dedicated_ping`], otherwise a pooled connection. A
+    /// reserved connection that was closed or fails to get a reply is
+    /// replaced with a new one, so pings succeed again once the server is
+    /// back.
     pub async fn ping(&self) -> Result<()> {
-        self.pool.get().await?.ping().await
+        let Some(reserved) = &self.ping_conn else {
+            return self.with_retry(|conn| async move { conn.ping().await }).await;
+        };
+        let mut conn = reserved.lock().await;
+        if conn.is_alive().await {
+            match conn.ping().await {
+                Err(e @ (Error::Connection(_) | Error::Timeout(_) | Error::Io(_))) => {
+                    debug!("Ping failed on the reserved connection, reconnecting: {}", e);
+                }
+                result => return result,
+            }
+        }
+        *conn = self.pool.connect().await?;
+        conn.ping().await
+    }
+
+    /// Send several commands on one pooled connection in a single round trip
+    ///
+    /// See [`Connection::pipeline`]; results are returned in command order.
+    pub async fn pipeline(&self, cmds: Vec<Command>) -> Result<Vec<Result<Response>>> {
+        self.pool.get().await?.pipeline(cmds).await
+    }
+
+    /// Send an arbitrary command on a pooled connection and return its response
+    ///
+    /// An escape hatch for opcodes the typed API doesn't cover yet. As with
+    /// [`Connection::pipeline`], the command is given a fresh sequence number,
+    /// so it can be built with any `seq`.
+    pub async fn execute_raw(&self, mut cmd: Command) -> Result<Response> {
+        let conn = self.pool.get().await?;
+        cmd.header.seq = conn.next_seq();
+        conn.execute(cmd).await
     }
 
     /// Set a key-value pair
@@ -1030,15 +2491,117 @@ impl Client {
         K: Into<Bytes>,
         V: Into<Bytes>,
     {
-        self.pool.get().await?.set(key, value).await
+        let (key, value) = (key.into(), value.into());
+        self.with_retry(|conn| {
+            let (key, value) = (key.clone(), value.clone());
+            async move { conn.set(key, value).await }
+        })
+        .await
     }
 
-    /// Get a value by key
+    /// Get a value by key, failing with [`Error::KeyNotFound`] if it doesn't exist
     pub async fn get<K>(&self, key: K) -> Result<Bytes>
     where
         K: Into<Bytes>,
     {
-        self.pool.get().await?.get(key).await
+        let key = key.into();
+        self.route(OpCode::Get, |conn| {
+            let key = key.clone();
+            async move { conn.get(key).await }
+        })
+        .await
+    }
+
+    /// Get a value by key, or `None` if the key doesn't exist
+    pub async fn get_opt<K>(&self, key: K) -> Result<Option<Bytes>>
+    where
+        K: Into<Bytes>,
+    {
+        let key = key.into();
+        self.route(OpCode::Get, |conn| {
+            let key = key.clone();
+            async move { conn.get_opt(key).await }
+        })
+        .await
+    }
+
+    /// Get a value by key along with its version, for a following
+    /// [`cas`](Client::cas)
+    ///
+    /// Always read from the primary, since a replica may hold an older
+    /// version. Fails with [`Error::NotSupported`] on v0.2.0 servers, whose
+    /// responses don't carry key versions.
+    pub async fn get_versioned<K>(&self, key: K) -> Result<(Bytes, u64)>
+    where
+        K: Into<Bytes>,
+    {
+        let key = key.into();
+        self.with_retry(|conn| {
+            let key = key.clone();
+            async move { conn.get_versioned(key).await }
+        })
+        .await
+    }
+
+    /// Check whether a key exists without transferring its value
+    pub async fn exists<K>(&self, key: K) -> Result<bool>
+    where
+        K: Into<Bytes>,
+    {
+        let key = key.into();
+        self.route(OpCode::Exists, |conn| {
+            let key = key.clone();
+            async move { conn.exists(key).await }
+        })
+        .await
+    }
+
+    /// Check several keys for existence on one pooled connection in a single
+    /// round trip, returning the results in key order
+    pub async fn exists_many<K>(&self, keys: &[K]) -> Result<Vec<bool>>
+    where
+        K: Clone + Into<Bytes>,
+    {
+        let keys: Vec<Bytes> = keys.iter().cloned().map(Into::into).collect();
+        self.route(OpCode::Exists, |conn| {
+            let keys = keys.clone();
+            async move { conn.exists_many(&keys).await }
+        })
+        .await
+    }
+
+    /// Get several keys on one pooled connection in a single round trip
+    ///
+    /// See [`Connection::mget`]; values are returned in key order, with `None`
+    /// for missing keys.
+    pub async fn mget<K>(&self, keys: &[K]) -> Result<Vec<Option<Bytes>>>
+    where
+        K: Clone + Into<Bytes>,
+    {
+        let keys: Vec<Bytes> = keys.iter().cloned().map(Into::into).collect();
+        self.route(OpCode::Get, |conn| {
+            let keys = keys.clone();
+            async move { conn.mget(&keys).await }
+        })
+        .await
+    }
+
+    /// Set several key-value pairs on one pooled connection in a single round
+    /// trip
+    pub async fn mset<K, V>(&self, pairs: &[(K, V)]) -> Result<()>
+    where
+        K: Clone + Into<Bytes>,
+        V: Clone + Into<Bytes>,
+    {
+        let pairs: Vec<(Bytes, Bytes)> = pairs
+            .iter()
+            .map(|(key, value)| (key.clone().into(), value.clone().into()))
+            .collect();
+        self.with_retry(|conn| {
+            let pairs = pairs.clone();
+            async move { conn.mset(&pairs).await }
+        })
+        .await
     }
 
     /// Delete a key
@@ -1049,6 +2612,77 @@ impl Client {
         self.pool.get().await?.delete(key).await
     }
 
+    /// Set a key-value pair that expires after `ttl`
+    ///
+    /// A zero `ttl` deletes the key instead.
+    pub async fn set_with_ttl<K, V>(&self, key: K, value: V, ttl: Duration) -> Result<()>
+    where
+        K: Into<Bytes>,
+        V: Into<Bytes>,
+    {
+        let (key, value) = (key.into(), value.into());
+        self.with_retry(|conn| {
+            let (key, value) = (key.clone(), value.clone());
+            async move { conn.set_with_ttl(key, value, ttl).await }
+        })
+        .await
+    }
+
+    /// Make an existing key expire after `ttl`, returning `false` if the key
+    /// doesn't exist
+    ///
+    /// A zero `ttl` deletes the key immediately.
+    pub async fn expire<K>(&self, key: K, ttl: Duration) -> Result<bool>
+    where
+        K: Into<Bytes>,
+    {
+        self.pool.get().await?.expire(key, ttl).await
+    }
+
+    /// Get how long a key has left before it expires, or `None` if it has
+    /// no expiry
+    pub async fn ttl<K>(&self, key: K) -> Result<Option<Duration>>
+    where
+        K: Into<Bytes>,
+    {
+        let key = key.into();
+        self.route(OpCode::Ttl, |conn| {
+            let key = key.clone();
+            async move { conn.ttl(key).await }
+        })
+        .await
+    }
+
+    /// Atomically increment the integer stored at a key, returning the new
+    /// value
+    ///
+    /// A missing key counts as 0. Fails with [`Error::WrongType`] if the key
+    /// holds a non-numeric value.
+    pub async fn incr<K>(&self, key: K) -> Result<i64>
+    where
+        K: Into<Bytes>,
+    {
+        self.incr_by(key, 1).await
+    }
+
+    /// Atomically decrement the integer stored at a key, returning the new
+    /// value
+    pub async fn decr<K>(&self, key: K) -> Result<i64>
+    where
+        K: Into<Bytes>,
+    {
+        self.incr_by(key, -1).await
+    }
+
+    /// Atomically add `delta` to the integer stored at a key, returning the
+    /// new value
+    pub async fn incr_by<K>(&self, key: K, delta: i64) -> Result<i64>
+    where
+        K: Into<Bytes>,
+    {
+        self.pool.get().await?.incr_by(key, delta).await
+    }
+
     /// Compare and swap a value
     pub async fn cas<K, V>(&self, key: K, expected_version: u64, value: V) -> Result<()>
     where
@@ -1062,9 +2696,18 @@ impl Client {
             .await
     }
 
+    /// Refresh the LRU recency and idle time of existing keys without
+    /// transferring their values, returning how many of the keys existed
+    pub async fn touch<K>(&self, keys: &[K]) -> Result<u64>
+    where
+        K: Clone + Into<Bytes>,
+    {
+        self.pool.get().await?.touch(keys).await
+    }
+
     /// List all keys (uses Fetch opcode 0x09)
     pub async fn list_keys(&self) -> Result<Vec<String>> {
-        let conn = self.pool.get().await?;
+        let conn = self.read_conn(OpCode::Fetch).await?;
         let cmd = Command::fetch(conn.next_seq(), Bytes::new());
         let response = conn.execute(cmd).await?;
         
@@ -1083,44 +2726,320 @@ impl Client {
         Ok(keys)
     }
 
+    /// List keys matching `pattern`
+    ///
+    /// A pattern containing `*` or `?` is matched as a glob against the whole
+    /// key; any other pattern matches keys containing it as a substring. The
+    /// filtering happens client-side on the result of [`list_keys`](Client::list_keys).
+    pub async fn list_keys_matching(&self, pattern: &str) -> Result<Vec<String>> {
+        let mut keys = self.list_keys().await?;
+        keys.retain(|key| key_matches(key, pattern));
+        Ok(keys)
+    }
+
+    /// Collect every key matching `pattern` whose data structure is `key_type`
+    ///
+    /// Both filters are applied server-side: the pattern is a glob, and the
+    /// type filter is sent as the SCAN `TYPE` argument, so keys of other types
+    /// are never transferred. Either filter may be omitted.
+    pub async fn scan_match(&self, pattern: Option<&str>, key_type: Option<KeyType>) -> Result<Vec<String>> {
+        let conn = self.read_conn(OpCode::Scan).await?;
+        let mut request = ScanRequest {
+            cursor: 0,
+            pattern: pattern.map(str::to_string),
+            count: None,
+            key_type,
+        };
+
+        let mut keys = Vec::new();
+        loop {
+            let (cursor, page) = conn.scan(&request).await?;
+            keys.extend(page);
+            if cursor == 0 {
+                return Ok(keys);
+            }
+            request.cursor = cursor;
+        }
+    }
+
+    /// Stream the keys matching `pattern`, fetching `batch` keys per round
+    /// trip
+    ///
+    /// Unlike [`list_keys`](Client::list_keys), this keeps at most one page of
+    /// keys in memory. The pattern is a glob applied server-side; `None`
+    /// matches every key.
+    pub fn scan(&self, pattern: Option<&str>, batch: usize) -> KeyScan {
+        KeyScan::new(self.clone(), pattern, batch)
+    }
+
+    /// Fetch one page of a keyspace scan
+    ///
+    /// Pages always come from the primary: a scan cursor is only meaningful
+    /// to the server that returned it.
+    pub(crate) async fn scan_page(&self, request: ScanRequest) -> Result<(u64, Vec<String>)> {
+        self.with_retry(|conn| {
+            let request = request.clone();
+            async move { conn.scan(&request).await }
+        })
+        .await
+    }
+
     // ============================================================================
     // v0.2.0 Document Operations
     // ============================================================================
 
     /// Query documents in a collection
     pub async fn query(&self, request: QueryRequest) -> Result<Vec<Document>> {
-        self.pool.get().await?.query(request).await
+        self.route(OpCode::Query, |conn| {
+            let request = request.clone();
+            async move { conn.query(request).await }
+        })
+        .await
+    }
+
+    /// Query documents in a collection, with a timeout for this call only
+    ///
+    /// The override doesn't change the default request timeout, and a query
+    /// that times out leaves its pooled connection usable.
+    pub async fn query_with_timeout(&self, request: QueryRequest, limit: Duration) -> Result<Vec<Document>> {
+        self.route(OpCode::Query, |conn| {
+            let request = request.clone();
+            async move { conn.query_with_timeout(request, limit).await }
+        })
+        .await
+    }
+
+    /// Stream the results of a query, fetching them page by page
+    ///
+    /// Unlike [`query`](Client::query), this keeps at most one page of
+    /// documents in memory. The page size defaults to 1000 and can be changed
+    /// with [`Cursor::batch_size`].
+    pub fn query_cursor(&self, request: QueryRequest) -> Cursor {
+        Cursor::new(self.clone(), request)
+    }
+
+    /// Run a query built with [`QueryBuilder`]
+    pub async fn find(&self, query: QueryBuilder) -> Result<Vec<Document>> {
+        self.query(query.build()?).await
+    }
+
+    /// Find the first document matching a filter, or `None` if nothing matches
+    pub async fn find_one(&self, collection: &str, filter: impl Into<Value>) -> Result<Option<Document>> {
+        let query = QueryBuilder::new(collection).filter(filter).limit(1);
+        Ok(self.find(query).await?.into_iter().next())
+    }
+
+    /// Get the document with the given id, or `None` if there is none
+    pub async fn get_document(&self, collection: &str, id: DocumentId) -> Result<Option<Document>> {
+        let mut filter = BTreeMap::new();
+        filter.insert("_id".to_string(), Value::from(id));
+        self.find_one(collection, Value::Object(filter)).await
+    }
+
+    /// Find documents whose `field` lies between `from` and `to` (inclusive),
+    /// sorted ascending by that field
+    pub async fn range_query(
+        &self,
+        collection: &str,
+        field: &str,
+        from: impl Into<Value>,
+        to: impl Into<Value>,
+        limit: Option<u64>,
+    ) -> Result<Vec<Document>> {
+        let mut range = BTreeMap::new();
+        range.insert("$gte".to_string(), from.into());
+        range.insert("$lte".to_string(), to.into());
+
+        let mut filter = BTreeMap::new();
+        filter.insert(field.to_string(), Value::Object(range));
+
+        let mut sort = BTreeMap::new();
+        sort.insert(field.to_string(), Value::Int32(1));
+
+        let mut query = QueryBuilder::new(collection).filter(filter).sort(sort);
+        if let Some(limit) = limit {
+            query = query.limit(limit);
+        }
+        self.find(query).await
+    }
+
+    /// Get the distinct values of `field` across the documents in a
+    /// collection, optionally restricted by a filter
+    ///
+    /// Values are deduplicated by the server, so the documents themselves
+    /// are never transferred, and returned in the stable order of
+    /// [`Value::total_cmp`].
+    pub async fn distinct(&self, collection: &str, field: &str, filter: Option<Value>) -> Result<Vec<Value>> {
+        let request = DistinctRequest {
+            collection: collection.to_string(),
+            field: field.to_string(),
+            filter,
+        };
+        self.route(OpCode::Distinct, |conn| {
+            let request = request.clone();
+            async move { conn.distinct(request).await }
+        })
+        .await
     }
 
-    /// Insert a document into a collection
-    pub async fn insert_document(&self, collection: &str, document: Document) -> Result<()> {
+    /// Insert a document into a collection, returning its id
+    ///
+    /// This is the document's own id, unless the server assigned another.
+    /// With [`with_schema_cache`](Client::with_schema_cache), the document is
+    /// checked against the collection's schema first.
+    pub async fn insert_document(&self, collection: &str, document: Document) -> Result<DocumentId> {
+        self.check_schema(collection, [&document]).await?;
         self.pool.get().await?.insert_document(collection, document).await
     }
 
+    /// Insert several documents into a collection in a single round trip
+    ///
+    /// With [`with_schema_cache`](Client::with_schema_cache), every document
+    /// is checked against the collection's schema first, and none are sent
+    /// if any fails.
+    pub async fn insert_many(&self, collection: &str, documents: Vec<Document>) -> Result<Vec<DocumentId>> {
+        self.check_schema(collection, &documents).await?;
+        self.pool.get().await?.insert_many(collection, documents).await
+    }
+
+    /// Start a transaction whose writes, possibly across several
+    /// collections, are committed all-or-nothing in one round trip
+    pub fn transaction(&self) -> Transaction {
+        Transaction::new(self.clone())
+    }
+
+    /// Apply a batch of writes atomically, returning the number of documents
+    /// each operation affected, in order
+    pub async fn commit_transaction(&self, request: TransactionRequest) -> Result<Vec<u64>> {
+        self.pool.get().await?.commit_transaction(request).await
+    }
+
+    /// Apply mixed inserts, updates and deletes to a collection in one
+    /// round trip
+    ///
+    /// With `ordered` set the writes are applied in order, stopping at the
+    /// first failure; otherwise every write is attempted. Unlike a
+    /// [`transaction`](Client::transaction), writes that succeed are kept
+    /// when others fail; [`Error::BulkWrite`] reports the first failure along
+    /// with the outcomes of the writes that were attempted.
+    pub async fn bulk_write(&self, collection: &str, operations: Vec<WriteOp>, ordered: bool) -> Result<BulkWriteResult> {
+        let inserts = operations.iter().filter_map(|op| match op {
+            WriteOp::Insert(document) => Some(document),
+            _ => None,
+        });
+        self.check_schema(collection, inserts).await?;
+        self.pool.get().await?.bulk_write(collection, operations, ordered).await
+    }
+
     /// Update documents in a collection
-    pub async fn update_document(&self, request: UpdateDocRequest) -> Result<u64> {
+    ///
+    /// When an upsert inserts a new document, its id is returned in
+    /// [`UpdateResult::upserted_id`].
+    pub async fn update_document(&self, request: UpdateDocRequest) -> Result<UpdateResult> {
         self.pool.get().await?.update_document(request).await
     }
 
+    /// Replace the first document matching a filter with `document`
+    ///
+    /// The document's fields are sent as a whole, without update operators,
+    /// so fields it lacks are removed. The matched document keeps its id:
+    /// `document.id` is not sent, so to give an upserted document a specific
+    /// id, set an `_id` field on the replacement.
+    pub async fn replace_one(
+        &self,
+        collection: &str,
+        filter: impl Into<Value>,
+        document: Document,
+        upsert: bool,
+    ) -> Result<UpdateResult> {
+        if let Some(field) = document.fields.keys().find(|field| field.starts_with('$')) {
+            return Err(Error::InvalidArgument(format!(
+                "Replacement document cannot contain update operator '{}'",
+                field
+            )));
+        }
+        self.update_document(UpdateDocRequest {
+            collection: collection.to_string(),
+            filter: filter.into(),
+            update: Value::Object(document.fields),
+            upsert,
+        })
+        .await
+    }
+
     /// Delete documents from a collection
     pub async fn delete_document(&self, request: DeleteDocRequest) -> Result<u64> {
         self.pool.get().await?.delete_document(request).await
     }
 
+    /// Delete the first document matching a filter, returning whether one
+    /// was deleted
+    pub async fn delete_one(&self, collection: &str, filter: impl Into<Value>) -> Result<bool> {
+        let deleted = self
+            .delete_document(DeleteDocRequest {
+                collection: collection.to_string(),
+                filter: filter.into(),
+                limit: Some(1),
+            })
+            .await?;
+        Ok(deleted > 0)
+    }
+
+    /// Delete a document by id, returning whether it existed
+    pub async fn delete_by_id(&self, collection: &str, id: DocumentId) -> Result<bool> {
+        let mut filter = BTreeMap::new();
+        filter.insert("_id".to_string(), Value::from(id));
+        self.delete_one(collection, filter).await
+    }
+
     /// Create a collection
     pub async fn create_collection(&self, request: CreateCollectionRequest) -> Result<()> {
-        self.pool.get().await?.create_collection(request).await
+        let (name, schema) = (request.name.clone(), request.schema.clone());
+        self.pool.get().await?.create_collection(request).await?;
+        if let Some(schemas) = &self.schemas {
+            schemas.insert(&name, schema);
+        }
+        Ok(())
+    }
+
+    /// Create a collection without a schema
+    pub async fn create_collection_named(&self, name: &str) -> Result<()> {
+        self.create_collection(CreateCollectionRequest {
+            name: name.to_string(),
+            schema: None,
+        })
+        .await
+    }
+
+    /// Create a collection without a schema unless it already exists,
+    /// returning whether it was created
+    pub async fn create_collection_if_not_exists(&self, name: &str) -> Result<bool> {
+        match self.create_collection_named(name).await {
+            Ok(()) => Ok(true),
+            Err(Error::AlreadyExists(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check whether a collection exists
+    pub async fn collection_exists(&self, name: &str) -> Result<bool> {
+        Ok(self.list_collections().await?.iter().any(|collection| collection == name))
     }
 
     /// List collections
     pub async fn list_collections(&self) -> Result<Vec<String>> {
-        let request = ListCollectionsRequest { filter: None };
-        self.pool.get().await?.list_collections(request).await
+        self.route(OpCode::ListCollections, |conn| async move {
+            conn.list_collections(ListCollectionsRequest { filter: None }).await
+        })
+        .await
     }
 
     /// Drop a collection
     pub async fn drop_collection(&self, name: impl Into<String>) -> Result<()> {
         let request = DropCollectionRequest { name: name.into() };
+        if let Some(schemas) = &self.schemas {
+            schemas.remove(&request.name);
+        }
         self.pool.get().await?.drop_collection(request).await
     }
 
@@ -1129,10 +3048,35 @@ impl Client {
         self.pool.get().await?.create_index(request).await
     }
 
+    /// Create an index, returning `false` if it already exists
+    pub async fn create_index_if_not_exists(&self, request: CreateIndexRequest) -> Result<bool> {
+        match self.create_index(request).await {
+            Ok(()) => Ok(true),
+            Err(Error::AlreadyExists(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     /// List indexes
     pub async fn list_indexes(&self, collection: impl Into<String>) -> Result<Vec<Value>> {
         let request = ListIndexesRequest { collection: collection.into() };
-        self.pool.get().await?.list_indexes(request).await
+        self.route(OpCode::ListIndexes, |conn| {
+            let request = request.clone();
+            async move { conn.list_indexes(request).await }
+        })
+        .await
+    }
+
+    /// List indexes parsed into [`IndexInfo`]
+    pub async fn list_indexes_typed(&self, collection: impl Into<String>) -> Result<Vec<crate::types::IndexInfo>> {
+        self.list_indexes(collection)
+            .await?
+            .iter()
+            .map(|index| {
+                crate::types::IndexInfo::from_value(index)
+                    .ok_or_else(|| Error::InvalidResponse("Expected index object".to_string()))
+            })
+            .collect()
     }
 
     /// Drop an index
@@ -1150,64 +3094,582 @@ impl Client {
 
     /// Execute a list operation
     pub async fn list_operation(&self, request: ListOpRequest) -> Result<Value> {
-        self.pool.get().await?.list_operation(request).await
+        if !request.operation.is_read_only() {
+            return self.pool.get().await?.list_operation(request).await;
+        }
+        self.route(request.operation.opcode(), |conn| {
+            let request = request.clone();
+            async move { conn.list_operation(request).await }
+        })
+        .await
     }
 
-    /// Execute a set operation
-    pub async fn set_operation(&self, request: SetOpRequest) -> Result<Value> {
-        self.pool.get().await?.set_operation(request).await
+    /// Push values onto the head of a list, returning the new list length
+    pub async fn lpush<V: Into<Value>>(
+        &self,
+        key: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Result<u64> {
+        self.push(key.into(), values.into_iter().map(Into::into).collect(), true).await
     }
 
-    /// Execute a sorted set operation
-    pub async fn sorted_set_operation(&self, request: SortedSetOpRequest) -> Result<Value> {
-        self.pool.get().await?.sorted_set_operation(request).await
+    /// Push values onto the tail of a list, returning the new list length
+    pub async fn rpush<V: Into<Value>>(
+        &self,
+        key: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Result<u64> {
+        self.push(key.into(), values.into_iter().map(Into::into).collect(), false).await
     }
 
-    /// Execute a hash operation
-    pub async fn hash_operation(&self, request: HashOpRequest) -> Result<Value> {
-        self.pool.get().await?.hash_operation(request).await
+    async fn push(&self, key: String, values: Vec<Value>, left: bool) -> Result<u64> {
+        let request = ListOpRequest {
+            key,
+            operation: ListOperation::Push { values, left },
+        };
+        count_from_value(self.list_operation(request).await?, "push")
     }
 
-    // ============================================================================
-    // Pub/Sub Operations
-    // ============================================================================
+    /// Pop a value from the head of a list, or `None` if the list is empty
+    pub async fn lpop(&self, key: impl Into<String>) -> Result<Option<Value>> {
+        let request = ListOpRequest {
+            key: key.into(),
+            operation: ListOperation::Pop { left: true },
+        };
+        Ok(optional_value(self.list_operation(request).await?))
+    }
 
-    /// Subscribe to a channel
-    pub async fn subscribe(&self, channel: &str) -> Result<()> {
-        self.pool.get().await?.subscribe(channel).await
+    /// Pop a value from the tail of a list, or `None` if the list is empty
+    pub async fn rpop(&self, key: impl Into<String>) -> Result<Option<Value>> {
+        let request = ListOpRequest {
+            key: key.into(),
+            operation: ListOperation::Pop { left: false },
+        };
+        Ok(optional_value(self.list_operation(request).await?))
     }
 
-    /// Unsubscribe from a channel
-    pub async fn unsubscribe(&self, channel: &str) -> Result<()> {
-        self.pool.get().await?.unsubscribe(channel).await
+    /// Get the elements of a list between `start` and `stop` (inclusive;
+    /// negative indexes count from the end)
+    pub async fn lrange(&self, key: impl Into<String>, start: i64, stop: i64) -> Result<Vec<Value>> {
+        let request = ListOpRequest {
+            key: key.into(),
+            operation: ListOperation::Range { start, stop },
+        };
+        array_from_value(self.list_operation(request).await?, "lrange")
     }
 
-    /// Publish a message to a channel
-    pub async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
-        self.pool.get().await?.publish(channel, message).await
+    /// Get the length of a list
+    pub async fn llen(&self, key: impl Into<String>) -> Result<u64> {
+        let request = ListOpRequest {
+            key: key.into(),
+            operation: ListOperation::Len,
+        };
+        count_from_value(self.list_operation(request).await?, "llen")
     }
 
-    // ============================================================================
-    // Server Info / Metrics
-    // ============================================================================
+    /// Execute a set operation
+    pub async fn set_operation(&self, request: SetOpRequest) -> Result<Value> {
+        if !request.operation.is_read_only() {
+            return self.pool.get().await?.set_operation(request).await;
+        }
+        self.route(request.operation.opcode(), |conn| {
+            let request = request.clone();
+            async move { conn.set_operation(request).await }
+        })
+        .await
+    }
 
-    /// Get server information and metrics
-    pub async fn info(&self) -> Result<crate::types::ServerInfo> {
-        let conn = self.pool.get().await?;
-        let seq = conn.next_seq();
-        let cmd = Command::new(
-            crate::types::CommandHeader::new(crate::types::OpCode::Info, seq),
-            Bytes::new(),
-            Bytes::new(),
-        );
+    /// Add members to a set, returning how many were newly added
+    pub async fn sadd<V: Into<Value>>(
+        &self,
+        key: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Result<u64> {
+        let request = SetOpRequest {
+            key: key.into(),
+            operation: SetOperation::Add { values: values.into_iter().map(Into::into).collect() },
+        };
+        count_from_value(self.set_operation(request).await?, "sadd")
+    }
 
-        let response = conn.execute(cmd).await?;
-        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
-            .map_err(|e| Error::Serialization(format!("Failed to parse info response: {}", e)))?;
+    /// Remove members from a set, returning how many were removed
+    pub async fn srem<V: Into<Value>>(
+        &self,
+        key: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Result<u64> {
+        let request = SetOpRequest {
+            key: key.into(),
+            operation: SetOperation::Remove { values: values.into_iter().map(Into::into).collect() },
+        };
+        count_from_value(self.set_operation(request).await?, "srem")
+    }
 
-        if !op_response.success {
-            let error_msg = op_response.error.unwrap_or_else(|| "Info request failed".to_string());
-            return Err(Error::Server(error_msg));
+    /// Get the members of a set
+    pub async fn smembers(&self, key: impl Into<String>) -> Result<Vec<Value>> {
+        let request = SetOpRequest {
+            key: key.into(),
+            operation: SetOperation::Members,
+        };
+        array_from_value(self.set_operation(request).await?, "smembers")
+    }
+
+    /// Check whether a value is a member of a set
+    pub async fn sismember(&self, key: impl Into<String>, value: impl Into<Value>) -> Result<bool> {
+        let request = SetOpRequest {
+            key: key.into(),
+            operation: SetOperation::IsMember { value: value.into() },
+        };
+        bool_from_value(self.set_operation(request).await?, "sismember")
+    }
+
+    /// Get the number of members in a set
+    pub async fn scard(&self, key: impl Into<String>) -> Result<u64> {
+        let request = SetOpRequest {
+            key: key.into(),
+            operation: SetOperation::Card,
+        };
+        count_from_value(self.set_operation(request).await?, "scard")
+    }
+
+    /// Get the members of the union of a set with `other_keys`
+    pub async fn sunion<K: Into<String>>(
+        &self,
+        key: impl Into<String>,
+        other_keys: impl IntoIterator<Item = K>,
+    ) -> Result<Vec<Value>> {
+        let request = SetOpRequest {
+            key: key.into(),
+            operation: SetOperation::Union { other_keys: other_keys.into_iter().map(Into::into).collect() },
+        };
+        array_from_value(self.set_operation(request).await?, "sunion")
+    }
+
+    /// Get the members of a set that are also in every one of `other_keys`
+    pub async fn sinter<K: Into<String>>(
+        &self,
+        key: impl Into<String>,
+        other_keys: impl IntoIterator<Item = K>,
+    ) -> Result<Vec<Value>> {
+        let request = SetOpRequest {
+            key: key.into(),
+            operation: SetOperation::Inter { other_keys: other_keys.into_iter().map(Into::into).collect() },
+        };
+        array_from_value(self.set_operation(request).await?, "sinter")
+    }
+
+    /// Get the members of a set that are in none of `other_keys`
+    pub async fn sdiff<K: Into<String>>(
+        &self,
+        key: impl Into<String>,
+        other_keys: impl IntoIterator<Item = K>,
+    ) -> Result<Vec<Value>> {
+        let request = SetOpRequest {
+            key: key.into(),
+            operation: SetOperation::Diff { other_keys: other_keys.into_iter().map(Into::into).collect() },
+        };
+        array_from_value(self.set_operation(request).await?, "sdiff")
+    }
+
+    /// Execute a sorted set operation
+    pub async fn sorted_set_operation(&self, request: SortedSetOpRequest) -> Result<Value> {
+        if !request.operation.is_read_only() {
+            return self.pool.get().await?.sorted_set_operation(request).await;
+        }
+        self.route(request.operation.opcode(), |conn| {
+            let request = request.clone();
+            async move { conn.sorted_set_operation(request).await }
+        })
+        .await
+    }
+
+    /// Add members to a sorted set, returning how many were newly added
+    pub async fn zadd<M: Into<ScoredMember>>(
+        &self,
+        key: impl Into<String>,
+        members: impl IntoIterator<Item = M>,
+    ) -> Result<u64> {
+        let request = SortedSetOpRequest {
+            key: key.into(),
+            operation: SortedSetOperation::Add { members: members.into_iter().map(Into::into).collect() },
+        };
+        count_from_value(self.sorted_set_operation(request).await?, "zadd")
+    }
+
+    /// Remove members from a sorted set, returning how many were removed
+    pub async fn zrem<V: Into<Value>>(
+        &self,
+        key: impl Into<String>,
+        members: impl IntoIterator<Item = V>,
+    ) -> Result<u64> {
+        let request = SortedSetOpRequest {
+            key: key.into(),
+            operation: SortedSetOperation::Remove { members: members.into_iter().map(Into::into).collect() },
+        };
+        count_from_value(self.sorted_set_operation(request).await?, "zrem")
+    }
+
+    /// Get the members of a sorted set between ranks `start` and `stop`
+    /// (inclusive; negative ranks count from the end)
+    pub async fn zrange(&self, key: impl Into<String>, start: i64, stop: i64) -> Result<Vec<Value>> {
+        let request = SortedSetOpRequest {
+            key: key.into(),
+            operation: SortedSetOperation::Range { start, stop },
+        };
+        array_from_value(self.sorted_set_operation(request).await?, "zrange")
+    }
+
+    /// Get the members of a sorted set with scores between `min` and `max` (inclusive)
+    pub async fn zrangebyscore(&self, key: impl Into<String>, min: f64, max: f64) -> Result<Vec<Value>> {
+        let request = SortedSetOpRequest {
+            key: key.into(),
+            operation: SortedSetOperation::RangeByScore { min, max },
+        };
+        array_from_value(self.sorted_set_operation(request).await?, "zrangebyscore")
+    }
+
+    /// Get the number of members in a sorted set
+    pub async fn zcard(&self, key: impl Into<String>) -> Result<u64> {
+        let request = SortedSetOpRequest {
+            key: key.into(),
+            operation: SortedSetOperation::Card,
+        };
+        count_from_value(self.sorted_set_operation(request).await?, "zcard")
+    }
+
+    /// Get the score of a member, or `None` if it is not in the sorted set
+    pub async fn zscore(&self, key: impl Into<String>, member: impl Into<Value>) -> Result<Option<f64>> {
+        let request = SortedSetOpRequest {
+            key: key.into(),
+            operation: SortedSetOperation::Score { member: member.into() },
+        };
+        match self.sorted_set_operation(request).await? {
+            Value::Null => Ok(None),
+            value => value.as_f64().map(Some).ok_or_else(|| {
+                Error::InvalidResponse(format!("Expected a score from zscore, got {:?}", value))
+            }),
+        }
+    }
+
+    /// Execute a hash operation
+    pub async fn hash_operation(&self, request: HashOpRequest) -> Result<Value> {
+        if !request.operation.is_read_only() {
+            return self.pool.get().await?.hash_operation(request).await;
+        }
+        self.route(request.operation.opcode(), |conn| {
+            let request = request.clone();
+            async move { conn.hash_operation(request).await }
+        })
+        .await
+    }
+
+    /// Set a field in a hash
+    pub async fn hset(
+        &self,
+        key: impl Into<String>,
+        field: impl Into<String>,
+        value: impl Into<Value>,
+    ) -> Result<()> {
+        let request = HashOpRequest {
+            key: key.into(),
+            operation: HashOperation::Set { field: field.into(), value: value.into() },
+        };
+        self.hash_operation(request).await?;
+        Ok(())
+    }
+
+    /// Get a field from a hash, or `None` if the field is absent
+    ///
+    /// A field explicitly set to null is returned as `Some(Value::Null)` when
+    /// the server distinguishes it from a missing field.
+    pub async fn hget(&self, key: impl Into<String>, field: impl Into<String>) -> Result<Option<Value>> {
+        let request = HashOpRequest {
+            key: key.into(),
+            operation: HashOperation::Get { field: field.into() },
+        };
+        self.route(OpCode::HGet, |conn| {
+            let request = request.clone();
+            async move { conn.hash_operation_data(request).await }
+        })
+        .await
+    }
+
+    /// Delete fields from a hash, returning how many were removed
+    pub async fn hdel<F: Into<String>>(
+        &self,
+        key: impl Into<String>,
+        fields: impl IntoIterator<Item = F>,
+    ) -> Result<u64> {
+        let request = HashOpRequest {
+            key: key.into(),
+            operation: HashOperation::Del { fields: fields.into_iter().map(Into::into).collect() },
+        };
+        count_from_value(self.hash_operation(request).await?, "hdel")
+    }
+
+    /// Get every field and value in a hash
+    pub async fn hgetall(&self, key: impl Into<String>) -> Result<BTreeMap<String, Value>> {
+        let request = HashOpRequest {
+            key: key.into(),
+            operation: HashOperation::GetAll,
+        };
+        hash_from_value(self.hash_operation(request).await?, "hgetall")
+    }
+
+    /// Get every field of several hashes in one round trip
+    ///
+    /// The maps are returned in the same order as `keys`, with an empty map
+    /// for each key that doesn't exist.
+    pub async fn hgetall_many(&self, keys: &[&str]) -> Result<Vec<BTreeMap<String, Value>>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let conn = self.read_conn(OpCode::HGetAll).await?;
+        let cmds = keys
+            .iter()
+            .map(|key| {
+                let request = HashOpRequest {
+                    key: key.to_string(),
+                    operation: HashOperation::GetAll,
+                };
+                let payload = serde_json::to_vec(&request)
+                    .map_err(|e| Error::Serialization(format!("Failed to serialize hash operation: {}", e)))?;
+                Ok(Command::new(
+                    crate::types::CommandHeader::new(crate::types::OpCode::HGetAll, 0),
+                    Bytes::new(),
+                    Bytes::from(payload),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        conn.pipeline(cmds)
+            .await?
+            .into_iter()
+            .map(|response| {
+                let response = match response {
+                    Ok(response) => response,
+                    Err(Error::KeyNotFound) => return Ok(BTreeMap::new()),
+                    Err(e) => return Err(e),
+                };
+                let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+                    .map_err(|e| Error::Serialization(format!("Failed to parse hash operation response: {}", e)))?;
+                if !op_response.success {
+                    let error_msg = op_response.error.unwrap_or_else(|| "Hash operation failed".to_string());
+                    return Err(Error::Server(error_msg));
+                }
+                hash_from_value(op_response.data.unwrap_or(Value::Null), "hgetall")
+            })
+            .collect()
+    }
+
+    /// Get the field names in a hash
+    pub async fn hkeys(&self, key: impl Into<String>) -> Result<Vec<String>> {
+        let request = HashOpRequest {
+            key: key.into(),
+            operation: HashOperation::Keys,
+        };
+        array_from_value(self.hash_operation(request).await?, "hkeys")?
+            .into_iter()
+            .map(|field| match field {
+                Value::String(name) => Ok(name),
+                other => Err(Error::InvalidResponse(format!("Expected a field name from hkeys, got {:?}", other))),
+            })
+            .collect()
+    }
+
+    /// Get the values in a hash
+    pub async fn hvals(&self, key: impl Into<String>) -> Result<Vec<Value>> {
+        let request = HashOpRequest {
+            key: key.into(),
+            operation: HashOperation::Vals,
+        };
+        array_from_value(self.hash_operation(request).await?, "hvals")
+    }
+
+    /// Get the number of fields in a hash
+    pub async fn hlen(&self, key: impl Into<String>) -> Result<u64> {
+        let request = HashOpRequest {
+            key: key.into(),
+            operation: HashOperation::Len,
+        };
+        count_from_value(self.hash_operation(request).await?, "hlen")
+    }
+
+    // ============================================================================
+    // Pub/Sub Operations
+    // ============================================================================
+
+    /// Subscribe to a channel
+    ///
+    /// The subscription runs on its own connection, outside the pool.
+    pub async fn subscribe(&self, channel: &str) -> Result<Subscription> {
+        Subscription::open(
+            self.pool.addr.clone(),
+            self.tls_config.clone(),
+            self.auth_config.clone(),
+            vec![channel.to_string()],
+            Vec::new(),
+            0,
+        )
+        .await
+    }
+
+    /// Subscribe to a channel with at-least-once delivery
+    ///
+    /// Each received [`Message`](crate::Message) must be acknowledged with
+    /// [`Message::ack`](crate::Message::ack); the server stops delivering once
+    /// `max_unacked` (at least 1) messages are outstanding and redelivers unacknowledged
+    /// messages after a reconnect. On a server that only supports
+    /// fire-and-forget pub/sub, messages arrive without a delivery id and
+    /// `ack` is a no-op.
+    pub async fn subscribe_with_ack(&self, channel: &str, max_unacked: u32) -> Result<Subscription> {
+        Subscription::open(
+            self.pool.addr.clone(),
+            self.tls_config.clone(),
+            self.auth_config.clone(),
+            vec![channel.to_string()],
+            Vec::new(),
+            max_unacked.max(1),
+        )
+        .await
+    }
+
+    /// Subscribe to several channels on one dedicated connection
+    ///
+    /// Each received [`Message`](crate::Message) carries the channel it was
+    /// published on.
+    pub async fn subscribe_many(&self, channels: &[&str]) -> Result<Subscription> {
+        if channels.is_empty() {
+            return Err(Error::InvalidArgument("subscribe_many requires at least one channel".to_string()));
+        }
+        Subscription::open(
+            self.pool.addr.clone(),
+            self.tls_config.clone(),
+            self.auth_config.clone(),
+            channels.iter().map(|channel| channel.to_string()).collect(),
+            Vec::new(),
+            0,
+        )
+        .await
+    }
+
+    /// Subscribe to every channel matching a glob pattern such as `events.*`
+    ///
+    /// Each received [`Message`](crate::Message) carries the concrete channel
+    /// it was published on.
+    pub async fn psubscribe(&self, pattern: &str) -> Result<Subscription> {
+        Subscription::open(
+            self.pool.addr.clone(),
+            self.tls_config.clone(),
+            self.auth_config.clone(),
+            Vec::new(),
+            vec![pattern.to_string()],
+            0,
+        )
+        .await
+    }
+
+    /// Subscribe to a channel, reconnecting automatically if the connection drops
+    pub async fn subscribe_resilient(&self, channel: &str) -> Result<ResilientSubscription> {
+        let subscription = self.subscribe(channel).await?;
+        Ok(ResilientSubscription::new(
+            subscription,
+            self.pool.addr.clone(),
+            self.tls_config.clone(),
+            self.auth_config.clone(),
+        ))
+    }
+
+    /// Subscribe to a channel with at-least-once delivery, reconnecting
+    /// automatically if the connection drops
+    ///
+    /// See [`subscribe_with_ack`](Client::subscribe_with_ack).
+    pub async fn subscribe_resilient_with_ack(&self, channel: &str, max_unacked: u32) -> Result<ResilientSubscription> {
+        let subscription = self.subscribe_with_ack(channel, max_unacked).await?;
+        Ok(ResilientSubscription::new(
+            subscription,
+            self.pool.addr.clone(),
+            self.tls_config.clone(),
+            self.auth_config.clone(),
+        ))
+    }
+
+    /// Unsubscribe from a channel
+    pub async fn unsubscribe(&self, channel: &str) -> Result<()> {
+        self.pool.get().await?.unsubscribe(channel).await
+    }
+
+    /// Publish a message to a channel
+    pub async fn publish(&self, channel: &str, message: &[u8]) -> Result<()> {
+        self.pool.get().await?.publish(channel, message).await
+    }
+
+    // ============================================================================
+    // Server Info / Metrics
+    // ============================================================================
+
+    /// Get client-side request statistics
+    ///
+    /// These are counted by this client, independently of the metrics the
+    /// server reports through [`info`](Client::info).
+    pub fn client_stats(&self) -> ClientStats {
+        self.pool.stats.snapshot()
+    }
+
+    /// Get the occupancy of the connection pool, e.g. to spot saturation
+    ///
+    /// Only the primary's pool is counted, not those of any replicas.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    /// Get the number of commands sent so far, keyed by opcode
+    ///
+    /// Like [`client_stats`](Client::client_stats) this is counted
+    /// client-side; it is cleared by [`reset_stats`](Client::reset_stats).
+    #[cfg(feature = "tracing-subscriber")]
+    pub fn opcode_counts(&self) -> BTreeMap<u8, u64> {
+        self.pool.stats.opcode_counts()
+    }
+
+    /// Reset the client-side request statistics to zero
+    pub fn reset_stats(&self) {
+        self.pool.stats.reset();
+    }
+
+    /// Close the client's connections cleanly
+    ///
+    /// See [`ConnectionPool::close`]; the pool is shared with every clone of
+    /// this client, so they are all shut down.
+    pub async fn shutdown(self) -> Result<()> {
+        let mut result = self.pool.close().await;
+        for replica in self.replicas() {
+            result = result.and(replica.close().await);
+        }
+        match self.ping_conn.map(Arc::try_unwrap) {
+            Some(Ok(conn)) => result.and(conn.into_inner().close().await),
+            _ => result,
+        }
+    }
+
+    /// Get server information and metrics
+    pub async fn info(&self) -> Result<crate::types::ServerInfo> {
+        let conn = self.pool.get().await?;
+        let seq = conn.next_seq();
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::Info, seq),
+            Bytes::new(),
+            Bytes::new(),
+        );
+
+        let response = conn.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse info response: {}", e)))?;
+
+        if !op_response.success {
+            let error_msg = op_response.error.unwrap_or_else(|| "Info request failed".to_string());
+            return Err(Error::Server(error_msg));
         }
 
         // Parse ServerInfo from response data - extract from Value::Object manually
@@ -1215,10 +3677,10 @@ impl Client {
         let obj = data.as_object().ok_or_else(|| Error::Server("Expected object data".to_string()))?;
         
         let info = crate::types::ServerInfo {
-            uptime_seconds: obj.get("uptime_seconds").and_then(|v| v.as_i64()).unwrap_or(0) as u64,
-            connection_count: obj.get("connection_count").and_then(|v| v.as_i64()).unwrap_or(0) as u32,
-            total_collections: obj.get("total_collections").and_then(|v| v.as_i64()).unwrap_or(0) as u64,
-            memory_usage_bytes: obj.get("memory_usage_bytes").and_then(|v| v.as_i64()).unwrap_or(0) as u64,
+            uptime_seconds: obj.get("uptime_seconds").and_then(|v| v.as_u64()).unwrap_or(0),
+            connection_count: obj.get("connection_count").and_then(|v| v.as_u32()).unwrap_or(0),
+            total_collections: obj.get("total_collections").and_then(|v| v.as_u64()).unwrap_or(0),
+            memory_usage_bytes: obj.get("memory_usage_bytes").and_then(|v| v.as_u64()).unwrap_or(0),
             ops_per_second: obj.get("ops_per_second").and_then(|v| v.as_f64()).unwrap_or(0.0),
             cache_hit_rate: obj.get("cache_hit_rate").and_then(|v| v.as_f64()).unwrap_or(0.0),
             version: obj.get("version").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
@@ -1227,6 +3689,79 @@ impl Client {
         Ok(info)
     }
 
+    /// Re-authenticate every connection, e.g. after changing the current
+    /// user's role with [`update_user_role`](Client::update_user_role)
+    ///
+    /// Until this is called, each connection keeps the permissions of the
+    /// token it last obtained, so operations may succeed or fail depending on
+    /// which pooled connection they use. Operations started after this
+    /// returns use fresh tokens; see [`ConnectionPool::refresh_auth`].
+    pub async fn refresh_auth(&self) -> Result<()> {
+        self.pool.refresh_auth().await?;
+        for replica in self.replicas() {
+            replica.refresh_auth().await?;
+        }
+        if let Some(conn) = &self.ping_conn {
+            conn.lock().await.refresh_auth().await?;
+        }
+        Ok(())
+    }
+
+    /// Switch to logical database `db`
+    ///
+    /// This applies to the whole client, including its clones: every
+    /// operation started after it returns runs against `db`, on whichever
+    /// pooled connection it uses. See [`ConnectionPool::select`].
+    pub async fn select(&self, db: u32) -> Result<()> {
+        self.pool.select(db).await?;
+        for replica in self.replicas() {
+            replica.select(db).await?;
+        }
+        Ok(())
+    }
+
+    /// The replica pools, if any
+    fn replicas(&self) -> impl Iterator<Item = &ConnectionPool> {
+        self.replicas.iter().flat_map(|replicas| replicas.pools())
+    }
+
+    /// Get the logical database the client runs commands against
+    pub fn database(&self) -> u32 {
+        self.pool.database()
+    }
+
+    /// Check whether the server is ready to serve requests
+    ///
+    /// Unlike [`ping`](Client::ping), which only proves the connection and
+    /// authentication work, this asks the server for its readiness, e.g.
+    /// for a Kubernetes readiness probe. Branch on
+    /// [`HealthStatus::state`](crate::HealthStatus::state) to tell degraded
+    /// from healthy.
+    pub async fn health(&self) -> Result<crate::types::HealthStatus> {
+        let conn = self.pool.get().await?;
+        let seq = conn.next_seq();
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::Health, seq),
+            Bytes::new(),
+            Bytes::new(),
+        );
+
+        let response = conn.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse health response: {}", e)))?;
+
+        if !op_response.success {
+            let error_msg = op_response.error.unwrap_or_else(|| "Health check failed".to_string());
+            return Err(Error::Server(error_msg));
+        }
+
+        op_response
+            .data
+            .as_ref()
+            .and_then(crate::types::HealthStatus::from_value)
+            .ok_or_else(|| Error::InvalidResponse("Expected health object".to_string()))
+    }
+
     // ============================================================================
     // User Management Operations
     // ============================================================================
@@ -1250,26 +3785,51 @@ impl Client {
             return Err(Error::Server(error_msg));
         }
 
-        // Parse users from response data - extract from Value::Array of Value::Object manually
+        // Parse users from response data
         let data = op_response.data.ok_or_else(|| Error::Server("No data in response".to_string()))?;
         let arr = data.as_array().ok_or_else(|| Error::Server("Expected array data".to_string()))?;
         
-        let users: Vec<crate::types::UserInfo> = arr.iter().filter_map(|user_val| {
-            let obj = user_val.as_object()?;
-            Some(crate::types::UserInfo {
-                username: obj.get("username").and_then(|v| v.as_str())?.to_string(),
-                role: obj.get("role").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
-                created_at: obj.get("created_at").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-                last_login: obj.get("last_login").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                enabled: obj.get("enabled").and_then(|v| v.as_bool()).unwrap_or(false),
-            })
-        }).collect();
+        let users: Vec<crate::types::UserInfo> = arr.iter()
+            .filter_map(crate::types::UserInfo::from_value)
+            .collect();
         
         Ok(users)
     }
 
-    /// Create a new user
-    pub async fn create_user(&self, request: crate::types::CreateUserRequest) -> Result<()> {
+    /// Get a single user by username, returning `None` if the user does not exist
+    pub async fn get_user(&self, username: impl Into<String>) -> Result<Option<crate::types::UserInfo>> {
+        let conn = self.pool.get().await?;
+        let seq = conn.next_seq();
+        let request = crate::types::GetUserRequest { username: username.into() };
+        let payload = serde_json::to_vec(&request)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize get user request: {}", e)))?;
+        
+        let cmd = Command::new(
+            crate::types::CommandHeader::new(crate::types::OpCode::GetUser, seq),
+            Bytes::new(),
+            Bytes::from(payload),
+        );
+
+        let response = conn.execute(cmd).await?;
+        let op_response: OperationResponse = serde_json::from_slice(&response.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to parse get user response: {}", e)))?;
+
+        if !op_response.success {
+            let error_msg = op_response.error.unwrap_or_else(|| "Get user failed".to_string());
+            return Err(Error::Server(error_msg));
+        }
+
+        match op_response.data {
+            None | Some(Value::Null) => Ok(None),
+            Some(data) => crate::types::UserInfo::from_value(&data)
+                .map(Some)
+                .ok_or_else(|| Error::InvalidResponse("Expected user object".to_string())),
+        }
+    }
+
+    /// Create a new user, returning the user as stored by the server
+    pub async fn create_user(&self, request: crate::types::CreateUserRequest) -> Result<crate::types::UserInfo> {
+        let username = request.username.clone();
         let conn = self.pool.get().await?;
         let seq = conn.next_seq();
         let payload = serde_json::to_vec(&request)
@@ -1290,7 +3850,13 @@ impl Client {
             return Err(Error::Server(error_msg));
         }
 
-        Ok(())
+        // Servers that don't echo the created user get a follow-up lookup
+        if let Some(user) = op_response.data.as_ref().and_then(crate::types::UserInfo::from_value) {
+            return Ok(user);
+        }
+        drop(conn);
+        self.get_user(username.clone()).await?
+            .ok_or_else(|| Error::InvalidResponse(format!("Created user '{}' not found", username)))
     }
 
     /// Delete a user
@@ -1320,12 +3886,17 @@ impl Client {
     }
 
     /// Update a user's role
-    pub async fn update_user_role(&self, username: impl Into<String>, role: impl Into<String>) -> Result<()> {
+    ///
+    /// Roles given as strings can be validated before the round trip with
+    /// `"admin".parse::<Role>()?`, which fails with [`Error::InvalidArgument`].
+    ///
+    /// [`Error::InvalidArgument`]: crate::Error::InvalidArgument
+    pub async fn update_user_role(&self, username: impl Into<String>, role: crate::types::Role) -> Result<()> {
         let conn = self.pool.get().await?;
         let seq = conn.next_seq();
         let request = crate::types::UpdateUserRoleRequest { 
             username: username.into(), 
-            role: role.into() 
+            role,
         };
         let payload = serde_json::to_vec(&request)
             .map_err(|e| Error::Serialization(format!("Failed to serialize update user role request: {}", e)))?;
@@ -1353,67 +3924,513 @@ impl Client {
 #[derive(Debug, Clone)]
 pub struct ConnectionPool {
     /// The server address
-    addr: SocketAddr,
+    addr: ServerAddr,
     /// The connection pool receiver
     pool: async_channel::Receiver<Connection>,
     /// The connection pool sender
     pool_sender: async_channel::Sender<Connection>,
     /// The number of connections in the pool
     size: usize,
+    /// TLS configuration for new connections
+    tls_config: Option<TlsConfig>,
+    /// Authentication configuration for new connections
+    auth_config: Option<AuthConfig>,
+    /// Connection timeout for new connections
+    connect_timeout: Duration,
+    /// Request timeout for new connections
+    request_timeout: Duration,
+    /// Request statistics shared by every connection
+    stats: Arc<StatsCounters>,
+    /// How long to wait for a free connection
+    acquire_timeout: Duration,
+    /// Connections currently open, idle or checked out
+    live: Arc<AtomicUsize>,
+    /// Connections currently checked out
+    in_use: Arc<AtomicUsize>,
+    /// Whether new connections detect the server's protocol version
+    detect_protocol: bool,
+    /// How long before token expiry new connections re-authenticate
+    auth_refresh_threshold: Duration,
+    /// Largest frame new connections accept
+    max_frame_size: usize,
+    /// Whether new connections checksum their commands
+    checksums: bool,
+    /// Logical database connections are switched to on checkout, shared
+    /// with every clone of the pool
+    database: Arc<AtomicU32>,
+    /// Write coalescing buffer size for new connections
+    write_buffer: usize,
+    /// Bumped by [`refresh_auth`](ConnectionPool::refresh_auth); connections
+    /// behind it re-authenticate on checkout
+    auth_epoch: Arc<AtomicU64>,
+    /// Whether the server is the primary or a replica
+    role: NodeRole,
+    /// Connections idle for longer than this are replaced on checkout
+    idle_timeout: Option<Duration>,
+    /// Connections open for longer than this are replaced on checkout
+    max_lifetime: Option<Duration>,
+    /// Smallest value compressed, if new connections negotiate compression
+    #[cfg(feature = "compression")]
+    compression: Option<usize>,
 }
 
 impl ConnectionPool {
     /// Create a new connection pool
     pub async fn new(
-        addr: impl Into<SocketAddr>, 
+        addr: impl Into<ServerAddr>, 
         size: usize,
         tls_config: Option<TlsConfig>,
         auth_config: Option<AuthConfig>,
     ) -> Result<Self> {
-        let addr = addr.into();
+        Self::with_timeouts(
+            addr,
+            size,
+            tls_config,
+            auth_config,
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_REQUEST_TIMEOUT,
+        )
+        .await
+    }
+
+    /// Create a new connection pool whose connections use the given timeouts
+    pub async fn with_timeouts(
+        addr: impl Into<ServerAddr>,
+        size: usize,
+        tls_config: Option<TlsConfig>,
+        auth_config: Option<AuthConfig>,
+        connect_timeout: Duration,
+        request_timeout: Duration,
+    ) -> Result<Self> {
+        Self::lazy(
+            addr,
+            size,
+            tls_config,
+            auth_config,
+            connect_timeout,
+            request_timeout,
+        )
+        .fill()
+        .await
+    }
+
+    /// Create a connection pool that opens connections on first checkout
+    ///
+    /// No connection is made up front; [`get`](ConnectionPool::get) opens a
+    /// new one whenever none is idle, up to `size` connections.
+    pub fn lazy(
+        addr: impl Into<ServerAddr>,
+        size: usize,
+        tls_config: Option<TlsConfig>,
+        auth_config: Option<AuthConfig>,
+        connect_timeout: Duration,
+        request_timeout: Duration,
+    ) -> Self {
         let (tx, rx) = async_channel::bounded(size);
+        Self {
+            addr: addr.into(),
+            pool: rx,
+            pool_sender: tx,
+            size,
+            tls_config,
+            auth_config,
+            connect_timeout,
+            request_timeout,
+            stats: Arc::default(),
+            acquire_timeout: DEFAULT_ACQUIRE_TIMEOUT,
+            live: Arc::default(),
+            in_use: Arc::default(),
+            detect_protocol: false,
+            auth_refresh_threshold: DEFAULT_AUTH_REFRESH_THRESHOLD,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            checksums: false,
+            database: Arc::default(),
+            write_buffer: 0,
+            auth_epoch: Arc::default(),
+            role: NodeRole::Primary,
+            idle_timeout: None,
+            max_lifetime: None,
+            #[cfg(feature = "compression")]
+            compression: None,
+        }
+    }
 
-        // Initialize connections
-        for _ in 0..size {
-            let conn = Connection::connect_with_config(addr, tls_config.clone(), auth_config.clone()).await?;
-            tx.send(conn)
+    /// Open connections until the pool is full
+    async fn fill(self) -> Result<Self> {
+        while self.reserve_slot() {
+            let conn = self.connect().await?;
+            self.pool_sender.send(conn)
                 .await
                 .map_err(|e| Error::Connection(e.to_string()))?;
         }
+        Ok(self)
+    }
 
-        Ok(Self {
-            addr,
-            pool: rx,
-            pool_sender: tx,
-            size,
-        })
+    /// Set how long [`get`](ConnectionPool::get) waits for a free connection
+    /// before failing with [`Error::PoolExhausted`]
+    pub fn with_acquire_timeout(mut self, acquire_timeout: Duration) -> Self {
+        self.acquire_timeout = acquire_timeout;
+        self
     }
 
-    /// Get a connection from the pool
-    pub async fn get(&self) -> Result<ConnectionGuard> {
-        let conn = self
-            .pool
-            .recv()
-            .await
-            .map_err(|e| Error::Connection(e.to_string()))?;
-        Ok(ConnectionGuard {
-            conn: Some(conn),
-            pool: self.pool_sender.clone(),
-        })
+    /// Detect the server's protocol version on each new connection
+    ///
+    /// See [`Connection::detect_protocol_version`]. Detection happens before
+    /// authentication and costs one extra round trip per connection.
+    pub fn with_protocol_detection(mut self, enabled: bool) -> Self {
+        self.detect_protocol = enabled;
+        self
     }
 
-    /// Get the number of connections in the pool
-    pub fn size(&self) -> usize {
-        self.size
+    /// Set how long before the auth token expires connections re-authenticate
+    pub fn with_auth_refresh_threshold(mut self, threshold: Duration) -> Self {
+        self.auth_refresh_threshold = threshold;
+        self
     }
-}
 
-/// A guard that returns a connection to the pool when dropped
-pub struct ConnectionGuard {
-    /// The connection
-    conn: Option<Connection>,
+    /// Set the largest frame new connections accept
+    ///
+    /// See [`Connection::set_max_frame_size`].
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size.min(MAX_FRAME_SIZE_LIMIT);
+        self
+    }
+
+    /// Checksum the commands of new connections
+    ///
+    /// See [`Connection::set_checksums`].
+    pub fn with_checksums(mut self, enabled: bool) -> Self {
+        self.checksums = enabled;
+        self
+    }
+
+    /// Coalesce concurrent writes on new connections
+    ///
+    /// See [`Connection::set_write_buffer`].
+    pub fn with_write_buffer(mut self, size: usize) -> Self {
+        self.write_buffer = size;
+        self
+    }
+
+    /// Mark the server as the primary (the default) or a replica
+    ///
+    /// New connections report this through [`Connection::role`].
+    pub fn with_role(mut self, role: NodeRole) -> Self {
+        self.role = role;
+        self
+    }
+
+    /// Get whether the pool's server is the primary or a replica
+    pub fn role(&self) -> NodeRole {
+        self.role
+    }
+
+    /// Replace connections that have sat idle in the pool for longer than
+    /// `idle_timeout` when they are next checked out
+    ///
+    /// Servers close connections that are idle for too long; set this below
+    /// the server's idle timeout so that the first request after a quiet
+    /// period doesn't fail on a connection the server is closing.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Replace connections that have been open for longer than
+    /// `max_lifetime` when they are next checked out
+    ///
+    /// A hostname is resolved again for each new connection, so this bounds
+    /// how long the pool keeps talking to an address the hostname no longer
+    /// points at. Connections are retired one checkout at a time rather than
+    /// all at once.
+    pub fn with_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = Some(max_lifetime);
+        self
+    }
+
+    /// Run commands against logical database `db`
+    ///
+    /// See [`ConnectionPool::select`].
+    pub fn with_database(self, db: u32) -> Self {
+        self.database.store(db, Ordering::SeqCst);
+        self
+    }
+
+    /// Negotiate compression of values of at least `threshold` bytes on each
+    /// new connection, or disable it with `None`
+    ///
+    /// See [`Connection::negotiate_compression`].
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, threshold: Option<usize>) -> Self {
+        self.compression = threshold;
+        self
+    }
+
+    /// Take a connection out of the pool, waiting at most the acquire timeout
+    ///
+    /// An idle connection is preferred; otherwise a new one is opened if the
+    /// pool is below its size.
+    async fn acquire(&self) -> Result<Connection> {
+        if self.pool.is_closed() {
+            return Err(Error::NotConnected);
+        }
+        if let Ok(conn) = self.pool.try_recv() {
+            return Ok(conn);
+        }
+        if self.reserve_slot() {
+            return match self.connect().await {
+                Ok(conn) => Ok(conn),
+                Err(e) => {
+                    self.live.fetch_sub(1, Ordering::SeqCst);
+                    Err(e)
+                }
+            };
+        }
+
+        match timeout(self.acquire_timeout, self.pool.recv()).await {
+            Ok(conn) => conn.map_err(|e| Error::Connection(e.to_string())),
+            Err(_) => Err(Error::PoolExhausted),
+        }
+    }
+
+    /// Count a new connection against the pool size, if there is room for it
+    fn reserve_slot(&self) -> bool {
+        self.live
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |live| {
+                (live < self.size).then_some(live + 1)
+            })
+            .is_ok()
+    }
+
+    /// Open a new connection configured like the rest of the pool
+    async fn connect(&self) -> Result<Connection> {
+        let mut conn = if self.detect_protocol {
+            // The version must be known before authenticating
+            let mut conn = Connection::connect_with_timeouts(
+                self.addr.clone(),
+                self.tls_config.clone(),
+                None,
+                self.connect_timeout,
+                self.request_timeout,
+            )
+            .await?;
+            conn.set_max_frame_size(self.max_frame_size);
+            conn.detect_protocol_version().await?;
+            if let Some(auth_config) = self.auth_config.clone() {
+                conn.authenticate(auth_config).await?;
+            }
+            conn
+        } else {
+            Connection::connect_with_timeouts(
+                self.addr.clone(),
+                self.tls_config.clone(),
+                self.auth_config.clone(),
+                self.connect_timeout,
+                self.request_timeout,
+            )
+            .await?
+        };
+        conn.stats = self.stats.clone();
+        conn.auth_refresh_threshold = self.auth_refresh_threshold;
+        conn.set_max_frame_size(self.max_frame_size);
+        conn.set_checksums(self.checksums);
+        conn.set_write_buffer(self.write_buffer);
+        conn.auth_epoch.store(self.auth_epoch.load(Ordering::SeqCst), Ordering::SeqCst);
+        conn.role = self.role;
+
+        #[cfg(feature = "compression")]
+        if let Some(threshold) = self.compression {
+            if !conn.negotiate_compression(threshold).await? {
+                debug!("Server at {} doesn't support compression", self.addr);
+            }
+        }
+        Ok(conn)
+    }
+
+    /// Replace one pooled connection with a freshly opened one
+    ///
+    /// The new connection uses the pool's TLS configuration and timeouts and
+    /// re-authenticates, obtaining a fresh token. This waits for a connection
+    /// to be free; if reconnecting fails, the old connection is kept.
+    pub async fn reconnect_one(&self) -> Result<()> {
+        let old = self.acquire().await?;
+
+        let conn = match self.connect().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                let _ = self.pool_sender.try_send(old);
+                return Err(e);
+            }
+        };
+        drop(old);
+
+        self.pool_sender
+            .send(conn)
+            .await
+            .map_err(|e| Error::Connection(e.to_string()))
+    }
+
+    /// Get a connection from the pool
+    ///
+    /// A connection the server has closed, that has been idle for longer
+    /// than the [idle timeout](ConnectionPool::with_idle_timeout), or that is
+    /// older than the [maximum lifetime](ConnectionPool::with_max_lifetime),
+    /// is transparently replaced with a new one before it is handed out.
+    pub async fn get(&self) -> Result<ConnectionGuard> {
+        let mut conn = self.acquire().await?;
+
+        let idle = self.idle_timeout.is_some_and(|limit| conn.last_used.elapsed() > limit);
+        let expired = self.max_lifetime.is_some_and(|limit| conn.opened_at.elapsed() > limit);
+        let stale = if idle {
+            debug!("Pooled connection to {} was idle too long, reconnecting", self.addr);
+            true
+        } else if expired {
+            debug!("Pooled connection to {} reached its maximum lifetime, reconnecting", self.addr);
+            true
+        } else if !conn.is_alive().await {
+            warn!("Pooled connection to {} was closed, reconnecting", self.addr);
+            true
+        } else {
+            false
+        };
+        if stale {
+            match self.connect().await {
+                Ok(fresh) => {
+                    let old = std::mem::replace(&mut conn, fresh);
+                    if idle || expired {
+                        // Still open as far as we know, so close it cleanly
+                        tokio::spawn(async move {
+                            if let Err(e) = old.close().await {
+                                debug!("Failed to close idle connection: {}", e);
+                            }
+                        });
+                    }
+                }
+                Err(e) => {
+                    // Keep the slot so a later checkout can retry
+                    let _ = self.pool_sender.try_send(conn);
+                    return Err(e);
+                }
+            }
+        }
+
+        self.in_use.fetch_add(1, Ordering::SeqCst);
+        let guard = ConnectionGuard {
+            conn: Some(conn),
+            pool: self.pool_sender.clone(),
+            live: self.live.clone(),
+            in_use: self.in_use.clone(),
+        };
+
+        // On failure the guard hands the connection back unchanged, and the
+        // next checkout tries again
+        let epoch = self.auth_epoch.load(Ordering::SeqCst);
+        if guard.auth_epoch.load(Ordering::SeqCst) != epoch {
+            guard.refresh_auth().await?;
+            guard.auth_epoch.store(epoch, Ordering::SeqCst);
+        }
+        let db = self.database();
+        if guard.database() != db {
+            guard.select(db).await?;
+        }
+        Ok(guard)
+    }
+
+    /// Re-authenticate every connection in the pool, so they all pick up
+    /// permission changes such as a new role
+    ///
+    /// Idle connections re-authenticate straight away. Connections that are
+    /// checked out re-authenticate when they are next checked out, so until
+    /// then operations already running on them keep the old token. Fails
+    /// with [`Error::InvalidArgument`] if the pool has no credentials.
+    pub async fn refresh_auth(&self) -> Result<()> {
+        if self.auth_config.is_none() {
+            return Err(Error::InvalidArgument("No credentials to re-authenticate with".to_string()));
+        }
+        self.auth_epoch.fetch_add(1, Ordering::SeqCst);
+        // Each checkout catches one idle connection up and returns it to the
+        // back of the queue
+        for _ in 0..self.pool.len() {
+            self.get().await?;
+        }
+        Ok(())
+    }
+
+    /// Switch the pool to logical database `db`
+    ///
+    /// One connection is switched straight away, so an unknown database or
+    /// a server without database support fails here. Every other connection
+    /// is switched when it is next checked out, so all operations started
+    /// after this returns run against `db`; operations already running on
+    /// checked-out connections finish on the previous database.
+    pub async fn select(&self, db: u32) -> Result<()> {
+        let conn = self.get().await?;
+        conn.select(db).await?;
+        self.database.store(db, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Get the logical database the pool's connections run against
+    pub fn database(&self) -> u32 {
+        self.database.load(Ordering::SeqCst)
+    }
+
+    /// Close the pool and every connection in it
+    ///
+    /// Idle connections are closed immediately; connections that are checked
+    /// out are closed when they are returned. Later checkouts fail with
+    /// [`Error::NotConnected`]. The pool is shared by every clone, so this
+    /// closes it for all of them. If closing a connection fails, the rest are
+    /// still closed and the first error is returned.
+    pub async fn close(&self) -> Result<()> {
+        self.pool.close();
+
+        let mut result = Ok(());
+        while let Ok(conn) = self.pool.try_recv() {
+            self.live.fetch_sub(1, Ordering::SeqCst);
+            if let Err(e) = conn.close().await {
+                warn!("Failed to close pooled connection to {}: {}", self.addr, e);
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Get the number of connections in the pool
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get the number of connections currently open, idle or checked out
+    pub fn live_connections(&self) -> usize {
+        self.live.load(Ordering::SeqCst)
+    }
+
+    /// Get how many of the pool's connections are open, idle and checked out
+    ///
+    /// The counts are read one after another while other tasks may be
+    /// checking connections in and out, so they are approximate under load.
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            total: self.live_connections(),
+            available: self.pool.len(),
+            in_use: self.in_use.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// A guard that returns a connection to the pool when dropped
+pub struct ConnectionGuard {
+    /// The connection
+    conn: Option<Connection>,
     /// The connection pool
     pool: async_channel::Sender<Connection>,
+    /// The pool's count of open connections
+    live: Arc<AtomicUsize>,
+    /// The pool's count of checked-out connections
+    in_use: Arc<AtomicUsize>,
 }
 
 impl ConnectionGuard {
@@ -1430,13 +4447,43 @@ impl ConnectionGuard {
 
 impl Drop for ConnectionGuard {
     fn drop(&mut self) {
-        if let Some(conn) = self.conn.take() {
-            let pool = self.pool.clone();
-            tokio::spawn(async move {
-                if let Err(e) = pool.send(conn).await {
-                    error!("Failed to return connection to pool: {}", e);
+        let Some(mut conn) = self.conn.take() else { return };
+        self.in_use.fetch_sub(1, Ordering::SeqCst);
+        conn.last_used = Instant::now();
+
+        // The channel holds one slot per live connection, so returning a
+        // connection synchronously only fails once the pool is closed
+        let conn = match self.pool.try_send(conn) {
+            Ok(()) => return,
+            Err(async_channel::TrySendError::Full(conn)) => conn,
+            Err(async_channel::TrySendError::Closed(conn)) => {
+                // A closed pool takes no connections back; close this one
+                self.live.fetch_sub(1, Ordering::SeqCst);
+                if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                    handle.spawn(async move {
+                        if let Err(e) = conn.close().await {
+                            debug!("Failed to close connection returned to a closed pool: {}", e);
+                        }
+                    });
                 }
-            });
+                return;
+            }
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let pool = self.pool.clone();
+                let live = self.live.clone();
+                handle.spawn(async move {
+                    if pool.send(conn).await.is_err() {
+                        live.fetch_sub(1, Ordering::SeqCst);
+                    }
+                });
+            }
+            Err(_) => {
+                debug!("Dropping connection returned outside a runtime to a full pool");
+                self.live.fetch_sub(1, Ordering::SeqCst);
+            }
         }
     }
 }
@@ -1455,28 +4502,234 @@ impl std::ops::DerefMut for ConnectionGuard {
     }
 }
 
-/// TLS certificate verifier that accepts all certificates (for testing only)
-struct AcceptAllVerifier;
+/// A registered wait for the response to one request
+///
+/// Dropping it, e.g. when the caller times out or is cancelled, stops the
+/// wait, so a late response is discarded rather than left in the map.
+struct PendingResponse<'a> {
+    shared: &'a Shared,
+    seq: u32,
+    rx: oneshot::Receiver<Result<Response>>,
+}
+
+impl<'a> PendingResponse<'a> {
+    fn register(shared: &'a Shared, seq: u32) -> Result<Option<Self>> {
+        Ok(shared.register(seq)?.map(|rx| Self { shared, seq, rx }))
+    }
+
+    /// Wait at most `limit` for the response
+    async fn wait(self, limit: Duration) -> Result<Response> {
+        timeout(limit, self.wait_forever()).await.map_err(Error::Timeout)?
+    }
+
+    /// Wait for the response until the connection closes
+    async fn wait_forever(mut self) -> Result<Response> {
+        (&mut self.rx)
+            .await
+            .unwrap_or_else(|_| Err(Error::Connection("Connection closed".to_string())))
+    }
+}
+
+impl Drop for PendingResponse<'_> {
+    fn drop(&mut self) {
+        self.shared.forget(self.seq);
+    }
+}
 
-impl rustls::client::ServerCertVerifier for AcceptAllVerifier {
-    fn verify_server_cert(
-        &self,
-        _end_entity: &rustls::Certificate,
-        _intermediates: &[rustls::Certificate],
-        _server_name: &rustls::ServerName,
-        _scts: &mut dyn Iterator<Item = &[u8]>,
-        _ocsp_response: &[u8],
-        _now: std::time::SystemTime,
-    ) -> std::result::Result<rustls::client::ServerCertVerified, rustls::Error> {
-        Ok(rustls::client::ServerCertVerified::assertion())
+/// A write to the socket in progress
+///
+/// Dropping it before [`complete`](PartialWrite::complete), because the
+/// write failed, timed out or was cancelled, closes the connection: part of
+/// a frame may have been sent, and the server would read the next frame's
+/// bytes as its remainder.
+struct PartialWrite<'a> {
+    shared: &'a Shared,
+    complete: bool,
+}
+
+impl<'a> PartialWrite<'a> {
+    fn new(shared: &'a Shared) -> Self {
+        Self { shared, complete: false }
+    }
+
+    fn complete(&mut self) {
+        self.complete = true;
+    }
+}
+
+impl Drop for PartialWrite<'_> {
+    fn drop(&mut self) {
+        if !self.complete {
+            self.shared.fail(None, Error::Connection("write interrupted partway through a frame".to_string()));
+        }
+    }
+}
+
+/// Read a little-endian `u32` from a response header
+fn u32_at(header: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([header[offset], header[offset + 1], header[offset + 2], header[offset + 3]])
+}
+
+/// Check a response payload length against the frame size limit
+fn frame_len(payload_len: u32, max: usize) -> Result<usize> {
+    if payload_len as usize > max {
+        return Err(Error::FrameTooLarge {
+            len: payload_len as usize,
+            max,
+        });
     }
+    Ok(payload_len as usize)
+}
+
+/// Reject a command too large for a single frame
+fn check_request_size(cmd: &Command, max: usize) -> Result<()> {
+    let request_len = cmd.encoded_len();
+    if request_len > max {
+        return Err(Error::InvalidArgument(format!(
+            "request exceeds max frame size ({} > {} bytes)",
+            request_len, max
+        )));
+    }
+    Ok(())
+}
+
+/// Turn a non-OK response into the matching error
+fn check_status(response: Response) -> Result<Response> {
+    if response.is_ok() {
+        return Ok(response);
+    }
+    let error_msg = String::from_utf8_lossy(&response.payload).into_owned();
+    Err(Error::from_status(response.status(), error_msg))
+}
+
+/// Interpret an operation result as a hash, treating `Null` as empty
+fn hash_from_value(value: Value, op: &str) -> Result<BTreeMap<String, Value>> {
+    match value {
+        Value::Object(fields) => Ok(fields),
+        Value::Null => Ok(BTreeMap::new()),
+        other => Err(Error::InvalidResponse(format!("Expected an object from {}, got {:?}", op, other))),
+    }
+}
+
+/// Interpret an operation result as a count
+fn count_from_value(value: Value, op: &str) -> Result<u64> {
+    value
+        .as_u64()
+        .ok_or_else(|| Error::InvalidResponse(format!("Expected a count from {}, got {:?}", op, value)))
+}
+
+/// Interpret an operation result as a flag, accepting 0 and 1 as well as
+/// booleans
+fn bool_from_value(value: Value, op: &str) -> Result<bool> {
+    match value {
+        Value::Bool(flag) => Ok(flag),
+        Value::Int32(n @ (0 | 1)) => Ok(n == 1),
+        Value::Int64(n @ (0 | 1)) => Ok(n == 1),
+        other => Err(Error::InvalidResponse(format!("Expected a boolean from {}, got {:?}", op, other))),
+    }
+}
+
+/// Interpret an operation result as an array, treating `Null` as empty
+fn array_from_value(value: Value, op: &str) -> Result<Vec<Value>> {
+    match value {
+        Value::Array(values) => Ok(values),
+        Value::Null => Ok(Vec::new()),
+        other => Err(Error::InvalidResponse(format!("Expected an array from {}, got {:?}", op, other))),
+    }
+}
+
+/// Interpret an id reported by the server for an inserted document
+fn document_id_from_value(value: &Value) -> Result<DocumentId> {
+    match value {
+        Value::String(id) => DocumentId::parse_str(id),
+        other => Err(Error::InvalidResponse(format!("Invalid inserted id: {:?}", other))),
+    }
+}
+
+/// Interpret a scan result as the next cursor and a page of keys
+fn scan_page_from_value(value: Value) -> Result<(u64, Vec<String>)> {
+    let invalid = |value: &Value| Error::InvalidResponse(format!("Unexpected scan response: {:?}", value));
+    let obj = match &value {
+        Value::Object(obj) => obj,
+        other => return Err(invalid(other)),
+    };
+
+    let cursor = match obj.get("cursor").and_then(Value::as_i64) {
+        Some(cursor) if cursor >= 0 => cursor as u64,
+        _ => return Err(invalid(&value)),
+    };
+    let keys = match obj.get("keys") {
+        Some(Value::Array(keys)) => keys
+            .iter()
+            .map(|key| key.as_str().map(str::to_string).ok_or_else(|| invalid(&value)))
+            .collect::<Result<Vec<_>>>()?,
+        None | Some(Value::Null) => Vec::new(),
+        Some(_) => return Err(invalid(&value)),
+    };
+    Ok((cursor, keys))
+}
+
+/// Interpret an operation result as an optional value, mapping `Null` to `None`
+fn optional_value(value: Value) -> Option<Value> {
+    match value {
+        Value::Null => None,
+        value => Some(value),
+    }
+}
+
+/// Check whether `key` matches a glob (`*`, `?`) or substring pattern
+fn key_matches(key: &str, pattern: &str) -> bool {
+    if !pattern.contains(['*', '?']) {
+        return key.contains(pattern);
+    }
+
+    let key: Vec<char> = key.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut k, mut p) = (0, 0);
+    // Position of the last `*` in the pattern and the key index it matched up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while k < key.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, k));
+                p += 1;
+            }
+            Some('?') => {
+                k += 1;
+                p += 1;
+            }
+            Some(&c) if c == key[k] => {
+                k += 1;
+                p += 1;
+            }
+            _ => match star {
+                Some((star_p, star_k)) => {
+                    p = star_p + 1;
+                    k = star_k + 1;
+                    star = Some((star_p, star_k + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Read from the stream, waiting at most `limit`
+async fn read_with_timeout<F, T>(limit: Duration, read: F) -> Result<()>
+where
+    F: std::future::Future<Output = std::io::Result<T>>,
+{
+    timeout(limit, read).await.map_err(Error::Timeout)??;
+    Ok(())
 }
 
 /// A builder for configuring and creating a client
 #[derive(Debug, Clone)]
 pub struct ClientBuilder {
     /// The server address
-    addr: SocketAddr,
+    addr: ServerAddr,
     /// The connection pool size
     pool_size: usize,
     /// The connection timeout
@@ -1487,6 +4740,37 @@ pub struct ClientBuilder {
     tls_config: Option<TlsConfig>,
     /// Authentication configuration
     auth_config: Option<AuthConfig>,
+    /// Whether to reserve a connection for pings
+    dedicated_ping: bool,
+    /// How long to wait for a free pooled connection
+    acquire_timeout: Duration,
+    /// How long pooled connections may sit idle before being replaced
+    idle_timeout: Option<Duration>,
+    /// How long pooled connections may stay open before being replaced
+    max_connection_lifetime: Option<Duration>,
+    /// Whether to open pooled connections on demand
+    lazy: bool,
+    /// Whether to detect the server's protocol version on connect
+    detect_protocol: bool,
+    /// How long before token expiry to re-authenticate
+    auth_refresh_threshold: Duration,
+    /// How idempotent operations are retried, if at all
+    retry: Option<RetryPolicy>,
+    /// Largest frame connections accept
+    max_frame_size: usize,
+    /// Whether commands carry a CRC32 checksum
+    checksums: bool,
+    /// Logical database to run commands against
+    database: u32,
+    /// Write coalescing buffer size, or 0 for none
+    write_buffer: usize,
+    /// Replica addresses
+    replicas: Vec<ServerAddr>,
+    /// Which servers reads go to
+    read_preference: ReadPreference,
+    /// Whether connections negotiate payload compression
+    #[cfg(feature = "compression")]
+    compression: bool,
 }
 
 impl Default for ClientBuilder {
@@ -1498,6 +4782,22 @@ impl Default for ClientBuilder {
             request_timeout: DEFAULT_REQUEST_TIMEOUT,
             tls_config: None,
             auth_config: None,
+            dedicated_ping: false,
+            acquire_timeout: DEFAULT_ACQUIRE_TIMEOUT,
+            idle_timeout: None,
+            max_connection_lifetime: None,
+            lazy: false,
+            detect_protocol: false,
+            auth_refresh_threshold: DEFAULT_AUTH_REFRESH_THRESHOLD,
+            retry: None,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            checksums: false,
+            database: 0,
+            write_buffer: 0,
+            replicas: Vec::new(),
+            read_preference: ReadPreference::Primary,
+            #[cfg(feature = "compression")]
+            compression: false,
         }
     }
 }
@@ -1509,7 +4809,7 @@ impl ClientBuilder {
     }
 
     /// Set the server address
-    pub fn addr(mut self, addr: impl Into<SocketAddr>) -> Self {
+    pub fn addr(mut self, addr: impl Into<ServerAddr>) -> Self {
         self.addr = addr.into();
         self
     }
@@ -1532,6 +4832,51 @@ impl ClientBuilder {
         self
     }
 
+    /// Set how long a request waits for a free pooled connection before
+    /// failing with [`Error::PoolExhausted`]
+    pub fn acquire_timeout(mut self, timeout: Duration) -> Self {
+        self.acquire_timeout = timeout;
+        self
+    }
+
+    /// Replace pooled connections idle for longer than `timeout` when they
+    /// are next checked out
+    ///
+    /// See [`ConnectionPool::with_idle_timeout`]. By default connections are
+    /// kept however long they sit idle.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Replace pooled connections open for longer than `lifetime` when they
+    /// are next checked out
+    ///
+    /// See [`ConnectionPool::with_max_lifetime`]. When the server is given by
+    /// hostname, this is how often connections pick up DNS changes, such as
+    /// a failover to a new IP. By default connections are kept until closed.
+    pub fn max_connection_lifetime(mut self, lifetime: Duration) -> Self {
+        self.max_connection_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Open pooled connections on first use instead of all at connect time
+    ///
+    /// The pool then grows up to its size as concurrent requests need more
+    /// connections, and [`connect`](ClientBuilder::connect) doesn't contact
+    /// the server unless a dedicated ping connection is requested.
+    pub fn lazy(mut self, enabled: bool) -> Self {
+        self.lazy = enabled;
+        self
+    }
+
+    /// Detect whether the server speaks the v0.1.x or v0.2.0 protocol on
+    /// each new connection, instead of assuming v0.2.0
+    pub fn detect_protocol(mut self, enabled: bool) -> Self {
+        self.detect_protocol = enabled;
+        self
+    }
+
     /// Set TLS configuration
     pub fn tls_config(mut self, tls_config: TlsConfig) -> Self {
         self.tls_config = Some(tls_config);
@@ -1544,13 +4889,1702 @@ impl ClientBuilder {
         self
     }
 
+    /// Set how long before the server-issued token expires each connection
+    /// re-authenticates with the configured credentials (default 60 seconds)
+    pub fn auth_refresh_threshold(mut self, threshold: Duration) -> Self {
+        self.auth_refresh_threshold = threshold;
+        self
+    }
+
+    /// Set the largest request or response frame, in bytes (default 16MB)
+    ///
+    /// Raise this if documents or query results exceed the default. Values
+    /// above 256MB are capped at 256MB.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Append a CRC32 checksum to every command (default off)
+    ///
+    /// Useful when debugging a flaky network: a corrupted response then
+    /// fails with a checksum mismatch instead of a confusing framing error.
+    /// See [`Connection::set_checksums`].
+    pub fn checksums(mut self, enabled: bool) -> Self {
+        self.checksums = enabled;
+        self
+    }
+
+    /// Coalesce concurrent writes of up to `size` bytes per connection
+    /// (default 0, off)
+    ///
+    /// This pays off where several tasks send on one connection at once, such
+    /// as the [`dedicated_ping`](ClientBuilder::dedicated_ping) connection;
+    /// a pooled connection serves one checkout at a time. See
+    /// [`Connection::set_write_buffer`].
+    pub fn write_buffer(mut self, size: usize) -> Self {
+        self.write_buffer = size;
+        self
+    }
+
+    /// Run commands against logical database `db` (default 0)
+    ///
+    /// See [`Client::select`].
+    pub fn database(mut self, db: u32) -> Self {
+        self.database = db;
+        self
+    }
+
+    /// Add a replica that reads may be sent to, per the
+    /// [`read_preference`](ClientBuilder::read_preference)
+    ///
+    /// Each replica gets its own pool, configured like the primary's. The
+    /// TLS settings are shared too, so with TLS leave
+    /// [`TlsConfig::server_name`] unset to verify each server by its own
+    /// hostname.
+    pub fn replica(mut self, addr: impl Into<ServerAddr>) -> Self {
+        self.replicas.push(addr.into());
+        self
+    }
+
+    /// Choose which servers reads go to (default [`ReadPreference::Primary`])
+    ///
+    /// Reads are the commands [`OpCode::is_read`] accepts; writes always go
+    /// to the primary.
+    pub fn read_preference(mut self, preference: ReadPreference) -> Self {
+        self.read_preference = preference;
+        self
+    }
+
+    /// Compress large payloads if the server supports it (default off)
+    ///
+    /// Each connection negotiates compression when it is opened; values of
+    /// 1KB or more are then sent zstd-compressed, and the server may compress
+    /// its responses. Smaller values are sent as-is.
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Retry idempotent operations that fail with a retryable error
+    ///
+    /// Each operation is attempted up to `max_attempts` times in total, on a
+    /// fresh pooled connection each time, with exponential backoff from
+    /// `initial_backoff` up to `max_backoff` plus jitter. Only pings, `set`,
+    /// reads and queries are retried; writes that aren't safe to repeat, such
+    /// as `incr`, inserts or list pushes, fail on the first error. Retries
+    /// are off by default.
+    pub fn retry(mut self, max_attempts: u32, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.retry = Some(RetryPolicy::new(max_attempts, initial_backoff, max_backoff));
+        self
+    }
+
+    /// Reserve an extra connection, outside the pool, for [`Client::ping`]
+    ///
+    /// Pings then don't wait behind queued requests when the pool is busy, so
+    /// liveness checks reflect the server rather than client-side load.
+    pub fn dedicated_ping(mut self, enabled: bool) -> Self {
+        self.dedicated_ping = enabled;
+        self
+    }
+
     /// Build and connect the client
     pub async fn connect(self) -> Result<Client> {
-        let pool = ConnectionPool::new(self.addr, self.pool_size, self.tls_config.clone(), self.auth_config.clone()).await?;
+        let pool = self.pool(self.addr.clone(), NodeRole::Primary, Arc::default()).await?;
+        let ping_conn = if self.dedicated_ping {
+            Some(Arc::new(Mutex::new(pool.connect().await?)))
+        } else {
+            None
+        };
+
+        let replicas = if self.replicas.is_empty() {
+            None
+        } else {
+            let mut pools = Vec::with_capacity(self.replicas.len());
+            for addr in &self.replicas {
+                // Replica requests count towards the client's stats
+                pools.push(self.pool(addr.clone(), NodeRole::Secondary, pool.stats.clone()).await?);
+            }
+            Some(Arc::new(Replicas::new(pools, self.read_preference)))
+        };
+
         Ok(Client { 
             pool,
             tls_config: self.tls_config,
             auth_config: self.auth_config,
+            ping_conn,
+            retry: self.retry,
+            replicas,
+            schemas: None,
         })
     }
+
+    /// Build a pool of connections to `addr`, filled unless lazy
+    async fn pool(&self, addr: ServerAddr, role: NodeRole, stats: Arc<StatsCounters>) -> Result<ConnectionPool> {
+        let mut pool = ConnectionPool::lazy(
+            addr,
+            self.pool_size,
+            self.tls_config.clone(),
+            self.auth_config.clone(),
+            self.connect_timeout,
+            self.request_timeout,
+        )
+        .with_acquire_timeout(self.acquire_timeout)
+        .with_protocol_detection(self.detect_protocol)
+        .with_auth_refresh_threshold(self.auth_refresh_threshold)
+        .with_max_frame_size(self.max_frame_size)
+        .with_checksums(self.checksums)
+        .with_database(self.database)
+        .with_write_buffer(self.write_buffer)
+        .with_role(role);
+        if let Some(idle_timeout) = self.idle_timeout {
+            pool = pool.with_idle_timeout(idle_timeout);
+        }
+        if let Some(max_lifetime) = self.max_connection_lifetime {
+            pool = pool.with_max_lifetime(max_lifetime);
+        }
+        pool.stats = stats;
+        #[cfg(feature = "compression")]
+        {
+            pool = pool.with_compression(self.compression.then_some(crate::compression::DEFAULT_THRESHOLD));
+        }
+        if !self.lazy {
+            pool = pool.fill().await?;
+        }
+        Ok(pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CommandHeader, StatusCode};
+    use tokio::net::TcpListener;
+
+    /// Encode a v0.2.0 response frame
+    fn response_frame(status: StatusCode, seq: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![status as u8, 0, 0, 0];
+        frame.extend_from_slice(&seq.to_le_bytes());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&[0; 4]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Encode a v0.1.x response frame, whose header ends in an 8-byte field
+    fn legacy_response_frame(status: StatusCode, seq: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![status as u8, 0, 0, 0];
+        frame.extend_from_slice(&seq.to_le_bytes());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&[0; 8]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Read one command from the client, returning its header
+    async fn read_command(socket: &mut TcpStream) -> CommandHeader {
+        let mut header = [0u8; CommandHeader::SIZE];
+        socket.read_exact(&mut header).await.unwrap();
+        let header = CommandHeader::from_bytes(&header).unwrap();
+        let mut body = vec![0u8; (header.key_len + header.value_len) as usize];
+        socket.read_exact(&mut body).await.unwrap();
+        header
+    }
+
+    #[tokio::test]
+    async fn test_detect_protocol_version() {
+        for version in [PROTOCOL_V1, PROTOCOL_V2] {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = tokio::spawn(async move {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                // Two detection pings, then a regular one
+                for _ in 0..3 {
+                    let header = read_command(&mut socket).await;
+                    let frame = if version == PROTOCOL_V1 {
+                        legacy_response_frame(StatusCode::Ok, header.seq, b"PONG")
+                    } else {
+                        response_frame(StatusCode::Ok, header.seq, b"PONG")
+                    };
+                    socket.write_all(&frame).await.unwrap();
+                }
+            });
+
+            let mut conn = Connection::connect(addr).await.unwrap();
+            assert_eq!(conn.detect_protocol_version().await.unwrap(), version);
+            assert_eq!(conn.protocol_version(), version);
+            conn.ping().await.unwrap();
+            server.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_requests_leave_pool_usable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // Answer the first request late, after the caller gave up on it
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let first = read_command(&mut socket).await;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            socket.write_all(&response_frame(StatusCode::Ok, first.seq, b"first")).await.unwrap();
+            let second = read_command(&mut socket).await;
+            socket.write_all(&response_frame(StatusCode::Ok, second.seq, b"second")).await.unwrap();
+
+            // Stop reading, so a large write stalls partway
+            let (mut fresh, _) = listener.accept().await.unwrap();
+            let third = read_command(&mut fresh).await;
+            fresh.write_all(&response_frame(StatusCode::Ok, third.seq, b"third")).await.unwrap();
+            socket
+        });
+
+        let client = ClientBuilder::new().addr(addr).pool_size(1).connect().await.unwrap();
+        let cancelled = tokio::time::timeout(Duration::from_millis(20), client.get("first")).await;
+        assert!(cancelled.is_err());
+        assert_eq!(client.get("second").await.unwrap(), Bytes::from_static(b"second"));
+
+        let large = vec![0u8; 15 * 1024 * 1024];
+        let cancelled = tokio::time::timeout(Duration::from_millis(50), client.set("large", large)).await;
+        assert!(cancelled.is_err());
+        assert_eq!(client.get("third").await.unwrap(), Bytes::from_static(b"third"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_connections_are_replaced() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut connections = 0;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                connections += 1;
+                tokio::spawn(async move {
+                    loop {
+                        let mut header = [0u8; CommandHeader::SIZE];
+                        if socket.read_exact(&mut header).await.is_err() {
+                            return;
+                        }
+                        let header = CommandHeader::from_bytes(&header).unwrap();
+                        socket.write_all(&response_frame(StatusCode::Ok, header.seq, b"")).await.unwrap();
+                    }
+                });
+                if connections == 2 {
+                    return connections;
+                }
+            }
+            connections
+        });
+
+        let client = ClientBuilder::new()
+            .addr(addr)
+            .pool_size(1)
+            .idle_timeout(Duration::from_millis(50))
+            .connect()
+            .await
+            .unwrap();
+        client.ping().await.unwrap();
+        client.ping().await.unwrap();
+        assert!(!server.is_finished());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        client.ping().await.unwrap();
+        assert_eq!(server.await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_connections_are_replaced() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let mut connections = 0;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                connections += 1;
+                tokio::spawn(async move {
+                    loop {
+                        let mut header = [0u8; CommandHeader::SIZE];
+                        if socket.read_exact(&mut header).await.is_err() {
+                            return;
+                        }
+                        let header = CommandHeader::from_bytes(&header).unwrap();
+                        socket.write_all(&response_frame(StatusCode::Ok, header.seq, b"")).await.unwrap();
+                    }
+                });
+                if connections == 2 {
+                    return connections;
+                }
+            }
+            connections
+        });
+
+        let client = ClientBuilder::new()
+            .addr(("localhost", port))
+            .pool_size(1)
+            .max_connection_lifetime(Duration::from_millis(300))
+            .connect()
+            .await
+            .unwrap();
+        // Busy, so never idle, but retired once it is old enough
+        for _ in 0..4 {
+            client.ping().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!server.is_finished());
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        client.ping().await.unwrap();
+        assert_eq!(server.await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_dedicated_ping_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (pooled, _) = listener.accept().await.unwrap();
+            // Answer one ping on the reserved connection, then drop it as a
+            // restarting server would
+            for _ in 0..2 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, crate::types::OpCode::Ping as u8);
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, b"PONG")).await.unwrap();
+            }
+            pooled
+        });
+
+        let client = ClientBuilder::new()
+            .addr(addr)
+            .pool_size(1)
+            .dedicated_ping(true)
+            .connect()
+            .await
+            .unwrap();
+        client.ping().await.unwrap();
+        client.ping().await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_versioned() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let header = read_command(&mut socket).await;
+            assert_eq!(header.opcode, OpCode::Get as u8);
+            let mut frame = legacy_response_frame(StatusCode::Ok, header.seq, b"value");
+            frame[12..20].copy_from_slice(&7u64.to_le_bytes());
+            socket.write_all(&frame).await.unwrap();
+
+            let header = read_command(&mut socket).await;
+            assert_eq!(header.opcode, OpCode::Get as u8);
+            let frame = legacy_response_frame(StatusCode::NotFound, header.seq, b"");
+            socket.write_all(&frame).await.unwrap();
+        });
+
+        let mut conn = Connection::connect(addr).await.unwrap();
+        conn.set_protocol_version(PROTOCOL_V1);
+        let (value, version) = conn.get_versioned("key").await.unwrap();
+        assert_eq!(value, Bytes::from_static(b"value"));
+        assert_eq!(version, 7);
+        assert!(matches!(conn.get_versioned("missing").await, Err(Error::KeyNotFound)));
+
+        conn.set_protocol_version(PROTOCOL_V2);
+        assert!(matches!(conn.get_versioned("key").await, Err(Error::NotSupported)));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout_covers_tls_and_auth() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // Never answer the TLS handshake
+            let (socket, _) = listener.accept().await.unwrap();
+            // Read the auth request, but never reply
+            let (mut stalled, _) = listener.accept().await.unwrap();
+            read_command(&mut stalled).await;
+            (socket, stalled)
+        });
+
+        let connect = |tls_config, auth_config| {
+            Connection::connect_with_timeouts(
+                addr,
+                tls_config,
+                auth_config,
+                Duration::from_millis(100),
+                Duration::from_secs(60),
+            )
+        };
+        let handshake = connect(Some(TlsConfig::new("localhost")), None).await;
+        assert!(matches!(handshake, Err(Error::Timeout(_))));
+        let auth = connect(None, Some(AuthConfig::username_password("user", "pass"))).await;
+        assert!(matches!(auth, Err(Error::Timeout(_))));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_is_configurable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // Accept and read the request, but never reply
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_command(&mut socket).await;
+            socket
+        });
+
+        let mut conn = Connection::connect_with_timeouts(
+            addr,
+            None,
+            None,
+            DEFAULT_CONNECT_TIMEOUT,
+            Duration::from_secs(60),
+        )
+        .await
+        .unwrap();
+        conn.set_request_timeout(Duration::from_millis(50));
+
+        let started = std::time::Instant::now();
+        assert!(matches!(conn.ping().await, Err(Error::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        drop(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_request_leaves_connection_usable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let slow = read_command(&mut socket).await;
+            let next = read_command(&mut socket).await;
+            // Answer the timed-out request late, then the next one
+            socket.write_all(&response_frame(StatusCode::Ok, slow.seq, b"late")).await.unwrap();
+            socket.write_all(&response_frame(StatusCode::Ok, next.seq, b"pong")).await.unwrap();
+        });
+
+        let conn = Connection::connect(addr).await.unwrap();
+        let slow = Command::ping(conn.next_seq());
+        assert!(matches!(
+            conn.execute_with_timeout(slow, Duration::from_millis(50)).await,
+            Err(Error::Timeout(_))
+        ));
+
+        let response = conn.execute(Command::ping(conn.next_seq())).await.unwrap();
+        assert_eq!(&response.payload[..], b"pong");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_seq_wraparound_skips_zero_and_in_flight() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let slow = read_command(&mut socket).await;
+            assert_eq!(slow.seq, u32::MAX);
+            // 0 is skipped and 1 is still outstanding
+            let wrapped = read_command(&mut socket).await;
+            assert_eq!(wrapped.seq, 2);
+            socket.write_all(&response_frame(StatusCode::Ok, wrapped.seq, b"pong")).await.unwrap();
+            // The timed-out request's seq isn't reused before its late response
+            let reused = read_command(&mut socket).await;
+            assert_eq!(reused.seq, 3);
+            socket.write_all(&response_frame(StatusCode::Ok, slow.seq, b"late")).await.unwrap();
+            socket.write_all(&response_frame(StatusCode::Ok, reused.seq, b"pong")).await.unwrap();
+        });
+
+        let conn = Connection::connect(addr).await.unwrap();
+        let _outstanding = PendingResponse::register(&conn.shared, 1).unwrap().unwrap();
+        conn.next_seq.store(u32::MAX, Ordering::SeqCst);
+        let slow = Command::ping(conn.next_seq());
+        assert!(matches!(
+            conn.execute_with_timeout(slow, Duration::from_millis(50)).await,
+            Err(Error::Timeout(_))
+        ));
+
+        let response = conn.execute(Command::ping(conn.next_seq())).await.unwrap();
+        assert_eq!(&response.payload[..], b"pong");
+        let response = conn.execute(Command::ping(u32::MAX)).await.unwrap();
+        assert_eq!(&response.payload[..], b"pong");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pool_replaces_closed_connection_on_checkout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (closed_tx, closed_rx) = tokio::sync::oneshot::channel();
+        let server = tokio::spawn(async move {
+            // Close the first connection, then serve a ping on its replacement
+            let (first, _) = listener.accept().await.unwrap();
+            drop(first);
+            closed_tx.send(()).unwrap();
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let ping = read_command(&mut socket).await;
+            socket.write_all(&response_frame(StatusCode::Ok, ping.seq, b"")).await.unwrap();
+        });
+
+        let pool = ConnectionPool::new(addr, 1, None, None).await.unwrap();
+        closed_rx.await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let conn = pool.get().await.unwrap();
+        conn.ping().await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pool_reconnect_one() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (_first, _) = listener.accept().await.unwrap();
+            let (mut second, _) = listener.accept().await.unwrap();
+            let ping = read_command(&mut second).await;
+            second.write_all(&response_frame(StatusCode::Ok, ping.seq, b"")).await.unwrap();
+        });
+
+        let pool = ConnectionPool::new(addr, 1, None, None).await.unwrap();
+        pool.reconnect_one().await.unwrap();
+        pool.get().await.unwrap().ping().await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pool_acquire_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+        let pool = ConnectionPool::new(addr, 1, None, None)
+            .await
+            .unwrap()
+            .with_acquire_timeout(Duration::from_millis(50));
+        let held = pool.get().await.unwrap();
+        assert!(matches!(pool.get().await, Err(Error::PoolExhausted)));
+
+        drop(held);
+        assert!(pool.get().await.is_ok());
+        drop(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_lazy_pool_connects_on_demand() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let pool = ConnectionPool::lazy(
+            addr,
+            2,
+            None,
+            None,
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_REQUEST_TIMEOUT,
+        );
+        assert_eq!(pool.live_connections(), 0);
+
+        let first = pool.get().await.unwrap();
+        let second = pool.get().await.unwrap();
+        assert_eq!(pool.live_connections(), 2);
+        let _accepted = (listener.accept().await.unwrap(), listener.accept().await.unwrap());
+
+        // At the cap, checkout waits for a connection to come back
+        drop(first);
+        let _reused = pool.get().await.unwrap();
+        assert_eq!(pool.live_connections(), 2);
+        drop(second);
+    }
+
+    #[tokio::test]
+    async fn test_key_expiry() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+
+            let header = read_command(&mut socket).await;
+            assert_eq!(header.opcode, crate::types::OpCode::Set as u8);
+            assert_ne!(header.flags & crate::types::flags::TTL, 0);
+            assert_eq!(header.extra, 1500);
+            socket.write_all(&response_frame(StatusCode::Ok, header.seq, b"")).await.unwrap();
+
+            // A zero TTL deletes the key
+            let header = read_command(&mut socket).await;
+            assert_eq!(header.opcode, crate::types::OpCode::Delete as u8);
+            socket.write_all(&response_frame(StatusCode::NotFound, header.seq, b"")).await.unwrap();
+
+            let header = read_command(&mut socket).await;
+            assert_eq!(header.opcode, crate::types::OpCode::Ttl as u8);
+            socket.write_all(&response_frame(StatusCode::Ok, header.seq, &2000u64.to_le_bytes())).await.unwrap();
+
+            let header = read_command(&mut socket).await;
+            socket.write_all(&response_frame(StatusCode::Ok, header.seq, b"")).await.unwrap();
+        });
+
+        let conn = Connection::connect(addr).await.unwrap();
+        conn.set_with_ttl("a", "1", Duration::from_millis(1500)).await.unwrap();
+        assert!(!conn.expire("gone", Duration::ZERO).await.unwrap());
+        assert_eq!(conn.ttl("a").await.unwrap(), Some(Duration::from_secs(2)));
+        assert_eq!(conn.ttl("b").await.unwrap(), None);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_incr_by() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let header = read_command(&mut socket).await;
+            assert_eq!(header.extra as i64, -3);
+            socket.write_all(&response_frame(StatusCode::Ok, header.seq, &7i64.to_le_bytes())).await.unwrap();
+
+            let header = read_command(&mut socket).await;
+            socket.write_all(&response_frame(StatusCode::WrongType, header.seq, b"not an integer")).await.unwrap();
+        });
+
+        let conn = Connection::connect(addr).await.unwrap();
+        assert_eq!(conn.incr_by("hits", -3).await.unwrap(), 7);
+        assert!(matches!(conn.incr_by("name", 1).await, Err(Error::WrongType(_))));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retries_idempotent_operations_only() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            use crate::types::OpCode::{Get, Incr};
+            let script = [
+                (Get, StatusCode::Full, &b"out of memory"[..]),
+                (Get, StatusCode::Ok, &b"value"[..]),
+                (Incr, StatusCode::Full, &b"out of memory"[..]),
+            ];
+            for (opcode, status, payload) in script {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, opcode as u8);
+                socket.write_all(&response_frame(status, header.seq, payload)).await.unwrap();
+            }
+        });
+
+        let client = ClientBuilder::new()
+            .addr(addr)
+            .pool_size(1)
+            .retry(3, Duration::from_millis(1), Duration::from_millis(5))
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(client.get("key").await.unwrap(), Bytes::from_static(b"value"));
+        assert!(matches!(client.incr("hits").await, Err(Error::ServerBusy(_))));
+        assert_eq!(client.client_stats().retries, 1);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pool_stats() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let _first = listener.accept().await.unwrap();
+            let _second = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        });
+
+        let pool = ConnectionPool::new(addr, 2, None, None).await.unwrap();
+        assert_eq!(pool.stats(), PoolStats { total: 2, available: 2, in_use: 0 });
+
+        let guard = pool.get().await.unwrap();
+        assert_eq!(pool.stats(), PoolStats { total: 2, available: 1, in_use: 1 });
+
+        drop(guard);
+        assert_eq!(pool.stats(), PoolStats { total: 2, available: 2, in_use: 0 });
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_guard_returns_connection_outside_runtime() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+        let pool = ConnectionPool::new(addr, 1, None, None).await.unwrap();
+        let guard = pool.get().await.unwrap();
+        std::thread::spawn(move || drop(guard)).join().unwrap();
+        assert_eq!(pool.stats(), PoolStats { total: 1, available: 1, in_use: 0 });
+        drop(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_max_frame_size() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let header = read_command(&mut socket).await;
+            socket.write_all(&response_frame(StatusCode::Ok, header.seq, &[0; 64])).await.unwrap();
+        });
+
+        let mut conn = Connection::connect(addr).await.unwrap();
+        conn.set_max_frame_size(32);
+        assert!(matches!(conn.set("k", "v").await, Err(Error::FrameTooLarge { len: 64, max: 32 })));
+        assert!(matches!(conn.set("k", vec![0u8; 64]).await, Err(Error::InvalidArgument(_))));
+        conn.set_max_frame_size(usize::MAX);
+        assert_eq!(conn.max_frame_size(), MAX_FRAME_SIZE_LIMIT);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_distinct_sorts_values() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let header = read_command(&mut socket).await;
+            assert_eq!(header.opcode, crate::types::OpCode::Distinct as u8);
+            let payload = serde_json::to_vec(&OperationResponse::success(Some(Value::Array(vec![
+                Value::String("paris".to_string()),
+                Value::String("berlin".to_string()),
+                Value::Null,
+            ]))))
+            .unwrap();
+            socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+        });
+
+        let conn = Connection::connect(addr).await.unwrap();
+        let request = DistinctRequest {
+            collection: "users".to_string(),
+            field: "city".to_string(),
+            filter: None,
+        };
+        assert_eq!(
+            conn.distinct(request).await.unwrap(),
+            vec![Value::Null, Value::String("berlin".to_string()), Value::String("paris".to_string())]
+        );
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pool_close() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            // The client shuts down cleanly, so the server reads EOF
+            let mut buf = [0u8; 1];
+            assert_eq!(socket.read(&mut buf).await.unwrap(), 0);
+        });
+
+        let pool = ConnectionPool::new(addr, 1, None, None).await.unwrap();
+        pool.close().await.unwrap();
+        assert_eq!(pool.live_connections(), 0);
+        assert!(matches!(pool.get().await, Err(Error::NotConnected)));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reauthenticates_before_expiry_and_on_auth_required() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let now = chrono::Utc::now().timestamp() as u64;
+            let login = |expires_at: u64| {
+                serde_json::to_vec(&AuthResponse {
+                    success: true,
+                    token: Some("token".to_string()),
+                    expires_at: Some(expires_at),
+                    error: None,
+                })
+                .unwrap()
+            };
+
+            use crate::types::OpCode::{Auth, Ping};
+            let script = [
+                // The first token expires within the refresh threshold
+                (Auth, StatusCode::Ok, login(now + 10)),
+                (Auth, StatusCode::Ok, login(now + 3600)),
+                (Ping, StatusCode::Ok, Vec::new()),
+                // The server drops the session early
+                (Ping, StatusCode::AuthRequired, Vec::new()),
+                (Auth, StatusCode::Ok, login(now + 3600)),
+                (Ping, StatusCode::Ok, Vec::new()),
+            ];
+            for (opcode, status, payload) in script {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, opcode as u8);
+                socket.write_all(&response_frame(status, header.seq, &payload)).await.unwrap();
+            }
+        });
+
+        let conn = Connection::connect_with_auth(addr, None, AuthConfig::username_password("u", "p"))
+            .await
+            .unwrap();
+        conn.ping().await.unwrap();
+        conn.ping().await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refresh_auth_reauthenticates_pooled_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let login = serde_json::to_vec(&AuthResponse {
+                success: true,
+                token: Some("token".to_string()),
+                expires_at: Some(chrono::Utc::now().timestamp() as u64 + 3600),
+                error: None,
+            })
+            .unwrap();
+            // Every connection logs in when opened and again when refreshed
+            let mut tasks = Vec::new();
+            for _ in 0..2 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let login = login.clone();
+                tasks.push(tokio::spawn(async move {
+                    for _ in 0..2 {
+                        let header = read_command(&mut socket).await;
+                        assert_eq!(header.opcode, crate::types::OpCode::Auth as u8);
+                        socket.write_all(&response_frame(StatusCode::Ok, header.seq, &login)).await.unwrap();
+                    }
+                }));
+            }
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+
+        let pool = ConnectionPool::new(addr, 2, None, Some(AuthConfig::username_password("u", "p")))
+            .await
+            .unwrap();
+        pool.refresh_auth().await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_username_password_from_var() {
+        let auth = AuthConfig::username_password_from_var("admin", Ok("secret".to_string())).unwrap();
+        assert_eq!(auth.username.as_deref(), Some("admin"));
+        assert_eq!(auth.password.as_deref(), Some("secret"));
+
+        assert!(matches!(
+            AuthConfig::username_password_from_var("admin", Err(std::env::VarError::NotPresent)),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reauthenticate_pooled_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let expires_at = chrono::Utc::now().timestamp() as u64 + 3600;
+        let server = tokio::spawn(async move {
+            let login = serde_json::to_vec(&AuthResponse {
+                success: true,
+                token: Some("token".to_string()),
+                expires_at: Some(expires_at),
+                error: None,
+            })
+            .unwrap();
+            let (mut socket, _) = listener.accept().await.unwrap();
+            // The explicit login, then a rejected read that makes the
+            // connection log in again with the same credentials
+            for status in [StatusCode::Ok, StatusCode::AuthRequired, StatusCode::Ok, StatusCode::Ok] {
+                let mut header = [0u8; CommandHeader::SIZE];
+                socket.read_exact(&mut header).await.unwrap();
+                let header = CommandHeader::from_bytes(&header).unwrap();
+                let mut body = vec![0u8; (header.key_len + header.value_len) as usize];
+                socket.read_exact(&mut body).await.unwrap();
+                let payload = if header.opcode == crate::types::OpCode::Auth as u8 {
+                    let request: AuthRequest = serde_json::from_slice(&body).unwrap();
+                    assert!(matches!(request.credentials, AuthCredentials::JwtToken { ref token } if token == "jwt"));
+                    login.as_slice()
+                } else {
+                    b"value"
+                };
+                socket.write_all(&response_frame(status, header.seq, payload)).await.unwrap();
+            }
+        });
+
+        let pool = ConnectionPool::new(addr, 1, None, None).await.unwrap();
+        let conn = pool.get().await.unwrap();
+        let response = conn.reauthenticate(AuthConfig::jwt_token("jwt")).await.unwrap();
+        assert_eq!(response.token.as_deref(), Some("token"));
+        assert_eq!(response.expires_at, Some(expires_at));
+        assert_eq!(conn.get("key").await.unwrap(), Bytes::from_static(b"value"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reads_routed_to_replicas() {
+        /// Serve one connection, checking each command's opcode
+        async fn serve(listener: TcpListener, script: Vec<(crate::types::OpCode, &'static [u8])>) {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for (opcode, payload) in script {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, opcode as u8);
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, payload)).await.unwrap();
+            }
+        }
+
+        use crate::types::OpCode::{Get, Set};
+        let primary = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let replica = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (primary_addr, replica_addr) = (primary.local_addr().unwrap(), replica.local_addr().unwrap());
+        let primary = tokio::spawn(serve(primary, vec![(Set, b""), (Set, b"")]));
+        let replica = tokio::spawn(serve(replica, vec![(Get, b"replica"), (Get, b"replica")]));
+
+        let client = ClientBuilder::new()
+            .addr(primary_addr)
+            .pool_size(1)
+            .replica(replica_addr)
+            .read_preference(ReadPreference::Secondary)
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(client.pool.get().await.unwrap().role(), NodeRole::Primary);
+        assert_eq!(client.replicas().next().unwrap().get().await.unwrap().role(), NodeRole::Secondary);
+
+        for _ in 0..2 {
+            client.set("key", "value").await.unwrap();
+            assert_eq!(&client.get("key").await.unwrap()[..], b"replica");
+        }
+        primary.await.unwrap();
+        replica.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reader_routes_responses_and_pushes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let subscribe = read_command(&mut socket).await;
+            socket.write_all(&response_frame(StatusCode::Ok, subscribe.seq, b"")).await.unwrap();
+
+            let first = read_command(&mut socket).await;
+            let second = read_command(&mut socket).await;
+            // A message pushed between the replies, which arrive out of order
+            let mut push = response_frame(StatusCode::Ok, 0, b"\x04\0\0\0newshello");
+            push[1] = crate::types::response_flags::PUSH;
+            socket.write_all(&push).await.unwrap();
+            socket.write_all(&response_frame(StatusCode::Ok, second.seq, b"second")).await.unwrap();
+            socket.write_all(&response_frame(StatusCode::Ok, first.seq, b"first")).await.unwrap();
+        });
+
+        let conn = Connection::connect(addr).await.unwrap();
+        conn.subscribe("news").await.unwrap();
+        let first = Command::ping(conn.next_seq());
+        let second = Command::ping(conn.next_seq());
+        let (first, second) = tokio::join!(conn.execute(first), async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            conn.execute(second).await
+        });
+        assert_eq!(&first.unwrap().payload[..], b"first");
+        assert_eq!(&second.unwrap().payload[..], b"second");
+
+        let push = conn.read_push().await.unwrap();
+        assert!(push.is_push());
+        assert_eq!(&push.payload[4..], b"newshello");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_to_channels_and_patterns() {
+        use crate::types::OpCode::{PSubscribe, PUnsubscribe, Subscribe, Unsubscribe};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for opcode in [Subscribe, Subscribe, PSubscribe] {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, opcode as u8);
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, b"")).await.unwrap();
+            }
+
+            let mut push = response_frame(StatusCode::Ok, 0, b"\x0b\0\0\0events.userjoined");
+            push[1] = crate::types::response_flags::PUSH;
+            socket.write_all(&push).await.unwrap();
+
+            for opcode in [Unsubscribe, Unsubscribe, PUnsubscribe] {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, opcode as u8);
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, b"")).await.unwrap();
+            }
+        });
+
+        let mut subscription = Subscription::open(
+            addr.into(),
+            None,
+            None,
+            vec!["news".to_string(), "sports".to_string()],
+            vec!["events.*".to_string()],
+            0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(subscription.patterns(), ["events.*"]);
+
+        let message = subscription.recv().await.unwrap();
+        assert_eq!(message.channel, "events.user");
+        assert_eq!(&message.payload[..], b"joined");
+
+        subscription.unsubscribe_all().await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mget_and_mset() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut frames = Vec::new();
+            for _ in 0..2 {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, crate::types::OpCode::Set as u8);
+                frames.extend(response_frame(StatusCode::Ok, header.seq, b""));
+            }
+            socket.write_all(&frames).await.unwrap();
+
+            // Answer the gets out of order; results must still follow key order
+            let headers = [
+                read_command(&mut socket).await,
+                read_command(&mut socket).await,
+                read_command(&mut socket).await,
+            ];
+            assert!(headers.iter().all(|h| h.opcode == crate::types::OpCode::Get as u8));
+            let mut frames = response_frame(StatusCode::Ok, headers[2].seq, b"c");
+            frames.extend(response_frame(StatusCode::NotFound, headers[1].seq, b""));
+            frames.extend(response_frame(StatusCode::Ok, headers[0].seq, b"a"));
+            socket.write_all(&frames).await.unwrap();
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        client.mset(&[("a", "a"), ("c", "c")]).await.unwrap();
+        let values = client.mget(&["a", "b", "c"]).await.unwrap();
+        assert_eq!(values, [Some(Bytes::from("a")), None, Some(Bytes::from("c"))]);
+        assert_eq!((client.client_stats().hits, client.client_stats().misses), (2, 1));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scan_streams_pages() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for (cursor, next, keys) in [(0, 7, vec!["a", "b"]), (7, 0, vec!["c"])] {
+                let mut header = [0u8; CommandHeader::SIZE];
+                socket.read_exact(&mut header).await.unwrap();
+                let header = CommandHeader::from_bytes(&header).unwrap();
+                assert_eq!(header.opcode, crate::types::OpCode::Scan as u8);
+                let mut body = vec![0u8; (header.key_len + header.value_len) as usize];
+                socket.read_exact(&mut body).await.unwrap();
+                let request: ScanRequest = serde_json::from_slice(&body).unwrap();
+                assert_eq!((request.cursor, request.pattern.as_deref(), request.count), (cursor, Some("user:*"), Some(2)));
+
+                let mut page = BTreeMap::new();
+                page.insert("cursor".to_string(), Value::Int64(next));
+                page.insert("keys".to_string(), Value::Array(keys.into_iter().map(Value::from).collect()));
+                let payload = serde_json::to_vec(&OperationResponse::success(Some(Value::Object(page)))).unwrap();
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+            }
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        let mut scan = client.scan(Some("user:*"), 2);
+        let mut keys = Vec::new();
+        while let Some(key) = scan.try_next().await.unwrap() {
+            keys.push(key);
+        }
+        assert_eq!(keys, ["a", "b", "c"]);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_exists() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for status in [StatusCode::Ok, StatusCode::NotFound, StatusCode::Ok] {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, crate::types::OpCode::Exists as u8);
+                socket.write_all(&response_frame(status, header.seq, b"")).await.unwrap();
+            }
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        assert!(client.exists("a").await.unwrap());
+        assert_eq!(client.exists_many(&["b", "c"]).await.unwrap(), [false, true]);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_one_and_by_id() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let id = DocumentId::new();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for deleted in [1, 0] {
+                let mut header = [0u8; CommandHeader::SIZE];
+                socket.read_exact(&mut header).await.unwrap();
+                let header = CommandHeader::from_bytes(&header).unwrap();
+                assert_eq!(header.opcode, crate::types::OpCode::DeleteDoc as u8);
+                let mut body = vec![0u8; (header.key_len + header.value_len) as usize];
+                socket.read_exact(&mut body).await.unwrap();
+                let request: DeleteDocRequest = serde_json::from_slice(&body).unwrap();
+                assert_eq!(request.limit, Some(1));
+                assert_eq!(request.filter.as_object().and_then(|f| f.get("_id")), Some(&Value::from(id)));
+
+                let mut response = OperationResponse::success(None);
+                response.affected_count = Some(deleted);
+                let payload = serde_json::to_vec(&response).unwrap();
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+            }
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        assert!(client.delete_by_id("users", id).await.unwrap());
+        let mut filter = BTreeMap::new();
+        filter.insert("_id".to_string(), Value::from(id));
+        assert!(!client.delete_one("users", filter).await.unwrap());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_document() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut document = Document::new();
+        document.insert("name", "Alice");
+        let stored = document.clone();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for found in [true, false] {
+                let mut header = [0u8; CommandHeader::SIZE];
+                socket.read_exact(&mut header).await.unwrap();
+                let header = CommandHeader::from_bytes(&header).unwrap();
+                assert_eq!(header.opcode, crate::types::OpCode::Query as u8);
+                let mut body = vec![0u8; (header.key_len + header.value_len) as usize];
+                socket.read_exact(&mut body).await.unwrap();
+                let request: QueryRequest = serde_json::from_slice(&body).unwrap();
+                assert_eq!(request.collection, "users");
+                assert_eq!(request.limit, Some(1));
+                // The server holds documents as objects with a string `_id`
+                let stored = Value::from(stored.clone());
+                let filter_id = request.filter.as_ref().and_then(|f| f.as_object()).and_then(|f| f.get("_id"));
+                assert_eq!(filter_id, stored.as_object().and_then(|f| f.get("_id")));
+
+                let docs = if found { vec![stored] } else { Vec::new() };
+                let payload = serde_json::to_vec(&OperationResponse::success(Some(Value::Array(docs)))).unwrap();
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+            }
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        assert_eq!(client.get_document("users", document.id).await.unwrap(), Some(document.clone()));
+        assert_eq!(client.get_document("users", document.id).await.unwrap(), None);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bulk_write() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for ordered in [true, false] {
+                let mut header = [0u8; CommandHeader::SIZE];
+                socket.read_exact(&mut header).await.unwrap();
+                let header = CommandHeader::from_bytes(&header).unwrap();
+                assert_eq!(header.opcode, crate::types::OpCode::BulkWrite as u8);
+                let mut body = vec![0u8; (header.key_len + header.value_len) as usize];
+                socket.read_exact(&mut body).await.unwrap();
+                let request: BulkWriteRequest = serde_json::from_slice(&body).unwrap();
+                assert_eq!(request.collection, "users");
+                assert_eq!(request.ordered, ordered);
+                assert!(matches!(request.operations[0], WriteOp::Insert(_)));
+                assert!(matches!(request.operations[1], WriteOp::Update { upsert: false, .. }));
+                assert!(matches!(request.operations[2], WriteOp::Delete { .. }));
+
+                let response = if ordered {
+                    OperationResponse::success(Some(Value::from(serde_json::json!([
+                        {},
+                        {"matched": 2, "modified": 1},
+                        {"deleted": 3}
+                    ]))))
+                } else {
+                    OperationResponse {
+                        data: Some(Value::from(serde_json::json!([
+                            {},
+                            {"error": "invalid update"},
+                            {"deleted": 1}
+                        ]))),
+                        ..OperationResponse::error("1 write failed".to_string())
+                    }
+                };
+                let payload = serde_json::to_vec(&response).unwrap();
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+            }
+        });
+
+        let document = Document::new();
+        let operations = vec![
+            WriteOp::Insert(document.clone()),
+            WriteOp::Update {
+                filter: Value::from(serde_json::json!({"active": false})),
+                update: Value::from(serde_json::json!({"$set": {"archived": true}})),
+                upsert: false,
+            },
+            WriteOp::Delete {
+                filter: Value::from(serde_json::json!({"archived": true})),
+            },
+        ];
+        let client = Client::connect(addr).await.unwrap();
+        assert!(client.bulk_write("users", Vec::new(), true).await.unwrap().results.is_empty());
+
+        let result = client.bulk_write("users", operations.clone(), true).await.unwrap();
+        assert_eq!(result.inserted_ids(), [document.id]);
+        assert_eq!((result.matched(), result.modified(), result.deleted()), (2, 1, 3));
+
+        match client.bulk_write("users", operations, false).await {
+            Err(Error::BulkWrite { failed_index, message, result }) => {
+                assert_eq!(failed_index, 1);
+                assert_eq!(message, "invalid update");
+                assert_eq!(result.inserted_ids(), [document.id]);
+                assert_eq!(result.deleted(), 1);
+            }
+            other => panic!("expected a bulk write error, got {:?}", other),
+        }
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_schema_cache_validates_inserts() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let schema = Value::from(serde_json::json!({
+                "type": "object",
+                "required": ["name"],
+                "properties": {"name": {"type": "string"}}
+            }));
+            // The schema is fetched once, and the invalid document never sent
+            use crate::types::OpCode::{GetSchema, InsertDoc, InsertMany};
+            for (opcode, data) in [(GetSchema, Some(schema)), (InsertDoc, None), (InsertMany, None)] {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, opcode as u8);
+                let payload = serde_json::to_vec(&OperationResponse::success(data)).unwrap();
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+            }
+        });
+
+        let client = Client::connect(addr).await.unwrap().with_schema_cache();
+        let mut valid = Document::new();
+        valid.insert("name", "Alice");
+        let mut invalid = Document::new();
+        invalid.insert("name", 7i64);
+
+        client.insert_document("users", valid.clone()).await.unwrap();
+        match client.insert_document("users", invalid.clone()).await {
+            Err(Error::InvalidArgument(message)) => assert!(message.contains("field 'name'"), "{}", message),
+            other => panic!("expected a schema error, got {:?}", other),
+        }
+        assert!(client.insert_many("users", vec![valid.clone(), invalid]).await.is_err());
+        client.insert_many("users", vec![valid]).await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replace_one() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut header = [0u8; CommandHeader::SIZE];
+            socket.read_exact(&mut header).await.unwrap();
+            let header = CommandHeader::from_bytes(&header).unwrap();
+            assert_eq!(header.opcode, crate::types::OpCode::UpdateDoc as u8);
+            let mut body = vec![0u8; (header.key_len + header.value_len) as usize];
+            socket.read_exact(&mut body).await.unwrap();
+            let request: UpdateDocRequest = serde_json::from_slice(&body).unwrap();
+            assert!(request.upsert);
+            // The whole body is sent, without the client-side id
+            let fields = request.update.as_object().unwrap();
+            assert_eq!(fields.keys().collect::<Vec<_>>(), ["name"]);
+
+            let mut response = OperationResponse::success(None);
+            response.affected_count = Some(1);
+            let payload = serde_json::to_vec(&response).unwrap();
+            socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        let mut filter = BTreeMap::new();
+        filter.insert("name".to_string(), Value::from("alice"));
+        let mut document = Document::new();
+        document.insert("name", "alicia");
+        let result = client.replace_one("users", filter.clone(), document, true).await.unwrap();
+        assert_eq!((result.matched, result.modified), (1, 1));
+
+        let mut operators = Document::new();
+        operators.insert("$set", Value::Null);
+        assert!(matches!(
+            client.replace_one("users", filter, operators, false).await,
+            Err(Error::InvalidArgument(_))
+        ));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_insert_document_returns_id() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let assigned = DocumentId::new();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut data = BTreeMap::new();
+            data.insert("_id".to_string(), Value::from(assigned));
+            for data in [None, Some(Value::Object(data))] {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, crate::types::OpCode::InsertDoc as u8);
+                let payload = serde_json::to_vec(&OperationResponse::success(data)).unwrap();
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+            }
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        let document = Document::new();
+        let id = document.id;
+        assert_eq!(client.insert_document("users", document).await.unwrap(), id);
+        assert_eq!(client.insert_document("users", Document::new()).await.unwrap(), assigned);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_insert_many_returns_ids() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let assigned = [DocumentId::new(), DocumentId::new()];
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let ids = |ids: Vec<Value>| {
+                let mut data = BTreeMap::new();
+                data.insert("_ids".to_string(), Value::Array(ids));
+                Some(Value::Object(data))
+            };
+            let responses = [
+                None,
+                ids(assigned.iter().copied().map(Value::from).collect()),
+                ids(vec![Value::from(assigned[0])]),
+            ];
+            for data in responses {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, crate::types::OpCode::InsertMany as u8);
+                let payload = serde_json::to_vec(&OperationResponse::success(data)).unwrap();
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+            }
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        let documents = vec![Document::new(), Document::new()];
+        let ids: Vec<DocumentId> = documents.iter().map(|doc| doc.id).collect();
+        assert_eq!(client.insert_many("users", documents).await.unwrap(), ids);
+        let documents = vec![Document::new(), Document::new()];
+        assert_eq!(client.insert_many("users", documents).await.unwrap(), assigned);
+        let documents = vec![Document::new(), Document::new()];
+        assert!(matches!(
+            client.insert_many("users", documents).await,
+            Err(Error::InvalidResponse(_))
+        ));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_select_applies_to_every_pooled_connection() {
+        async fn serve_select(socket: &mut TcpStream) {
+            let mut header = [0u8; CommandHeader::SIZE];
+            socket.read_exact(&mut header).await.unwrap();
+            let header = CommandHeader::from_bytes(&header).unwrap();
+            assert_eq!(header.opcode, crate::types::OpCode::Select as u8);
+            let mut body = vec![0u8; (header.key_len + header.value_len) as usize];
+            socket.read_exact(&mut body).await.unwrap();
+            let request: SelectRequest = serde_json::from_slice(&body).unwrap();
+            assert_eq!(request.db, 3);
+            let payload = serde_json::to_vec(&OperationResponse::success(None)).unwrap();
+            socket.write_all(&response_frame(StatusCode::Ok, header.seq, &payload)).await.unwrap();
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut first, _) = listener.accept().await.unwrap();
+            let (mut second, _) = listener.accept().await.unwrap();
+            // select switches the idle connection straight away...
+            serve_select(&mut second).await;
+            // ...and the one that was checked out on its next checkout
+            serve_select(&mut first).await;
+            let ping = read_command(&mut first).await;
+            assert_eq!(ping.opcode, crate::types::OpCode::Ping as u8);
+            first.write_all(&response_frame(StatusCode::Ok, ping.seq, b"")).await.unwrap();
+        });
+
+        let client = ClientBuilder::new().addr(addr).pool_size(2).connect().await.unwrap();
+        assert_eq!(client.database(), 0);
+        let held = client.pool.get().await.unwrap();
+        client.select(3).await.unwrap();
+        assert_eq!(client.database(), 3);
+        drop(held);
+
+        let second = client.pool.get().await.unwrap();
+        let first = client.pool.get().await.unwrap();
+        assert_eq!((first.database(), second.database()), (3, 3));
+        first.ping().await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_write_buffer_coalesces_concurrent_writes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for _ in 0..4 {
+                let header = read_command(&mut socket).await;
+                socket.write_all(&response_frame(StatusCode::Ok, header.seq, b"")).await.unwrap();
+            }
+        });
+
+        let mut conn = Connection::connect(addr).await.unwrap();
+        conn.set_write_buffer(256);
+        let conn = Arc::new(conn);
+        // The large frame doesn't fit in the buffer and is written directly
+        let (a, b, c, large) = tokio::join!(
+            conn.ping(),
+            conn.ping(),
+            conn.ping(),
+            conn.set("large", vec![0u8; 1024]),
+        );
+        a.unwrap();
+        b.unwrap();
+        c.unwrap();
+        large.unwrap();
+        assert!(conn.outbox.lock().unwrap().is_empty());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_by_hostname() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let ping = read_command(&mut socket).await;
+            socket.write_all(&response_frame(StatusCode::Ok, ping.seq, b"")).await.unwrap();
+        });
+
+        // localhost may resolve to ::1 first, which isn't listening
+        let client = Client::connect(format!("localhost:{}", port)).await.unwrap();
+        client.ping().await.unwrap();
+        server.await.unwrap();
+
+        assert!(matches!(Client::connect("localhost").await, Err(Error::InvalidArgument(_))));
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_auth_requires_mutual_tls() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // No Auth command is sent
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1];
+            assert_eq!(socket.read(&mut buf).await.unwrap(), 0);
+        });
+
+        let result = Connection::connect_with_auth(addr, None, AuthConfig::client_certificate()).await;
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_checksums() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for corrupt in [false, true] {
+                let mut header = [0u8; CommandHeader::SIZE];
+                socket.read_exact(&mut header).await.unwrap();
+                let header = CommandHeader::from_bytes(&header).unwrap();
+                assert!(header.has_flag(crate::types::flags::CHECKSUM));
+                let mut rest = vec![0u8; (header.key_len + header.value_len) as usize + 4];
+                socket.read_exact(&mut rest).await.unwrap();
+
+                let mut frame = response_frame(StatusCode::Ok, header.seq, b"value");
+                frame[1] = crate::types::response_flags::CHECKSUM;
+                let checksum = crate::types::crc32(&[b"value"]) ^ corrupt as u32;
+                frame.extend_from_slice(&checksum.to_le_bytes());
+                socket.write_all(&frame).await.unwrap();
+            }
+        });
+
+        let client = ClientBuilder::new().addr(addr).pool_size(1).checksums(true).connect().await.unwrap();
+        assert_eq!(&client.get("key").await.unwrap()[..], b"value");
+        match client.get("key").await {
+            Err(Error::Protocol(msg)) => assert!(msg.contains("checksum mismatch"), "{}", msg),
+            other => panic!("expected a checksum mismatch, got {:?}", other),
+        }
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_execute_raw() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let header = read_command(&mut socket).await;
+            assert_eq!(header.opcode, 0x7F);
+            assert_ne!(header.seq, 0);
+            socket.write_all(&response_frame(StatusCode::Ok, header.seq, b"raw")).await.unwrap();
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        let mut header = crate::types::CommandHeader::new(crate::types::OpCode::Ping, 0);
+        header.opcode = 0x7F;
+        let cmd = Command::new(header, Bytes::new(), Bytes::new());
+        let response = client.execute_raw(cmd).await.unwrap();
+        assert_eq!(&response.payload[..], b"raw");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_collection_if_not_exists() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let created = serde_json::to_vec(&OperationResponse::success(None)).unwrap();
+            for (status, payload) in [(StatusCode::Ok, &created[..]), (StatusCode::CollectionExists, &b"users"[..])] {
+                let header = read_command(&mut socket).await;
+                assert_eq!(header.opcode, crate::types::OpCode::CreateCollection as u8);
+                socket.write_all(&response_frame(status, header.seq, payload)).await.unwrap();
+            }
+        });
+
+        let client = Client::connect(addr).await.unwrap();
+        assert!(client.create_collection_if_not_exists("users").await.unwrap());
+        assert!(!client.create_collection_if_not_exists("users").await.unwrap());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_closed_connection_fails_pending_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_command(&mut socket).await;
+        });
+
+        let conn = Connection::connect(addr).await.unwrap();
+        assert!(matches!(conn.ping().await, Err(Error::Connection(_))));
+        assert!(!conn.is_alive().await);
+        assert!(matches!(conn.ping().await, Err(Error::Connection(_))));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_opt_maps_not_found_to_none() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for status in [StatusCode::Ok, StatusCode::NotFound, StatusCode::NotFound] {
+                let header = read_command(&mut socket).await;
+                socket.write_all(&response_frame(status, header.seq, b"value")).await.unwrap();
+            }
+        });
+
+        let conn = Connection::connect(addr).await.unwrap();
+        assert_eq!(conn.get_opt("a").await.unwrap().as_deref(), Some(&b"value"[..]));
+        assert_eq!(conn.get_opt("b").await.unwrap(), None);
+        assert!(matches!(conn.get("b").await, Err(Error::KeyNotFound)));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_matches_out_of_order_responses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut seqs = Vec::new();
+            for _ in 0..2 {
+                let header = read_command(&mut socket).await;
+                seqs.push(header.seq);
+            }
+            // Reply in reverse order
+            socket.write_all(&response_frame(StatusCode::NotFound, seqs[1], b"")).await.unwrap();
+            socket.write_all(&response_frame(StatusCode::Ok, seqs[0], b"first")).await.unwrap();
+        });
+
+        let conn = Connection::connect(addr).await.unwrap();
+        let cmds = vec![Command::get(0, "a"), Command::get(0, "b")];
+        let results = conn.pipeline(cmds).await.unwrap();
+        assert_eq!(&results[0].as_ref().unwrap().payload[..], b"first");
+        assert!(matches!(results[1], Err(Error::KeyNotFound)));
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_bool_from_value() {
+        assert!(bool_from_value(Value::Bool(true), "sismember").unwrap());
+        assert!(bool_from_value(Value::Int64(1), "sismember").unwrap());
+        assert!(!bool_from_value(Value::Int32(0), "sismember").unwrap());
+        assert!(bool_from_value(Value::Int64(2), "sismember").is_err());
+        assert!(bool_from_value(Value::Null, "sismember").is_err());
+    }
+
+    #[test]
+    fn test_scan_page_from_value() {
+        let mut page = BTreeMap::new();
+        page.insert("cursor".to_string(), Value::Int64(17));
+        page.insert(
+            "keys".to_string(),
+            Value::Array(vec![Value::String("a".to_string()), Value::String("b".to_string())]),
+        );
+        assert_eq!(
+            scan_page_from_value(Value::Object(page.clone())).unwrap(),
+            (17, vec!["a".to_string(), "b".to_string()])
+        );
+
+        page.insert("keys".to_string(), Value::Array(vec![Value::Int32(1)]));
+        assert!(scan_page_from_value(Value::Object(page)).is_err());
+        assert!(scan_page_from_value(Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_key_matches() {
+        assert!(key_matches("user:42", "user"));
+        assert!(key_matches("session:user", "user"));
+        assert!(!key_matches("order:1", "user"));
+
+        assert!(key_matches("user:42", "user:*"));
+        assert!(key_matches("user:42", "*:4?"));
+        assert!(key_matches("user:42", "*"));
+        assert!(key_matches("a:b:c", "a*c"));
+        assert!(!key_matches("session:user", "user:*"));
+        assert!(!key_matches("user:42", "user:?"));
+    }
 }
@@ -0,0 +1,264 @@
+//! Streaming cursors over query results and the keyspace
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+
+use crate::connection::Client;
+use crate::types::{Document, KeyType, QueryRequest, ScanRequest};
+use crate::Result;
+
+/// Default number of documents fetched per page
+const DEFAULT_BATCH_SIZE: u64 = 1000;
+
+/// A page request in flight
+type PageFuture = Pin<Box<dyn Future<Output = Result<Vec<Document>>> + Send>>;
+
+/// A keyspace scan page request in flight
+type ScanFuture = Pin<Box<dyn Future<Output = Result<(u64, Vec<String>)>> + Send>>;
+
+/// A stream of query results fetched page by page
+///
+/// Pages are requested with `skip`/`limit`, so at most one page of documents
+/// is held in memory at a time. The original request's `skip` is used as the
+/// starting offset and its `limit` caps the total number of documents.
+pub struct Cursor {
+    /// Client used to fetch pages
+    client: Client,
+    /// The query being paged through
+    request: QueryRequest,
+    /// Documents fetched per page
+    batch_size: u64,
+    /// Documents fetched so far
+    fetched: u64,
+    /// Documents from the current page not yet yielded
+    buffer: VecDeque<Document>,
+    /// The page request in flight and how many documents it asked for
+    pending: Option<(PageFuture, u64)>,
+    /// Set once the last page has been fetched or an error was returned
+    exhausted: bool,
+}
+
+impl Cursor {
+    /// Create a cursor over the results of `request`
+    pub(crate) fn new(client: Client, request: QueryRequest) -> Self {
+        Self {
+            client,
+            request,
+            batch_size: DEFAULT_BATCH_SIZE,
+            fetched: 0,
+            buffer: VecDeque::new(),
+            pending: None,
+            exhausted: false,
+        }
+    }
+
+    /// Set the number of documents fetched per page (at least 1)
+    pub fn batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Fetch the next document, or `None` once the results are exhausted
+    pub async fn try_next(&mut self) -> Result<Option<Document>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx))
+            .await
+            .transpose()
+    }
+}
+
+/// Build the request for the page after `fetched` documents, or `None` if the
+/// request's limit has been reached
+fn page_request(request: &QueryRequest, batch_size: u64, fetched: u64) -> Option<QueryRequest> {
+    let page_limit = match request.limit {
+        Some(limit) => batch_size.min(limit.saturating_sub(fetched)),
+        None => batch_size,
+    };
+    if page_limit == 0 {
+        return None;
+    }
+
+    let mut page = request.clone();
+    page.skip = Some(request.skip.unwrap_or(0) + fetched);
+    page.limit = Some(page_limit);
+    Some(page)
+}
+
+impl Stream for Cursor {
+    type Item = Result<Document>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(document) = this.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(document)));
+            }
+            if this.exhausted {
+                return Poll::Ready(None);
+            }
+
+            if this.pending.is_none() {
+                let page = match page_request(&this.request, this.batch_size, this.fetched) {
+                    Some(page) => page,
+                    None => {
+                        this.exhausted = true;
+                        continue;
+                    }
+                };
+                let requested = page.limit.unwrap_or(0);
+                let client = this.client.clone();
+                this.pending = Some((Box::pin(async move { client.query(page).await }), requested));
+            }
+
+            let (future, requested) = this.pending.as_mut().expect("page request in flight");
+            let requested = *requested;
+            let result = ready!(future.as_mut().poll(cx));
+            this.pending = None;
+
+            match result {
+                Ok(documents) => {
+                    // A short page means there is nothing further to fetch
+                    if (documents.len() as u64) < requested {
+                        this.exhausted = true;
+                    }
+                    this.fetched += documents.len() as u64;
+                    this.buffer.extend(documents);
+                }
+                Err(e) => {
+                    this.exhausted = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+/// A stream of keys fetched with cursor-based SCAN
+///
+/// Each page is requested with the cursor returned by the previous one, so at
+/// most one page of keys is held in memory at a time. As with Redis SCAN, a
+/// key may be returned more than once if the keyspace changes during the scan.
+pub struct KeyScan {
+    /// Client used to fetch pages
+    client: Client,
+    /// The request for the next page
+    request: ScanRequest,
+    /// Keys from the current page not yet yielded
+    buffer: VecDeque<String>,
+    /// The page request in flight
+    pending: Option<ScanFuture>,
+    /// Set once the server returns cursor 0 or an error was returned
+    exhausted: bool,
+}
+
+impl KeyScan {
+    /// Create a scan over keys matching `pattern`, asking for `batch` keys per page
+    pub(crate) fn new(client: Client, pattern: Option<&str>, batch: usize) -> Self {
+        Self {
+            client,
+            request: ScanRequest {
+                cursor: 0,
+                pattern: pattern.map(str::to_string),
+                count: Some(batch.max(1) as u64),
+                key_type: None,
+            },
+            buffer: VecDeque::new(),
+            pending: None,
+            exhausted: false,
+        }
+    }
+
+    /// Only return keys whose data structure is `key_type`
+    pub fn key_type(mut self, key_type: KeyType) -> Self {
+        self.request.key_type = Some(key_type);
+        self
+    }
+
+    /// Fetch the next key, or `None` once the scan is complete
+    pub async fn try_next(&mut self) -> Result<Option<String>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx))
+            .await
+            .transpose()
+    }
+}
+
+impl Stream for KeyScan {
+    type Item = Result<String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(key) = this.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(key)));
+            }
+            if this.exhausted {
+                return Poll::Ready(None);
+            }
+
+            let future = this.pending.get_or_insert_with(|| {
+                let client = this.client.clone();
+                let request = this.request.clone();
+                Box::pin(async move { client.scan_page(request).await })
+            });
+            let result = ready!(future.as_mut().poll(cx));
+            this.pending = None;
+
+            match result {
+                Ok((cursor, keys)) => {
+                    this.exhausted = cursor == 0;
+                    this.request.cursor = cursor;
+                    this.buffer.extend(keys);
+                }
+                Err(e) => {
+                    this.exhausted = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(skip: Option<u64>, limit: Option<u64>) -> QueryRequest {
+        QueryRequest {
+            collection: "users".to_string(),
+            filter: None,
+            projection: None,
+            sort: None,
+            skip,
+            limit,
+        }
+    }
+
+    fn pages(request: &QueryRequest, batch_size: u64) -> Vec<(u64, u64)> {
+        let mut pages = Vec::new();
+        let mut fetched = 0;
+        while let Some(page) = page_request(request, batch_size, fetched) {
+            let limit = page.limit.unwrap();
+            pages.push((page.skip.unwrap(), limit));
+            fetched += limit;
+        }
+        pages
+    }
+
+    #[test]
+    fn test_page_request_respects_skip_and_limit() {
+        assert_eq!(
+            pages(&query(Some(5), Some(250)), 100),
+            vec![(5, 100), (105, 100), (205, 50)]
+        );
+        assert!(pages(&query(None, Some(0)), 100).is_empty());
+
+        // Without a limit, paging continues until a short page is returned
+        let page = page_request(&query(None, None), 100, 300).unwrap();
+        assert_eq!((page.skip, page.limit), (Some(300), Some(100)));
+    }
+}
@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use crate::types::{BulkWriteResult, StatusCode, Value};
+
 /// Error type for VedDB client operations
 #[derive(Debug, Error)]
 pub enum Error {
@@ -13,10 +15,34 @@ pub enum Error {
     #[error("Protocol error: {0}")]
     Protocol(String),
 
+    /// A response arrived for a different request than the one awaited
+    #[error("Protocol error: sequence number mismatch for opcode {opcode:#04x}: expected {expected}, got {received}")]
+    SequenceMismatch {
+        /// Sequence number of the request that was sent
+        expected: u32,
+        /// Sequence number of the response that arrived
+        received: u32,
+        /// Opcode of the request that was sent
+        opcode: u8,
+    },
+
+    /// A response frame exceeded the maximum frame size
+    #[error("Protocol error: response too large: {len} bytes (max: {max})")]
+    FrameTooLarge {
+        /// Payload length reported in the response header
+        len: usize,
+        /// The maximum frame size
+        max: usize,
+    },
+
     /// Server returned an error
     #[error("Server error: {0}")]
     Server(String),
 
+    /// Server returned a structured error with a stable code
+    #[error("Server error: {0}")]
+    ServerDetail(ServerError),
+
     /// Operation timed out
     #[error("Operation timed out: {0}")]
     Timeout(#[from] tokio::time::error::Elapsed),
@@ -37,6 +63,31 @@ pub enum Error {
     #[error("Key not found")]
     KeyNotFound,
 
+    /// Collection or index not found
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Collection or index already exists
+    #[error("Already exists: {0}")]
+    AlreadyExists(String),
+
+    /// The authenticated user lacks permission for the operation
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// The key holds a value of the wrong kind for the operation, e.g. a
+    /// non-numeric value for an increment
+    #[error("Wrong type: {0}")]
+    WrongType(String),
+
+    /// A compare-and-swap found a different version than expected
+    #[error("Version mismatch")]
+    VersionMismatch,
+
+    /// The server is temporarily out of capacity
+    #[error("Server busy: {0}")]
+    ServerBusy(String),
+
     /// Connection pool exhausted
     #[error("Connection pool exhausted")]
     PoolExhausted,
@@ -49,6 +100,10 @@ pub enum Error {
     #[error("Authentication failed")]
     AuthenticationFailed,
 
+    /// The server requires authentication for the operation
+    #[error("Authentication required")]
+    AuthenticationRequired,
+
     /// Not connected to server
     #[error("Not connected")]
     NotConnected,
@@ -61,6 +116,37 @@ pub enum Error {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    /// A bulk insert was rejected partway through the batch
+    #[error("Bulk insert failed at index {failed_index} after {inserted} documents were inserted: {message}")]
+    BulkInsert {
+        /// Number of documents inserted before the failure
+        inserted: usize,
+        /// Index of the document that was rejected
+        failed_index: usize,
+        /// Error message reported by the server
+        message: String,
+    },
+
+    /// Some writes of a bulk write failed
+    #[error("Bulk write failed at index {failed_index}: {message}")]
+    BulkWrite {
+        /// Index of the first failed write
+        failed_index: usize,
+        /// Error message reported for that write
+        message: String,
+        /// Outcomes of the writes the server attempted
+        result: BulkWriteResult,
+    },
+
+    /// A value had a different type than the one requested
+    #[error("Type mismatch: expected {expected}, got {actual}")]
+    TypeMismatch {
+        /// The requested type
+        expected: &'static str,
+        /// The type of the value
+        actual: &'static str,
+    },
+
     /// TLS error
     #[error("TLS error: {0}")]
     Tls(String),
@@ -105,6 +191,97 @@ impl Error {
     pub fn other<S: Into<String>>(msg: S) -> Self {
         Error::Other(msg.into())
     }
+
+    /// Whether the failure is likely transient, so that repeating an
+    /// idempotent operation may succeed
+    ///
+    /// This covers dropped connections, timeouts, an exhausted pool and a
+    /// server that is temporarily full.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Connection(_) | Error::Timeout(_) | Error::PoolExhausted | Error::ServerBusy(_) => true,
+            Error::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+            ),
+            _ => false,
+        }
+    }
+
+    /// Get the server's structured error, if it sent one
+    pub fn server_error(&self) -> Option<&ServerError> {
+        match self {
+            Error::ServerDetail(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Create the error for a non-OK response status, with the server's message
+    ///
+    /// A JSON error object is read into a [`ServerError`]; statuses with a
+    /// dedicated variant keep just its message.
+    pub(crate) fn from_status(status: StatusCode, msg: String) -> Self {
+        let (msg, detail) = match ServerError::parse(&msg) {
+            Some(detail) => (detail.message.clone(), Some(detail)),
+            None => (msg, None),
+        };
+        match status {
+            StatusCode::NotFound => Error::KeyNotFound,
+            StatusCode::CollectionNotFound | StatusCode::IndexNotFound => Error::NotFound(msg),
+            StatusCode::CollectionExists | StatusCode::IndexExists => Error::AlreadyExists(msg),
+            StatusCode::AuthRequired => Error::AuthenticationRequired,
+            StatusCode::AuthFailed => Error::AuthenticationFailed,
+            StatusCode::PermissionDenied => Error::PermissionDenied(msg),
+            StatusCode::VersionMismatch => Error::VersionMismatch,
+            StatusCode::WrongType => Error::WrongType(msg),
+            StatusCode::Full => Error::ServerBusy(msg),
+            status => match detail {
+                Some(detail) => Error::ServerDetail(detail),
+                None => Error::Server(format!("Server error: {:?}: {}", status, msg)),
+            },
+        }
+    }
+}
+
+/// An error reported by the server as a JSON object
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerError {
+    /// Stable, machine-readable error code, e.g. `"INVALID_QUERY"`
+    pub code: String,
+    /// Human-readable description
+    pub message: String,
+    /// Any further detail the server attached
+    pub details: Option<Value>,
+}
+
+impl ServerError {
+    /// Read an error object with string `code` and `message` fields, or
+    /// `None` if `body` isn't one
+    fn parse(body: &str) -> Option<Self> {
+        let serde_json::Value::Object(mut fields) = serde_json::from_str(body).ok()? else {
+            return None;
+        };
+        let mut take_string = |name| match fields.remove(name) {
+            Some(serde_json::Value::String(s)) => Some(s),
+            _ => None,
+        };
+        let code = take_string("code")?;
+        let message = take_string("message")?;
+        let details = fields.remove("details").filter(|d| !d.is_null()).map(Value::from);
+        Some(Self { code, message, details })
+    }
+}
+
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
 }
 
 impl From<String> for Error {
@@ -118,3 +295,67 @@ impl From<&str> for Error {
         Error::Other(s.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        let from_status = |status| Error::from_status(status, "users".to_string());
+        assert!(matches!(from_status(StatusCode::NotFound), Error::KeyNotFound));
+        assert!(matches!(from_status(StatusCode::CollectionNotFound), Error::NotFound(m) if m == "users"));
+        assert!(matches!(from_status(StatusCode::CollectionExists), Error::AlreadyExists(m) if m == "users"));
+        assert!(matches!(from_status(StatusCode::AuthFailed), Error::AuthenticationFailed));
+        assert!(matches!(from_status(StatusCode::AuthRequired), Error::AuthenticationRequired));
+        assert!(matches!(from_status(StatusCode::PermissionDenied), Error::PermissionDenied(_)));
+        assert!(matches!(from_status(StatusCode::VersionMismatch), Error::VersionMismatch));
+        assert!(matches!(from_status(StatusCode::WrongType), Error::WrongType(_)));
+        assert!(matches!(from_status(StatusCode::Full), Error::ServerBusy(_)));
+        assert!(matches!(from_status(StatusCode::InvalidQuery), Error::Server(_)));
+    }
+
+    #[test]
+    fn test_structured_server_error() {
+        let body = r#"{"code": "INVALID_QUERY", "message": "unknown operator $gtt", "details": {"field": "age"}}"#;
+        let error = Error::from_status(StatusCode::InvalidQuery, body.to_string());
+        let detail = error.server_error().unwrap();
+        assert_eq!(detail.code, "INVALID_QUERY");
+        assert_eq!(detail.message, "unknown operator $gtt");
+        assert!(detail.details.as_ref().and_then(Value::as_object).is_some());
+        assert_eq!(error.to_string(), "Server error: INVALID_QUERY: unknown operator $gtt");
+
+        // Dedicated variants keep just the message
+        let error = Error::from_status(StatusCode::PermissionDenied, body.to_string());
+        assert!(matches!(error, Error::PermissionDenied(m) if m == "unknown operator $gtt"));
+
+        // Anything else is passed through as text
+        for body in ["bad query", r#"{"message": "no code"}"#] {
+            let error = Error::from_status(StatusCode::InvalidQuery, body.to_string());
+            assert!(matches!(&error, Error::Server(m) if m.ends_with(body)));
+            assert!(error.server_error().is_none());
+        }
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert!(Error::Io(reset).is_retryable());
+        assert!(Error::ServerBusy("full".to_string()).is_retryable());
+        assert!(Error::PoolExhausted.is_retryable());
+        assert!(!Error::KeyNotFound.is_retryable());
+        assert!(!Error::WrongType("string".to_string()).is_retryable());
+        assert!(!Error::Io(std::io::Error::from(std::io::ErrorKind::InvalidData)).is_retryable());
+    }
+
+    #[test]
+    fn test_protocol_error_messages() {
+        let mismatch = Error::SequenceMismatch { expected: 7, received: 5, opcode: 0x02 };
+        assert_eq!(
+            mismatch.to_string(),
+            "Protocol error: sequence number mismatch for opcode 0x02: expected 7, got 5"
+        );
+        let too_large = Error::FrameTooLarge { len: 20, max: 16 };
+        assert_eq!(too_large.to_string(), "Protocol error: response too large: 20 bytes (max: 16)");
+    }
+}
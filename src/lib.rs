@@ -87,14 +87,19 @@
 //!
 //! ### TLS Connection
 //!
+//! TLS uses `rustls` by default. To use the platform TLS stack instead,
+//! disable default features and enable `native-tls`; [`TlsConfig`] works the
+//! same with either backend. Turning off certificate validation for testing
+//! requires the `dangerous-tls` feature.
+//!
 //! ```no_run
 //! use veddb_client::{Client, TlsConfig};
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
-//!     // Configure TLS
+//!     // Configure TLS, trusting a private CA
 //!     let tls_config = TlsConfig::new("localhost")
-//!         .accept_invalid_certs(); // For testing only
+//!         .with_ca_cert("ca.pem");
 //!     
 //!     // Connect with TLS
 //!     let client = Client::connect_with_tls("127.0.0.1:50051", tls_config).await?;
@@ -146,7 +151,7 @@
 //! - **Document Storage** - MongoDB-like document operations with JSON support
 //! - **Collections & Indexes** - Organize documents and optimize queries
 //! - **Advanced Data Structures** - Redis-like lists, sets, sorted sets, hashes
-//! - **Pub/Sub Messaging** - Real-time publish-subscribe communication
+//! - **Pub/Sub Messaging** - Real-time publish-subscribe communication with automatic resubscription
 //! - **Hybrid Architecture** - Automatic routing between cache and persistent layers
 //!
 //! ## 📖 Advanced Usage
@@ -184,11 +189,15 @@
 //!     let client = Client::connect("127.0.0.1:50051").await?;
 //!     
 //!     // Subscribe to a channel
-//!     client.subscribe("events").await?;
+//!     let mut subscription = client.subscribe("events").await?;
 //!     
 //!     // Publish a message
 //!     client.publish("events", b"Hello, World!").await?;
 //!     
+//!     // Receive it
+//!     let message = subscription.recv().await?;
+//!     println!("{}: {}", message.channel, String::from_utf8_lossy(&message.payload));
+//!     
 //!     Ok(())
 //! }
 //! ```
@@ -222,23 +231,46 @@
 #![warn(rustdoc::missing_crate_level_docs)]
 #![forbid(unsafe_code)]
 
+mod addr;
+mod api;
+#[cfg(feature = "compression")]
+mod compression;
 mod connection;
+mod cursor;
 mod error;
+mod retry;
+mod routing;
+mod schema;
+mod stats;
+mod subscription;
+mod tls;
+mod transaction;
 mod types;
 
+pub use addr::ServerAddr;
+pub use api::VedDbApi;
 pub use connection::{Client, ClientBuilder, Connection, ConnectionPool, TlsConfig, AuthConfig};
-pub use error::Error;
+pub use cursor::{Cursor, KeyScan};
+pub use error::{Error, ServerError};
+pub use retry::RetryPolicy;
+pub use routing::{NodeRole, ReadPreference};
+pub use stats::{ClientStats, PoolStats};
+pub use subscription::{
+    Message, ReconnectPolicy, ResilientSubscription, Subscription, SubscriptionEvent,
+};
+pub use transaction::{Transaction, TransactionOutcome};
 pub use types::{
     Command, Response, StatusCode, OpCode, Value, Document, DocumentId, ObjectId,
     AuthRequest, AuthMethod, AuthCredentials, AuthResponse,
-    QueryRequest, InsertDocRequest, UpdateDocRequest, DeleteDocRequest,
-    CreateCollectionRequest, CreateIndexRequest, IndexField,
+    QueryRequest, QueryBuilder, Projection, Filter, Update, InsertDocRequest, InsertManyRequest, TouchRequest, SelectRequest, GetSchemaRequest, AckRequest, KeyType, ScanRequest, UpdateDocRequest, UpdateResult, DeleteDocRequest,
+    TransactionOp, TransactionRequest, WriteOp, BulkWriteRequest, BulkWriteResult, WriteOpResult,
+    CreateCollectionRequest, CreateIndexRequest, IndexField, IndexInfo,
     ListCollectionsRequest, DropCollectionRequest, DropIndexRequest, ListIndexesRequest,
     ListOpRequest, ListOperation, SetOpRequest, SetOperation,
     SortedSetOpRequest, SortedSetOperation, ScoredMember,
     HashOpRequest, HashOperation, OperationResponse,
-    CreateUserRequest, DeleteUserRequest, UpdateUserRoleRequest, UserInfo, ServerInfo,
-    PROTOCOL_V1, PROTOCOL_V2
+    CreateUserRequest, DeleteUserRequest, GetUserRequest, UpdateUserRoleRequest, UserInfo, ServerInfo, HealthStatus, HealthState, Role,
+    PROTOCOL_V1, PROTOCOL_V2, tabulate
 };
 
 /// Custom result type for VedDB operations
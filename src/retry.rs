@@ -0,0 +1,63 @@
+//! Retrying transient failures
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Backoff settings for retrying idempotent operations after a transient
+/// failure
+///
+/// Only errors for which [`Error::is_retryable`](crate::Error::is_retryable)
+/// holds are retried, and only for operations that are safe to repeat: pings,
+/// `set`, reads and queries. Operations such as `incr`, inserts and pushes
+/// are never retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts per operation, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled after each failure
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Create a retry policy
+    pub fn new(max_attempts: u32, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff,
+            max_backoff,
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 0)
+    ///
+    /// The exponential delay is capped at `max_backoff`, then jittered to
+    /// between half and all of it so that clients failing together don't
+    /// retry in lockstep.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        exponential.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100), Duration::from_millis(300));
+        for _ in 0..20 {
+            let first = policy.backoff(0);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let second = policy.backoff(1);
+            assert!(second >= Duration::from_millis(100) && second <= Duration::from_millis(200));
+            assert!(policy.backoff(10) <= Duration::from_millis(300));
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Routing reads to replica servers
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::connection::ConnectionPool;
+
+/// Every this many reads, [`ReadPreference::Nearest`] sends one to the next
+/// server in turn, so latencies stay current
+const PROBE_INTERVAL: usize = 16;
+
+/// Role of the server a connection talks to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NodeRole {
+    /// The server that accepts writes
+    #[default]
+    Primary,
+    /// A replica, which only serves reads
+    Secondary,
+}
+
+/// Which servers reads go to, see [`ClientBuilder::read_preference`]
+///
+/// Only reads, as classified by [`OpCode::is_read`], are routed; writes
+/// always go to the primary. Replicas may lag behind the primary, so a read
+/// routed to one may not see a write that just completed.
+///
+/// [`ClientBuilder::read_preference`]: crate::ClientBuilder::read_preference
+/// [`OpCode::is_read`]: crate::OpCode::is_read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ReadPreference {
+    /// Read from the primary
+    #[default]
+    Primary,
+    /// Read from each replica in turn, or the primary if there are none
+    Secondary,
+    /// Read from whichever server, primary or replica, has been answering
+    /// reads fastest
+    Nearest,
+}
+
+/// Replica pools, and how reads are spread over them and the primary
+#[derive(Debug)]
+pub(crate) struct Replicas {
+    pools: Vec<ConnectionPool>,
+    preference: ReadPreference,
+    /// Reads routed so far
+    next: AtomicUsize,
+    /// Smoothed read latency in microseconds for each server, primary first,
+    /// or 0 before the first read
+    latency: Vec<AtomicU64>,
+}
+
+impl Replicas {
+    pub(crate) fn new(pools: Vec<ConnectionPool>, preference: ReadPreference) -> Self {
+        let latency = (0..=pools.len()).map(|_| AtomicU64::new(0)).collect();
+        Self {
+            pools,
+            preference,
+            next: AtomicUsize::new(0),
+            latency,
+        }
+    }
+
+    /// The replica pools
+    pub(crate) fn pools(&self) -> &[ConnectionPool] {
+        &self.pools
+    }
+
+    /// Pick the server for the next read: 0 for the primary, `i + 1` for
+    /// replica `i`
+    pub(crate) fn pick(&self) -> usize {
+        let servers = self.pools.len() + 1;
+        match self.preference {
+            ReadPreference::Primary => 0,
+            ReadPreference::Secondary if self.pools.is_empty() => 0,
+            ReadPreference::Secondary => 1 + self.next.fetch_add(1, Ordering::Relaxed) % self.pools.len(),
+            ReadPreference::Nearest => {
+                let n = self.next.fetch_add(1, Ordering::Relaxed);
+                match n % PROBE_INTERVAL {
+                    0 => (n / PROBE_INTERVAL) % servers,
+                    // Unmeasured servers read as 0, so each is tried early on
+                    _ => (0..servers)
+                        .min_by_key(|&i| self.latency[i].load(Ordering::Relaxed))
+                        .unwrap_or(0),
+                }
+            }
+        }
+    }
+
+    /// The pool for server `index`, as returned by [`pick`](Replicas::pick)
+    pub(crate) fn pool<'a>(&'a self, primary: &'a ConnectionPool, index: usize) -> &'a ConnectionPool {
+        match index {
+            0 => primary,
+            i => &self.pools[i - 1],
+        }
+    }
+
+    /// Record how long a read on server `index` took
+    pub(crate) fn record_latency(&self, index: usize, elapsed: Duration) {
+        let sample = (elapsed.as_micros() as u64).max(1);
+        let _ = self.latency[index].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |old| {
+            Some(if old == 0 { sample } else { (old * 7 + sample) / 8 })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replicas(count: usize, preference: ReadPreference) -> Replicas {
+        let pools = (0..count)
+            .map(|_| ConnectionPool::lazy("127.0.0.1:1", 1, None, None, Duration::ZERO, Duration::ZERO))
+            .collect();
+        Replicas::new(pools, preference)
+    }
+
+    #[test]
+    fn test_pick() {
+        let primary = replicas(2, ReadPreference::Primary);
+        assert!((0..4).all(|_| primary.pick() == 0));
+
+        let secondary = replicas(2, ReadPreference::Secondary);
+        let picks: Vec<usize> = (0..4).map(|_| secondary.pick()).collect();
+        assert_eq!(picks, [1, 2, 1, 2]);
+        assert_eq!(replicas(0, ReadPreference::Secondary).pick(), 0);
+
+        let nearest = replicas(2, ReadPreference::Nearest);
+        nearest.record_latency(0, Duration::from_millis(5));
+        nearest.record_latency(1, Duration::from_millis(1));
+        nearest.record_latency(2, Duration::from_millis(9));
+        // The first read probes the primary; the rest go to the fastest
+        assert_eq!(nearest.pick(), 0);
+        assert!((1..PROBE_INTERVAL).all(|_| nearest.pick() == 1));
+        assert_eq!(nearest.pick(), 1);
+        // Then the next probe goes to the second replica
+        (0..PROBE_INTERVAL - 1).for_each(|_| {
+            nearest.pick();
+        });
+        assert_eq!(nearest.pick(), 2);
+    }
+}
@@ -0,0 +1,194 @@
+//! Client-side validation of documents against collection schemas
+//!
+//! Schemas use a subset of JSON Schema: `type` (a name or a list of names),
+//! `properties`, `required`, `additionalProperties: false` and `items`.
+//! Anything else in a schema is left for the server to check.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::types::{Document, Value};
+use crate::{Error, Result};
+
+/// Collection schemas fetched so far, `None` for collections without one
+#[derive(Debug, Default)]
+pub(crate) struct SchemaCache {
+    schemas: Mutex<HashMap<String, Option<Value>>>,
+}
+
+impl SchemaCache {
+    /// Get the cached schema for `collection`, if it has been fetched
+    pub(crate) fn get(&self, collection: &str) -> Option<Option<Value>> {
+        self.schemas.lock().unwrap_or_else(|e| e.into_inner()).get(collection).cloned()
+    }
+
+    /// Remember the schema for `collection`
+    pub(crate) fn insert(&self, collection: &str, schema: Option<Value>) {
+        self.schemas.lock().unwrap_or_else(|e| e.into_inner()).insert(collection.to_string(), schema);
+    }
+
+    /// Forget the schema for `collection`, so it is fetched again
+    pub(crate) fn remove(&self, collection: &str) {
+        self.schemas.lock().unwrap_or_else(|e| e.into_inner()).remove(collection);
+    }
+}
+
+/// Check a document against the schema of `collection`, failing with
+/// [`Error::InvalidArgument`] naming the first offending field
+pub(crate) fn validate(collection: &str, schema: &Value, document: &Document) -> Result<()> {
+    check_object(schema, &document.fields, "").map_err(|problem| {
+        Error::InvalidArgument(format!(
+            "Document {} doesn't match the schema of collection '{}': {}",
+            document.id, collection, problem
+        ))
+    })
+}
+
+/// Check the fields of an object against an object schema
+fn check_object(schema: &Value, fields: &BTreeMap<String, Value>, path: &str) -> std::result::Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        return Ok(());
+    };
+    let properties = schema.get("properties").and_then(Value::as_object);
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !fields.contains_key(name) {
+                return Err(format!("missing required field '{}'", join(path, name)));
+            }
+        }
+    }
+
+    for (name, value) in fields {
+        let field = join(path, name);
+        match properties.and_then(|properties| properties.get(name)) {
+            Some(property) => check_value(property, value, &field)?,
+            None if schema.get("additionalProperties") == Some(&Value::Bool(false)) && field != "_id" => {
+                return Err(format!("field '{}' is not allowed", field));
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Check one value against its schema
+fn check_value(schema: &Value, value: &Value, path: &str) -> std::result::Result<(), String> {
+    let Some(rules) = schema.as_object() else {
+        return Ok(());
+    };
+
+    if let Some(expected) = rules.get("type") {
+        let names: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !names.is_empty() && !names.iter().any(|name| has_type(value, name)) {
+            return Err(format!(
+                "field '{}' should be {}, got {}",
+                path,
+                names.join(" or "),
+                value.type_name()
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(fields) => check_object(schema, fields, path),
+        Value::Array(items) => match rules.get("items") {
+            Some(item_schema) => items
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, item)| check_value(item_schema, item, &format!("{}[{}]", path, i))),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Whether a value has the JSON Schema type `name`; unknown names match
+/// anything
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => matches!(value, Value::Null),
+        "boolean" => matches!(value, Value::Bool(_)),
+        "integer" => matches!(value, Value::Int32(_) | Value::Int64(_)),
+        "number" => matches!(value, Value::Int32(_) | Value::Int64(_) | Value::Float64(_)),
+        // ObjectIds, dates and binary data travel as strings in JSON
+        "string" => matches!(
+            value,
+            Value::String(_) | Value::ObjectId(_) | Value::DateTime(_) | Value::Binary(_)
+        ),
+        "array" => matches!(value, Value::Array(_)),
+        "object" => matches!(value, Value::Object(_)),
+        _ => true,
+    }
+}
+
+/// Path of field `name` within the object at `path`
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Value {
+        Value::from(serde_json::json!({
+            "type": "object",
+            "required": ["name", "age"],
+            "additionalProperties": false,
+            "properties": {
+                "name": {"type": "string"},
+                "age": {"type": "integer"},
+                "score": {"type": ["number", "null"]},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "address": {
+                    "type": "object",
+                    "required": ["city"],
+                    "properties": {"city": {"type": "string"}}
+                }
+            }
+        }))
+    }
+
+    fn check(fields: serde_json::Value) -> Result<()> {
+        let document = Document::try_from(Value::from(fields)).unwrap();
+        validate("users", &schema(), &document)
+    }
+
+    fn problem(fields: serde_json::Value) -> String {
+        match check(fields) {
+            Err(Error::InvalidArgument(message)) => message,
+            other => panic!("expected a schema error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        use serde_json::json;
+
+        check(json!({"name": "Alice", "age": 30})).unwrap();
+        check(json!({
+            "name": "Alice",
+            "age": 30,
+            "score": null,
+            "tags": ["admin"],
+            "address": {"city": "Pune", "zip": "411001"}
+        }))
+        .unwrap();
+
+        assert!(problem(json!({"name": "Alice"})).contains("missing required field 'age'"));
+        assert!(problem(json!({"name": "Alice", "age": "30"})).contains("field 'age' should be integer, got String"));
+        assert!(problem(json!({"name": "Alice", "age": 30, "admin": true})).contains("field 'admin' is not allowed"));
+        assert!(problem(json!({"name": "Alice", "age": 30, "tags": ["a", 1]})).contains("field 'tags[1]'"));
+        assert!(problem(json!({"name": "Alice", "age": 30, "address": {}})).contains("'address.city'"));
+        assert!(problem(json!({"name": "Alice", "age": 30, "score": "high"})).contains("number or null"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Client-side request statistics
+
+#[cfg(feature = "tracing-subscriber")]
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{Error, Result};
+
+/// A snapshot of the requests a client has made, as observed client-side
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    /// Commands sent to the server
+    pub requests: u64,
+    /// Commands that failed, including timeouts (missing keys are not errors)
+    pub errors: u64,
+    /// Commands that timed out
+    pub timeouts: u64,
+    /// Commands retried after a failure
+    pub retries: u64,
+    /// Key lookups that found the key
+    pub hits: u64,
+    /// Key lookups for a key that doesn't exist
+    pub misses: u64,
+}
+
+impl ClientStats {
+    /// Fraction of key lookups that found the key, or `None` before any lookup
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+/// A snapshot of a connection pool's occupancy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Connections currently open, idle or checked out
+    pub total: usize,
+    /// Idle connections waiting in the pool
+    pub available: usize,
+    /// Connections currently checked out
+    pub in_use: usize,
+}
+
+/// Counters shared by every connection of a client
+#[derive(Debug, Default)]
+pub(crate) struct StatsCounters {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    retries: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    #[cfg(feature = "tracing-subscriber")]
+    by_opcode: std::sync::Mutex<BTreeMap<u8, u64>>,
+}
+
+impl StatsCounters {
+    /// Record the outcome of a command
+    pub(crate) fn record<T>(&self, result: &Result<T>) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        match result {
+            Ok(_) | Err(Error::KeyNotFound) => {}
+            Err(e) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                if matches!(e, Error::Timeout(_)) {
+                    self.timeouts.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Count a command sent with `opcode`
+    #[cfg(feature = "tracing-subscriber")]
+    pub(crate) fn record_opcode(&self, opcode: u8) {
+        *self.by_opcode.lock().unwrap_or_else(|e| e.into_inner()).entry(opcode).or_default() += 1;
+    }
+
+    /// Commands sent so far, keyed by opcode
+    #[cfg(feature = "tracing-subscriber")]
+    pub(crate) fn opcode_counts(&self) -> BTreeMap<u8, u64> {
+        self.by_opcode.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Record a retried command
+    pub(crate) fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record whether a key lookup found the key
+    pub(crate) fn record_lookup(&self, found: bool) {
+        let counter = if found { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Read the current counts
+    pub(crate) fn snapshot(&self) -> ClientStats {
+        ClientStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reset every count to zero
+    pub(crate) fn reset(&self) {
+        for counter in [
+            &self.requests,
+            &self.errors,
+            &self.timeouts,
+            &self.retries,
+            &self.hits,
+            &self.misses,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        #[cfg(feature = "tracing-subscriber")]
+        self.by_opcode.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let counters = StatsCounters::default();
+        counters.record(&Ok(()));
+        counters.record::<()>(&Err(Error::KeyNotFound));
+        counters.record::<()>(&Err(Error::Server("boom".to_string())));
+        counters.record_lookup(true);
+        counters.record_lookup(false);
+        counters.record_lookup(true);
+
+        let stats = counters.snapshot();
+        assert_eq!((stats.requests, stats.errors, stats.timeouts), (3, 1, 0));
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+        assert!((stats.hit_rate().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+
+        counters.reset();
+        assert_eq!(counters.snapshot(), ClientStats::default());
+        assert_eq!(ClientStats::default().hit_rate(), None);
+    }
+
+    #[cfg(feature = "tracing-subscriber")]
+    #[test]
+    fn test_opcode_counts() {
+        let counters = StatsCounters::default();
+        counters.record_opcode(0x01);
+        counters.record_opcode(0x02);
+        counters.record_opcode(0x01);
+        assert_eq!(counters.opcode_counts(), BTreeMap::from([(0x01, 2), (0x02, 1)]));
+
+        counters.reset();
+        assert!(counters.opcode_counts().is_empty());
+    }
+}
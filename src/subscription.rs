@@ -0,0 +1,420 @@
+//! Pub/Sub subscriptions for VedDB client
+//!
+//! Each subscription owns a dedicated connection. The connection's reader
+//! routes server-pushed messages to the subscription and request replies to
+//! their callers, so requests can still be made on a subscribed connection.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::{Buf, Bytes};
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::addr::ServerAddr;
+use crate::connection::{AuthConfig, Connection, TlsConfig};
+use crate::types::{response_flags, Response};
+use crate::{Error, Result};
+
+/// Default delay before the first reconnection attempt
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// Default upper bound on the delay between reconnection attempts
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Delivery ids acknowledged but not yet sent to the server
+type AckQueue = Arc<Mutex<Vec<u64>>>;
+
+/// A message received on a subscribed channel
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// Channel the message was published on
+    pub channel: String,
+    /// Message body
+    pub payload: Bytes,
+    /// When the client received the message
+    pub received_at: DateTime<Utc>,
+    /// Delivery id to acknowledge, for at-least-once subscriptions
+    pub delivery_id: Option<u64>,
+    /// Queue the acknowledgment is recorded in
+    acks: Option<AckQueue>,
+}
+
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.channel == other.channel
+            && self.payload == other.payload
+            && self.received_at == other.received_at
+            && self.delivery_id == other.delivery_id
+    }
+}
+
+impl Eq for Message {}
+
+impl Message {
+    /// Acknowledge the message so the server won't redeliver it
+    ///
+    /// Acknowledgments are sent on the subscription's next
+    /// [`recv`](Subscription::recv) or [`unsubscribe_all`](Subscription::unsubscribe_all).
+    /// If the connection is lost first, the message is redelivered after
+    /// reconnecting. This is a no-op for fire-and-forget messages.
+    pub fn ack(&self) {
+        if let (Some(id), Some(acks)) = (self.delivery_id, &self.acks) {
+            acks.lock().unwrap_or_else(|e| e.into_inner()).push(id);
+        }
+    }
+
+    /// Decode a pushed message frame
+    ///
+    /// The payload of a push frame is the channel name length (u32,
+    /// little-endian), the channel name, then the message body. Frames
+    /// flagged [`ACK_REQUIRED`](response_flags::ACK_REQUIRED) are prefixed
+    /// with a u64 delivery id.
+    pub(crate) fn from_response(response: Response) -> Result<Self> {
+        let mut payload = response.payload;
+        let delivery_id = if response.header.flags & response_flags::ACK_REQUIRED != 0 {
+            if payload.len() < 8 {
+                return Err(Error::Protocol("Push message missing delivery id".to_string()));
+            }
+            Some(payload.get_u64_le())
+        } else {
+            None
+        };
+
+        if payload.len() < 4 {
+            return Err(Error::Protocol("Push message too short".to_string()));
+        }
+
+        let channel_len = payload.get_u32_le() as usize;
+        if payload.len() < channel_len {
+            return Err(Error::Protocol(format!(
+                "Push message channel length {} exceeds payload length {}",
+                channel_len,
+                payload.len()
+            )));
+        }
+
+        let channel = payload.split_to(channel_len);
+        let channel = String::from_utf8(channel.to_vec())
+            .map_err(|e| Error::Protocol(format!("Invalid channel name: {}", e)))?;
+
+        Ok(Self {
+            channel,
+            payload,
+            received_at: Utc::now(),
+            delivery_id,
+            acks: None,
+        })
+    }
+}
+
+/// A subscription to one or more pub/sub channels or channel patterns
+///
+/// If the connection drops, [`recv`](Subscription::recv) returns the
+/// underlying error; use [`ResilientSubscription`] to reconnect automatically.
+#[derive(Debug)]
+pub struct Subscription {
+    /// Dedicated connection receiving pushed messages
+    conn: Connection,
+    /// Subscribed channels
+    channels: Vec<String>,
+    /// Subscribed glob patterns
+    patterns: Vec<String>,
+    /// Maximum unacknowledged messages (0 for fire-and-forget delivery)
+    max_unacked: u32,
+    /// Acknowledgments waiting to be sent
+    acks: AckQueue,
+}
+
+impl Subscription {
+    /// Open a dedicated connection and subscribe it to `channels` and
+    /// `patterns`, with at-least-once delivery if `max_unacked` is non-zero
+    pub(crate) async fn open(
+        addr: ServerAddr,
+        tls_config: Option<TlsConfig>,
+        auth_config: Option<AuthConfig>,
+        channels: Vec<String>,
+        patterns: Vec<String>,
+        max_unacked: u32,
+    ) -> Result<Self> {
+        let conn = Connection::connect_with_config(addr, tls_config, auth_config).await?;
+        for channel in &channels {
+            conn.subscribe_with_ack(channel, max_unacked).await?;
+        }
+        for pattern in &patterns {
+            conn.psubscribe_with_ack(pattern, max_unacked).await?;
+        }
+        Ok(Self {
+            conn,
+            channels,
+            patterns,
+            max_unacked,
+            acks: AckQueue::default(),
+        })
+    }
+
+    /// Get the subscribed channels
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Get the subscribed glob patterns
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Wait for the next message
+    pub async fn recv(&mut self) -> Result<Message> {
+        self.flush_acks().await?;
+        let response = self.conn.read_push().await?;
+        let mut message = Message::from_response(response)?;
+        if message.delivery_id.is_some() {
+            message.acks = Some(self.acks.clone());
+        }
+        Ok(message)
+    }
+
+    /// Send any pending acknowledgments
+    async fn flush_acks(&self) -> Result<()> {
+        let pending = std::mem::take(&mut *self.acks.lock().unwrap_or_else(|e| e.into_inner()));
+        if pending.is_empty() {
+            return Ok(());
+        }
+        self.conn.ack(pending).await
+    }
+
+    /// Unsubscribe from every channel and pattern and close the
+    /// subscription, sending any pending acknowledgments first
+    pub async fn unsubscribe_all(self) -> Result<()> {
+        self.flush_acks().await?;
+        for channel in &self.channels {
+            self.conn.unsubscribe(channel).await?;
+        }
+        for pattern in &self.patterns {
+            self.conn.punsubscribe(pattern).await?;
+        }
+        self.conn.close().await
+    }
+}
+
+/// Backoff settings for re-establishing a dropped subscription
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Delay before the first reconnection attempt; doubled after each failure
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+    /// Give up after this many consecutive failed attempts (`None` retries forever)
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            max_attempts: None,
+        }
+    }
+}
+
+/// An event delivered by a [`ResilientSubscription`]
+#[derive(Debug, Clone)]
+pub enum SubscriptionEvent {
+    /// A message was received
+    Message(Message),
+    /// The connection was lost and re-established.
+    ///
+    /// Messages published while disconnected were not delivered, so this
+    /// marks a possible gap in the stream.
+    Reconnected {
+        /// Number of attempts it took to reconnect
+        attempts: u32,
+    },
+}
+
+/// A subscription that survives connection loss
+///
+/// When the connection drops, it reconnects with exponential backoff
+/// (re-using the client's TLS and authentication settings), re-subscribes to
+/// the same channels and emits [`SubscriptionEvent::Reconnected`].
+#[derive(Debug)]
+pub struct ResilientSubscription {
+    /// Server address
+    addr: ServerAddr,
+    /// TLS configuration
+    tls_config: Option<TlsConfig>,
+    /// Authentication configuration
+    auth_config: Option<AuthConfig>,
+    /// Subscribed channels
+    channels: Vec<String>,
+    /// Subscribed glob patterns
+    patterns: Vec<String>,
+    /// Maximum unacknowledged messages (0 for fire-and-forget delivery)
+    max_unacked: u32,
+    /// Reconnection backoff settings
+    policy: ReconnectPolicy,
+    /// The live subscription, if currently connected
+    inner: Option<Subscription>,
+}
+
+impl ResilientSubscription {
+    /// Wrap an established subscription
+    pub(crate) fn new(
+        subscription: Subscription,
+        addr: ServerAddr,
+        tls_config: Option<TlsConfig>,
+        auth_config: Option<AuthConfig>,
+    ) -> Self {
+        Self {
+            addr,
+            tls_config,
+            auth_config,
+            channels: subscription.channels.clone(),
+            patterns: subscription.patterns.clone(),
+            max_unacked: subscription.max_unacked,
+            policy: ReconnectPolicy::default(),
+            inner: Some(subscription),
+        }
+    }
+
+    /// Set the reconnection backoff policy
+    pub fn with_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Get the subscribed channels
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Get the subscribed glob patterns
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Wait for the next event, reconnecting if the connection was lost
+    ///
+    /// Returns an error only for non-connection failures, or when the policy's
+    /// `max_attempts` is exhausted while reconnecting.
+    pub async fn recv(&mut self) -> Result<SubscriptionEvent> {
+        if let Some(subscription) = self.inner.as_mut() {
+            match subscription.recv().await {
+                Ok(message) => return Ok(SubscriptionEvent::Message(message)),
+                Err(e) if is_connection_lost(&e) => {
+                    warn!("Subscription to {:?} lost: {}", self.channels, e);
+                    self.inner = None;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let attempts = self.reconnect().await?;
+        Ok(SubscriptionEvent::Reconnected { attempts })
+    }
+
+    /// Re-open the subscription, returning the number of attempts it took
+    async fn reconnect(&mut self) -> Result<u32> {
+        let mut backoff = self.policy.initial_backoff;
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            match Subscription::open(
+                self.addr.clone(),
+                self.tls_config.clone(),
+                self.auth_config.clone(),
+                self.channels.clone(),
+                self.patterns.clone(),
+                self.max_unacked,
+            )
+            .await
+            {
+                Ok(subscription) => {
+                    info!("Resubscribed to {:?} after {} attempt(s)", self.channels, attempts);
+                    self.inner = Some(subscription);
+                    return Ok(attempts);
+                }
+                Err(e) => {
+                    if self.policy.max_attempts.is_some_and(|max| attempts >= max) {
+                        return Err(e);
+                    }
+                    warn!("Resubscribe attempt {} failed: {}; retrying in {:?}", attempts, e, backoff);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.policy.max_backoff);
+                }
+            }
+        }
+    }
+}
+
+/// Check whether an error means the subscription's connection is gone
+fn is_connection_lost(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Io(_) | Error::Connection(_) | Error::Timeout(_) | Error::Tls(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ResponseHeader, StatusCode};
+    use bytes::{BufMut, BytesMut};
+
+    fn push_frame(channel: &str, body: &[u8]) -> Response {
+        let mut payload = BytesMut::new();
+        payload.put_u32_le(channel.len() as u32);
+        payload.extend_from_slice(channel.as_bytes());
+        payload.extend_from_slice(body);
+
+        let mut header = ResponseHeader::new(StatusCode::Ok, 0);
+        header.flags = response_flags::PUSH;
+        Response::new(header, payload.freeze())
+    }
+
+    #[test]
+    fn test_message_decoding() {
+        let response = push_frame("news", b"hello");
+        assert!(response.is_push());
+
+        let message = Message::from_response(response).unwrap();
+        assert_eq!(message.channel, "news");
+        assert_eq!(&message.payload[..], b"hello");
+    }
+
+    #[test]
+    fn test_acknowledged_message_decoding() {
+        let plain = push_frame("news", b"hello");
+        let mut payload = BytesMut::new();
+        payload.put_u64_le(42);
+        payload.extend_from_slice(&plain.payload);
+        let mut header = plain.header;
+        header.flags |= response_flags::ACK_REQUIRED;
+
+        let mut message = Message::from_response(Response::new(header, payload.freeze())).unwrap();
+        assert_eq!(message.delivery_id, Some(42));
+        assert_eq!(message.channel, "news");
+        assert_eq!(&message.payload[..], b"hello");
+
+        let acks = AckQueue::default();
+        message.acks = Some(acks.clone());
+        message.ack();
+        assert_eq!(*acks.lock().unwrap(), vec![42]);
+
+        // Fire-and-forget messages have nothing to acknowledge
+        let message = Message::from_response(push_frame("news", b"hello")).unwrap();
+        assert_eq!(message.delivery_id, None);
+        message.ack();
+    }
+
+    #[test]
+    fn test_message_decoding_rejects_truncated_channel() {
+        let mut response = push_frame("news", b"");
+        response.payload = response.payload.slice(0..6);
+        assert!(matches!(
+            Message::from_response(response),
+            Err(Error::Protocol(_))
+        ));
+    }
+}
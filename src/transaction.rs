@@ -0,0 +1,176 @@
+//! Multi-collection transactions
+
+use crate::connection::Client;
+use crate::types::{
+    DeleteDocRequest, Document, DocumentId, InsertDocRequest, TransactionOp, TransactionRequest,
+    UpdateDocRequest, Value,
+};
+use crate::{Error, Result};
+
+/// The result of one operation in a committed transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionOutcome {
+    /// A document was inserted with this id
+    Inserted(DocumentId),
+    /// This many documents were updated
+    Updated(u64),
+    /// This many documents were deleted
+    Deleted(u64),
+}
+
+/// A batch of writes, possibly spanning several collections, committed
+/// all-or-nothing
+///
+/// Writes are queued locally and sent to the server in a single round trip
+/// on [`commit`](Transaction::commit). The server applies them atomically on
+/// the node that receives the commit: either every operation takes effect or
+/// none does. Atomicity across replicas is best-effort, as replication of the
+/// committed writes is asynchronous.
+#[derive(Debug)]
+pub struct Transaction {
+    /// Client used to commit
+    client: Client,
+    /// Queued operations, in order
+    operations: Vec<TransactionOp>,
+}
+
+impl Transaction {
+    /// Start an empty transaction
+    pub(crate) fn new(client: Client) -> Self {
+        Self {
+            client,
+            operations: Vec::new(),
+        }
+    }
+
+    /// Queue a document insert
+    pub fn insert(&mut self, collection: &str, document: Document) -> &mut Self {
+        self.operations.push(TransactionOp::Insert(InsertDocRequest {
+            collection: collection.to_string(),
+            document,
+        }));
+        self
+    }
+
+    /// Queue an update of the documents matching `filter`
+    pub fn update(&mut self, collection: &str, filter: Value, update: Value) -> &mut Self {
+        self.operations.push(TransactionOp::Update(UpdateDocRequest {
+            collection: collection.to_string(),
+            filter,
+            update,
+            upsert: false,
+        }));
+        self
+    }
+
+    /// Queue a delete of the documents matching `filter`
+    pub fn delete(&mut self, collection: &str, filter: Value) -> &mut Self {
+        self.operations.push(TransactionOp::Delete(DeleteDocRequest {
+            collection: collection.to_string(),
+            filter,
+            limit: None,
+        }));
+        self
+    }
+
+    /// Get the queued operations
+    pub fn operations(&self) -> &[TransactionOp] {
+        &self.operations
+    }
+
+    /// Commit the queued writes, returning one outcome per operation in the
+    /// order they were queued
+    ///
+    /// If the server rejects any operation, the whole transaction is rolled
+    /// back and an error is returned.
+    pub async fn commit(self) -> Result<Vec<TransactionOutcome>> {
+        if self.operations.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pending = self.operations.iter().map(pending_outcome).collect();
+        let request = TransactionRequest {
+            operations: self.operations,
+        };
+        let counts = self.client.commit_transaction(request).await?;
+        outcomes(pending, counts)
+    }
+}
+
+/// The outcome of an operation, before the server reports its affected count
+fn pending_outcome(op: &TransactionOp) -> TransactionOutcome {
+    match op {
+        TransactionOp::Insert(insert) => TransactionOutcome::Inserted(insert.document.id),
+        TransactionOp::Update(_) => TransactionOutcome::Updated(0),
+        TransactionOp::Delete(_) => TransactionOutcome::Deleted(0),
+    }
+}
+
+/// Fill in each pending outcome with the affected count the server reported
+fn outcomes(pending: Vec<TransactionOutcome>, counts: Vec<u64>) -> Result<Vec<TransactionOutcome>> {
+    pending
+        .into_iter()
+        .zip(counts)
+        .map(|(outcome, count)| match outcome {
+            TransactionOutcome::Inserted(id) if count == 1 => Ok(TransactionOutcome::Inserted(id)),
+            TransactionOutcome::Inserted(_) => Err(Error::InvalidResponse(format!(
+                "Transaction insert affected {} documents",
+                count
+            ))),
+            TransactionOutcome::Updated(_) => Ok(TransactionOutcome::Updated(count)),
+            TransactionOutcome::Deleted(_) => Ok(TransactionOutcome::Deleted(count)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcomes_follow_operation_order() {
+        let document = Document::new();
+        let id = document.id;
+        let operations = vec![
+            TransactionOp::Insert(InsertDocRequest {
+                collection: "orders".to_string(),
+                document,
+            }),
+            TransactionOp::Update(UpdateDocRequest {
+                collection: "order_items".to_string(),
+                filter: Value::Null,
+                update: Value::Null,
+                upsert: false,
+            }),
+            TransactionOp::Delete(DeleteDocRequest {
+                collection: "carts".to_string(),
+                filter: Value::Null,
+                limit: None,
+            }),
+        ];
+
+        let pending: Vec<_> = operations.iter().map(pending_outcome).collect();
+        assert_eq!(
+            outcomes(pending.clone(), vec![1, 3, 0]).unwrap(),
+            vec![
+                TransactionOutcome::Inserted(id),
+                TransactionOutcome::Updated(3),
+                TransactionOutcome::Deleted(0),
+            ]
+        );
+        assert!(outcomes(pending, vec![0, 3, 0]).is_err());
+    }
+
+    #[test]
+    fn test_operation_wire_format() {
+        let op = TransactionOp::Delete(DeleteDocRequest {
+            collection: "carts".to_string(),
+            filter: Value::Null,
+            limit: None,
+        });
+        let json = serde_json::to_value(&op).unwrap();
+        assert_eq!(json["op"], "delete");
+        assert_eq!(json["collection"], "carts");
+        assert!(json.get("limit").is_none());
+    }
+}
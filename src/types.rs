@@ -31,6 +31,15 @@ pub enum ProtocolError {
     /// Serialization error
     #[error("Serialization error: {0}")]
     Serialization(String),
+
+    /// The CRC32 trailer doesn't match the frame's contents
+    #[error("checksum mismatch: expected {expected:#010x}, got {actual:#010x}")]
+    ChecksumMismatch {
+        /// Checksum carried by the frame
+        expected: u32,
+        /// Checksum computed over the received bytes
+        actual: u32,
+    },
 }
 
 /// Protocol version constants
@@ -113,6 +122,66 @@ pub enum OpCode {
     CreateUser = 0x3C,
     DeleteUser = 0x3D,
     UpdateUserRole = 0x3E,
+    /// Fetch a single user
+    GetUser = 0x3F,
+    
+    // Bulk document operations
+    /// Insert several documents
+    InsertMany = 0x40,
+    
+    // Key maintenance
+    /// Reset the idle time of keys
+    Touch = 0x41,
+    /// Iterate over the keyspace
+    Scan = 0x42,
+    
+    // Pub/Sub acknowledgment
+    /// Acknowledge delivered messages
+    Ack = 0x43,
+    
+    // Transactions
+    /// Commit a batch of writes atomically
+    Transaction = 0x44,
+    
+    // Key expiry
+    /// Set a key's time to live
+    Expire = 0x45,
+    /// Get a key's remaining time to live
+    Ttl = 0x46,
+    
+    // Counters
+    /// Increment a counter
+    Incr = 0x47,
+    
+    // Aggregation
+    /// Get the distinct values of a field
+    Distinct = 0x48,
+    
+    // Pattern pub/sub
+    /// Subscribe to channels matching a pattern
+    PSubscribe = 0x49,
+    /// Unsubscribe from a pattern
+    PUnsubscribe = 0x4A,
+    
+    // Key presence
+    /// Check whether keys exist
+    Exists = 0x4B,
+    
+    // Health checks
+    /// Report server health
+    Health = 0x4C,
+    
+    // Logical databases
+    /// Switch logical database
+    Select = 0x4D,
+    
+    // Collection schemas
+    /// Get a collection's schema
+    GetSchema = 0x4E,
+    
+    // Mixed document writes
+    /// Apply mixed writes to a collection
+    BulkWrite = 0x4F,
 }
 
 impl TryFrom<u8> for OpCode {
@@ -177,11 +246,76 @@ impl TryFrom<u8> for OpCode {
             0x3C => Ok(OpCode::CreateUser),
             0x3D => Ok(OpCode::DeleteUser),
             0x3E => Ok(OpCode::UpdateUserRole),
+            0x3F => Ok(OpCode::GetUser),
+            0x40 => Ok(OpCode::InsertMany),
+            0x41 => Ok(OpCode::Touch),
+            0x42 => Ok(OpCode::Scan),
+            0x43 => Ok(OpCode::Ack),
+            0x44 => Ok(OpCode::Transaction),
+            0x45 => Ok(OpCode::Expire),
+            0x46 => Ok(OpCode::Ttl),
+            0x47 => Ok(OpCode::Incr),
+            0x48 => Ok(OpCode::Distinct),
+            0x49 => Ok(OpCode::PSubscribe),
+            0x4A => Ok(OpCode::PUnsubscribe),
+            0x4B => Ok(OpCode::Exists),
+            0x4C => Ok(OpCode::Health),
+            0x4D => Ok(OpCode::Select),
+            0x4E => Ok(OpCode::GetSchema),
+            0x4F => Ok(OpCode::BulkWrite),
             _ => Err(ProtocolError::InvalidOpCode(value)),
         }
     }
 }
 
+impl OpCode {
+    /// Whether the command only reads data, so a replica can serve it
+    ///
+    /// Reads are `Get`, `Fetch`, `Query`, `Scan`, `Ttl`, `Exists`,
+    /// `Distinct`, `ListCollections`, `ListIndexes`, `GetSchema`, and the
+    /// data structure lookups: `LRange`, `LLen`, `SMembers`, `SIsMember`, `SCard`,
+    /// `SUnion`, `SInter`, `SDiff`, `ZRange`, `ZRangeByScore`, `ZCard`,
+    /// `ZScore`, `HGet`, `HGetAll`, `HKeys`, `HVals` and `HLen`.
+    ///
+    /// Everything else counts as a write and always goes to the primary:
+    /// commands that modify data, transactions, pub/sub, commands that set
+    /// up the connection (`Auth`, `Select`), user management (lookups
+    /// included, so they see role changes straight away) and commands about
+    /// the server itself (`Ping`, `Info`, `Health`).
+    pub fn is_read(self) -> bool {
+        matches!(
+            self,
+            OpCode::Get
+                | OpCode::Fetch
+                | OpCode::Query
+                | OpCode::Scan
+                | OpCode::Ttl
+                | OpCode::Exists
+                | OpCode::Distinct
+                | OpCode::ListCollections
+                | OpCode::ListIndexes
+                | OpCode::GetSchema
+                | OpCode::LRange
+                | OpCode::LLen
+                | OpCode::SMembers
+                | OpCode::SIsMember
+                | OpCode::SCard
+                | OpCode::SUnion
+                | OpCode::SInter
+                | OpCode::SDiff
+                | OpCode::ZRange
+                | OpCode::ZRangeByScore
+                | OpCode::ZCard
+                | OpCode::ZScore
+                | OpCode::HGet
+                | OpCode::HGetAll
+                | OpCode::HKeys
+                | OpCode::HVals
+                | OpCode::HLen
+        )
+    }
+}
+
 /// Response status codes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StatusCode {
@@ -213,6 +347,8 @@ pub enum StatusCode {
     IndexExists = 0x0C,
     /// Index not found
     IndexNotFound = 0x0D,
+    /// The key holds a value of the wrong kind for the operation
+    WrongType = 0x0E,
 }
 
 impl TryFrom<u8> for StatusCode {
@@ -234,6 +370,7 @@ impl TryFrom<u8> for StatusCode {
             0x0B => Ok(StatusCode::CollectionNotFound),
             0x0C => Ok(StatusCode::IndexExists),
             0x0D => Ok(StatusCode::IndexNotFound),
+            0x0E => Ok(StatusCode::WrongType),
             _ => Err(ProtocolError::InvalidStatusCode(value)),
         }
     }
@@ -245,10 +382,20 @@ pub mod flags {
     pub const URGENT: u8 = 0x02; // High priority operation
     pub const TTL: u8 = 0x04; // Extra field contains TTL
     pub const CAS_VERSION: u8 = 0x08; // Extra field contains expected version
+    pub const COMPRESSED: u8 = 0x10; // Value is zstd-compressed
+    pub const CHECKSUM: u8 = 0x20; // Body is followed by a CRC32 of the key and value
+}
+
+/// Response flags
+pub mod response_flags {
+    pub const PUSH: u8 = 0x01; // Server-initiated pub/sub message, not a reply to a command
+    pub const ACK_REQUIRED: u8 = 0x02; // Push message prefixed with a u64 delivery id to acknowledge
+    pub const COMPRESSED: u8 = 0x04; // Payload is zstd-compressed
+    pub const CHECKSUM: u8 = 0x08; // Payload is followed by a CRC32 of the payload
 }
 
 /// Command header (24 bytes, little-endian)
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub struct CommandHeader {
     /// Operation code
@@ -270,6 +417,9 @@ pub struct CommandHeader {
 }
 
 impl CommandHeader {
+    /// Size of the serialized header in bytes
+    pub const SIZE: usize = 24;
+
     /// Create a new command header with v0.2.0 protocol
     pub fn new(opcode: OpCode, seq: u32) -> Self {
         Self {
@@ -326,6 +476,25 @@ impl CommandHeader {
     pub fn total_payload_len(&self) -> usize {
         (self.key_len + self.value_len) as usize
     }
+
+    /// Deserialize a command header from its 24-byte wire form
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, ProtocolError> {
+        if bytes.len() < Self::SIZE {
+            return Err(ProtocolError::InvalidFormat("command header too short".into()));
+        }
+
+        // Read header - ALL LITTLE-ENDIAN
+        Ok(Self {
+            opcode: bytes.get_u8(),
+            flags: bytes.get_u8(),
+            version: bytes.get_u8(),
+            reserved: bytes.get_u8(),
+            seq: bytes.get_u32_le(),
+            key_len: bytes.get_u32_le(),
+            value_len: bytes.get_u32_le(),
+            extra: bytes.get_u64_le(),
+        })
+    }
 }
 
 /// Command structure
@@ -398,14 +567,83 @@ impl Command {
         )
     }
 
+    /// Create a SET command whose key expires after `ttl`
+    ///
+    /// The TTL is sent in milliseconds in the header's extra field.
+    pub fn set_with_ttl<K, V>(seq: u32, key: K, value: V, ttl: std::time::Duration) -> Self
+    where
+        K: Into<Bytes>,
+        V: Into<Bytes>,
+    {
+        Self::new(
+            CommandHeader::new(OpCode::Set, seq)
+                .with_flag(flags::TTL)
+                .with_extra(ttl_millis(ttl)),
+            key,
+            value,
+        )
+    }
+
+    /// Create an EXPIRE command, setting a key to expire after `ttl`
+    pub fn expire<K>(seq: u32, key: K, ttl: std::time::Duration) -> Self
+    where
+        K: Into<Bytes>,
+    {
+        Self::new(
+            CommandHeader::new(OpCode::Expire, seq)
+                .with_flag(flags::TTL)
+                .with_extra(ttl_millis(ttl)),
+            key,
+            Bytes::new(),
+        )
+    }
+
+    /// Create an EXISTS command, checking for a key without fetching its value
+    pub fn exists<K>(seq: u32, key: K) -> Self
+    where
+        K: Into<Bytes>,
+    {
+        Self::new(CommandHeader::new(OpCode::Exists, seq), key, Bytes::new())
+    }
+
+    /// Create a TTL command, asking how long a key has left
+    pub fn ttl<K>(seq: u32, key: K) -> Self
+    where
+        K: Into<Bytes>,
+    {
+        Self::new(CommandHeader::new(OpCode::Ttl, seq), key, Bytes::new())
+    }
+
+    /// Create an INCR command, adding `delta` to the integer stored at a key
+    ///
+    /// The delta is sent in the header's extra field as a two's complement
+    /// `i64`.
+    pub fn incr<K>(seq: u32, key: K, delta: i64) -> Self
+    where
+        K: Into<Bytes>,
+    {
+        Self::new(
+            CommandHeader::new(OpCode::Incr, seq).with_extra(delta as u64),
+            key,
+            Bytes::new(),
+        )
+    }
+
     /// Create a FETCH command (list keys)
     pub fn fetch(seq: u32, key: impl Into<Bytes>) -> Self {
         Self::new(CommandHeader::new(OpCode::Fetch, seq), key, Bytes::new())
     }
 
+    /// Size of the serialized command in bytes, including the header and
+    /// any checksum
+    pub fn encoded_len(&self) -> usize {
+        let checksum_len = if self.header.has_flag(flags::CHECKSUM) { 4 } else { 0 };
+        CommandHeader::SIZE + self.key.len() + self.value.len() + checksum_len
+    }
+
     /// Serialize the command to bytes
     pub fn to_bytes(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(24 + self.key.len() + self.value.len());
+        let mut buf = BytesMut::with_capacity(self.encoded_len());
 
         // Write header (24 bytes) - ALL LITTLE-ENDIAN
         buf.put_u8(self.header.opcode);
@@ -421,11 +659,69 @@ impl Command {
         buf.extend_from_slice(&self.key);
         buf.extend_from_slice(&self.value);
 
+        if self.header.has_flag(flags::CHECKSUM) {
+            buf.put_u32_le(crc32(&[&self.key, &self.value]));
+        }
+
         buf.freeze()
     }
+
+    /// Deserialize a command from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        let header = CommandHeader::from_bytes(bytes)?;
+        let body = &bytes[CommandHeader::SIZE..];
+        let key_len = header.key_len as usize;
+        let value_len = header.value_len as usize;
+
+        if body.len() < key_len + value_len {
+            return Err(ProtocolError::InvalidFormat("invalid key/value length".into()));
+        }
+        let (key, value) = body[..key_len + value_len].split_at(key_len);
+
+        if header.has_flag(flags::CHECKSUM) {
+            let trailer = body
+                .get(key_len + value_len..key_len + value_len + 4)
+                .ok_or_else(|| ProtocolError::InvalidFormat("missing checksum".into()))?;
+            verify_checksum(trailer, &[key, value])?;
+        }
+
+        Ok(Self {
+            header,
+            key: Bytes::copy_from_slice(key),
+            value: Bytes::copy_from_slice(value),
+        })
+    }
+}
+
+/// CRC32 (IEEE 802.3) of the concatenation of `parts`
+pub(crate) fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Check a little-endian CRC32 trailer against the bytes it covers
+fn verify_checksum(trailer: &[u8], parts: &[&[u8]]) -> Result<(), ProtocolError> {
+    let expected = u32::from_le_bytes(trailer.try_into().expect("4-byte checksum"));
+    let actual = crc32(parts);
+    if expected != actual {
+        return Err(ProtocolError::ChecksumMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// Convert a TTL to whole milliseconds, rounding up so that a non-zero TTL
+/// never becomes zero
+fn ttl_millis(ttl: std::time::Duration) -> u64 {
+    u64::try_from(ttl.as_nanos().div_ceil(1_000_000)).unwrap_or(u64::MAX)
 }
 
-/// Response header (20 bytes)
+/// Response header (16 bytes in v0.2.0, 20 bytes in v0.1.x)
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct ResponseHeader {
@@ -444,6 +740,23 @@ pub struct ResponseHeader {
 }
 
 impl ResponseHeader {
+    /// Size of a v0.2.0 response header
+    pub const SIZE: usize = 16;
+    /// Size of a v0.1.x response header
+    pub const SIZE_V1: usize = 20;
+
+    /// Get the header size for a protocol version
+    pub fn size_for(version: u8) -> Result<usize, ProtocolError> {
+        match version {
+            PROTOCOL_V1 => Ok(Self::SIZE_V1),
+            PROTOCOL_V2 => Ok(Self::SIZE),
+            _ => Err(ProtocolError::InvalidFormat(format!(
+                "unsupported protocol version {}",
+                version
+            ))),
+        }
+    }
+
     /// Create a new response header
     pub fn new(status: StatusCode, seq: u32) -> Self {
         Self {
@@ -500,10 +813,13 @@ impl Response {
         )
     }
 
-    /// Deserialize a response from bytes
-    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, ProtocolError> {
-        // Support both v0.1.x (20-byte header) and v0.2.0 (16-byte header)
-        if bytes.len() < 16 {
+    /// Deserialize a response framed for the given protocol version
+    ///
+    /// v0.2.0 headers end in 4 bytes of padding and always have an `extra`
+    /// of 0; v0.1.x headers end in the 8-byte `extra` field. Any bytes after
+    /// the payload are ignored.
+    pub fn from_bytes(mut bytes: &[u8], version: u8) -> Result<Self, ProtocolError> {
+        if bytes.len() < ResponseHeader::size_for(version)? {
             return Err(ProtocolError::InvalidFormat("response too short".into()));
         }
 
@@ -513,18 +829,10 @@ impl Response {
         let reserved = bytes.get_u16_le();
         let seq = bytes.get_u32_le();
         let payload_len = bytes.get_u32_le() as usize;
-        
-        // v0.2.0 uses 16-byte header (no extra field), v0.1.x uses 20-byte header
-        // v0.2.0 uses 16-byte header (no extra field), v0.1.x uses 20-byte header
-        // We have already consumed 12 bytes.
-        let extra = if bytes.len() >= payload_len + 8 {
-            // v0.1.x format with extra field (8 bytes remaining from header)
+        let extra = if version == PROTOCOL_V1 {
             bytes.get_u64_le()
         } else {
-            // v0.2.0 format without extra field (4 bytes padding remaining from header)
-            if bytes.len() >= payload_len + 4 {
-                bytes.advance(4);
-            }
+            bytes.advance(4);
             0
         };
 
@@ -538,6 +846,13 @@ impl Response {
         // Read payload
         let payload = bytes.copy_to_bytes(payload_len);
 
+        if flags & response_flags::CHECKSUM != 0 {
+            if bytes.remaining() < 4 {
+                return Err(ProtocolError::InvalidFormat("missing checksum".into()));
+            }
+            verify_checksum(&bytes[..4], &[&payload])?;
+        }
+
         Ok(Self {
             header: ResponseHeader {
                 status: status as u8,
@@ -560,6 +875,11 @@ impl Response {
     pub fn status(&self) -> StatusCode {
         StatusCode::try_from(self.header.status).unwrap_or(StatusCode::Error)
     }
+
+    /// Check if this frame is a pushed pub/sub message rather than a reply
+    pub fn is_push(&self) -> bool {
+        (self.header.flags & response_flags::PUSH) != 0
+    }
 }
 
 // ============================================================================
@@ -595,6 +915,19 @@ impl DocumentId {
     pub fn from_bytes(bytes: [u8; 16]) -> Self {
         Self(Uuid::from_bytes(bytes))
     }
+
+    /// Parse a document ID from a UUID string, e.g. taken from a URL path
+    pub fn parse_str(s: &str) -> crate::Result<Self> {
+        Uuid::parse_str(s)
+            .map(Self)
+            .map_err(|e| crate::Error::invalid_argument(format!("Invalid document id '{}': {}", s, e)))
+    }
+}
+
+impl From<DocumentId> for Value {
+    fn from(id: DocumentId) -> Self {
+        Value::String(id.to_string())
+    }
 }
 
 impl Default for DocumentId {
@@ -603,6 +936,20 @@ impl Default for DocumentId {
     }
 }
 
+impl std::fmt::Display for DocumentId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.hyphenated().fmt(f)
+    }
+}
+
+impl std::str::FromStr for DocumentId {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_str(s)
+    }
+}
+
 /// ObjectId type for MongoDB compatibility
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ObjectId([u8; 12]);
@@ -643,6 +990,27 @@ impl ObjectId {
         bytes.copy_from_slice(&self.0[0..4]);
         u32::from_be_bytes(bytes) as i64
     }
+
+    /// Parse an ObjectId from its canonical 24-character hex form
+    pub fn from_hex(s: &str) -> crate::Result<Self> {
+        if s.len() != 24 {
+            return Err(crate::Error::invalid_argument(format!(
+                "Invalid ObjectId '{}': expected 24 hex digits, got {} characters",
+                s,
+                s.len()
+            )));
+        }
+        let mut bytes = [0u8; 12];
+        hex::decode_to_slice(s, &mut bytes).map_err(|e| {
+            crate::Error::invalid_argument(format!("Invalid ObjectId '{}': {}", s, e))
+        })?;
+        Ok(Self(bytes))
+    }
+
+    /// Format the ObjectId as 24 lowercase hex digits
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
 }
 
 impl Default for ObjectId {
@@ -651,6 +1019,20 @@ impl Default for ObjectId {
     }
 }
 
+impl std::fmt::Display for ObjectId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl std::str::FromStr for ObjectId {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
 /// Value type supporting all JSON types plus ObjectId, DateTime, Binary
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value")]
@@ -672,6 +1054,9 @@ pub enum Value {
     /// Array of values
     Array(Vec<Value>),
     /// Object with string keys and value values
+    ///
+    /// Keys are kept sorted, so insertion order is not preserved across a
+    /// round trip; see [`Document::fields_in_order`] for a custom order.
     Object(BTreeMap<String, Value>),
     /// ObjectId for MongoDB compatibility
     ObjectId(ObjectId),
@@ -702,6 +1087,26 @@ impl Value {
         }
     }
 
+    /// Get as i32, or `None` if the integer doesn't fit
+    pub fn as_i32(&self) -> Option<i32> {
+        self.as_i64().and_then(|i| i32::try_from(i).ok())
+    }
+
+    /// Get as u64, or `None` if the integer is negative
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i64().and_then(|i| u64::try_from(i).ok())
+    }
+
+    /// Get as u32, or `None` if the integer is negative or doesn't fit
+    pub fn as_u32(&self) -> Option<u32> {
+        self.as_i64().and_then(|i| u32::try_from(i).ok())
+    }
+
+    /// Get as usize, or `None` if the integer is negative or doesn't fit
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_i64().and_then(|i| usize::try_from(i).ok())
+    }
+
     /// Get as f64
     pub fn as_f64(&self) -> Option<f64> {
         match self {
@@ -735,9 +1140,137 @@ impl Value {
             _ => None,
         }
     }
+
+    /// Get the name of this value's variant
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "Null",
+            Value::Bool(_) => "Bool",
+            Value::Int32(_) => "Int32",
+            Value::Int64(_) => "Int64",
+            Value::Float64(_) => "Float64",
+            Value::String(_) => "String",
+            Value::Binary(_) => "Binary",
+            Value::Array(_) => "Array",
+            Value::Object(_) => "Object",
+            Value::ObjectId(_) => "ObjectId",
+            Value::DateTime(_) => "DateTime",
+        }
+    }
+
+    /// Compare two values in a total order, e.g. to sort mixed-type results
+    ///
+    /// Values are ordered by kind first (null, bool, number, string, binary,
+    /// array, object, ObjectId, DateTime), then by value. All numeric
+    /// variants compare by numeric value; floats use IEEE total ordering.
+    pub fn total_cmp(&self, other: &Value) -> std::cmp::Ordering {
+        fn rank(value: &Value) -> u8 {
+            match value {
+                Value::Null => 0,
+                Value::Bool(_) => 1,
+                Value::Int32(_) | Value::Int64(_) | Value::Float64(_) => 2,
+                Value::String(_) => 3,
+                Value::Binary(_) => 4,
+                Value::Array(_) => 5,
+                Value::Object(_) => 6,
+                Value::ObjectId(_) => 7,
+                Value::DateTime(_) => 8,
+            }
+        }
+
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Binary(a), Value::Binary(b)) => a.cmp(b),
+            (Value::Array(a), Value::Array(b)) => a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| x.total_cmp(y))
+                .find(|ord| ord.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Value::Object(a), Value::Object(b)) => a
+                .iter()
+                .zip(b)
+                .map(|((ka, va), (kb, vb))| ka.cmp(kb).then_with(|| va.total_cmp(vb)))
+                .find(|ord| ord.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Value::ObjectId(a), Value::ObjectId(b)) => a.cmp(b),
+            (Value::DateTime(a), Value::DateTime(b)) => a.cmp(b),
+            _ => match (self.as_i64(), other.as_i64(), self.as_f64(), other.as_f64()) {
+                (Some(a), Some(b), _, _) => a.cmp(&b),
+                (_, _, Some(a), Some(b)) => a.total_cmp(&b),
+                _ => rank(self).cmp(&rank(other)),
+            },
+        }
+    }
+
+    /// Convert into `T`, failing with [`Error::TypeMismatch`](crate::Error::TypeMismatch)
+    /// if the value has another type
+    pub fn try_into<T: TryFrom<Value, Error = crate::Error>>(self) -> crate::Result<T> {
+        T::try_from(self)
+    }
+
+    /// The error for converting this value to `expected`
+    fn mismatch(&self, expected: &'static str) -> crate::Error {
+        crate::Error::TypeMismatch {
+            expected,
+            actual: self.type_name(),
+        }
+    }
+}
+
+/// Renders a compact, JSON-like form: strings are quoted, objects and
+/// arrays are rendered recursively, binary data is shown as `0x`-prefixed hex
+/// and ObjectIds as `ObjectId("...")`
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int32(i) => write!(f, "{}", i),
+            Value::Int64(i) => write!(f, "{}", i),
+            Value::Float64(x) => write!(f, "{:?}", x),
+            Value::String(s) => write_quoted(f, s),
+            Value::Binary(bytes) => write!(f, "0x{}", hex::encode(bytes)),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_quoted(f, key)?;
+                    write!(f, ": {}", value)?;
+                }
+                f.write_str("}")
+            }
+            Value::ObjectId(oid) => write!(f, "ObjectId(\"{}\")", oid),
+            Value::DateTime(dt) => write_quoted(f, &dt.to_rfc3339()),
+        }
+    }
+}
+
+/// Write a string as a quoted, escaped JSON string
+fn write_quoted(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    match serde_json::to_string(s) {
+        Ok(quoted) => f.write_str(&quoted),
+        Err(_) => write!(f, "{:?}", s),
+    }
 }
 
 /// Document type for v0.2.0
+///
+/// Fields are stored sorted by name, so they iterate and serialize
+/// alphabetically regardless of insertion order.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Document {
     #[serde(rename = "_id")]
@@ -772,6 +1305,65 @@ impl Document {
     pub fn get(&self, key: &str) -> Option<&Value> {
         self.fields.get(key)
     }
+
+    /// Get a mutable reference to a field, to update it in place
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.fields.get_mut(key)
+    }
+
+    /// Remove a field, returning its value if it was present
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.fields.remove(key)
+    }
+
+    /// Check whether the document has a field
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.fields.contains_key(key)
+    }
+
+    /// Number of fields, not counting the id
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Check whether the document has no fields besides its id
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Iterate over fields in name order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.fields.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Copy the fields of `other` into this document, overwriting fields
+    /// both have; the id of this document is kept
+    pub fn merge(&mut self, other: Document) {
+        self.fields.extend(other.fields);
+    }
+
+    /// Get a field converted to `T`, failing if it's missing or has
+    /// another type
+    pub fn get_as<T: TryFrom<Value, Error = crate::Error>>(&self, key: &str) -> crate::Result<T> {
+        let value = self
+            .get(key)
+            .ok_or_else(|| crate::Error::invalid_argument(format!("Document has no field '{}'", key)))?;
+        value.clone().try_into()
+    }
+
+    /// Iterate over fields with those named in `order` first, in that order,
+    /// followed by the rest alphabetically
+    ///
+    /// Names in `order` that the document doesn't have are skipped.
+    pub fn fields_in_order<'a>(&'a self, order: &'a [&str]) -> impl Iterator<Item = (&'a str, &'a Value)> {
+        let listed = order
+            .iter()
+            .enumerate()
+            .filter(move |(i, name)| !order[..*i].contains(name))
+            .filter_map(move |(_, name)| self.fields.get_key_value(*name));
+        let rest = self.fields.iter().filter(move |(name, _)| !order.contains(&name.as_str()));
+        listed.chain(rest).map(|(name, value)| (name.as_str(), value))
+    }
 }
 
 impl Default for Document {
@@ -780,6 +1372,101 @@ impl Default for Document {
     }
 }
 
+impl<K: Into<String>, V: Into<Value>> Extend<(K, V)> for Document {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, fields: I) {
+        for (key, value) in fields {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl Document {
+    /// Convert to a `Value::Object`, with the id stored under `_id`
+    pub fn to_value(&self) -> Value {
+        Value::from(self.clone())
+    }
+
+    /// Parse a document from a plain JSON object such as `{"name": "Alice"}`
+    ///
+    /// An `_id` field, if present, must be a UUID string; otherwise a new id
+    /// is generated.
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        let json: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| crate::Error::invalid_argument(format!("Invalid document JSON: {}", e)))?;
+        Document::try_from(Value::from(json))
+    }
+}
+
+impl From<Document> for Value {
+    fn from(doc: Document) -> Self {
+        let mut obj = doc.fields;
+        obj.insert("_id".to_string(), Value::String(doc.id.to_string()));
+        Value::Object(obj)
+    }
+}
+
+impl TryFrom<Value> for Document {
+    type Error = crate::Error;
+
+    /// Convert a `Value::Object` to a document, taking the id from `_id`
+    /// (a UUID string) or generating a new one if `_id` is absent
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let mut fields = match value {
+            Value::Object(obj) => obj,
+            other => {
+                return Err(crate::Error::invalid_argument(format!(
+                    "Expected an object to convert to a document, got {:?}",
+                    other
+                )))
+            }
+        };
+
+        let id = match fields.remove("_id") {
+            Some(Value::String(s)) => DocumentId::parse_str(&s)?,
+            Some(other) => {
+                return Err(crate::Error::invalid_argument(format!(
+                    "Document _id must be a UUID string, got {:?}",
+                    other
+                )))
+            }
+            None => DocumentId::new(),
+        };
+
+        Ok(Self { id, fields })
+    }
+}
+
+/// Lay out documents as a table for display
+///
+/// Columns are `_id` followed by the union of field names in first-seen
+/// order; absent fields become empty cells. Strings are shown unquoted and
+/// other values use their `Display` form.
+pub fn tabulate(documents: &[Document]) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut columns = vec!["_id".to_string()];
+    for doc in documents {
+        for name in doc.fields.keys() {
+            if !columns.contains(name) {
+                columns.push(name.clone());
+            }
+        }
+    }
+
+    let rows = documents
+        .iter()
+        .map(|doc| {
+            let mut row = vec![doc.id.to_string()];
+            row.extend(columns[1..].iter().map(|name| match doc.fields.get(name) {
+                Some(Value::String(s)) => s.clone(),
+                Some(value) => value.to_string(),
+                None => String::new(),
+            }));
+            row
+        })
+        .collect();
+
+    (columns, rows)
+}
+
 // ============================================================================
 // v0.2.0 Protocol Request/Response Types
 // ============================================================================
@@ -795,6 +1482,10 @@ pub struct AuthRequest {
 pub enum AuthMethod {
     UsernamePassword,
     JwtToken,
+    /// Authenticated by the client certificate presented in the TLS
+    /// handshake, with the user derived from its subject; no `Auth` command
+    /// is sent
+    ClientCertificate,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -823,52 +1514,752 @@ pub struct QueryRequest {
     pub limit: Option<u64>,
 }
 
-/// List collections request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListCollectionsRequest {
-    pub filter: Option<Value>,
+impl QueryRequest {
+    /// Start building a query over the whole of `collection`
+    pub fn builder(collection: impl Into<String>) -> QueryBuilder {
+        QueryBuilder::new(collection)
+    }
 }
 
-/// Drop collection request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DropCollectionRequest {
-    pub name: String,
+/// Fields to return from a query, for [`QueryRequest::projection`]
+///
+/// Projections are either inclusive (only the listed fields are returned) or
+/// exclusive (the listed fields are left out); the two can't be mixed, except
+/// that `_id` may be suppressed from an inclusive projection with
+/// [`without_id`](Projection::without_id).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Projection {
+    /// Projected fields, mapped to 1 (include) or 0 (exclude)
+    fields: BTreeMap<String, i32>,
 }
 
-/// List indexes request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListIndexesRequest {
-    pub collection: String,
-}
+impl Projection {
+    /// Return only the given fields (plus `_id`, unless suppressed)
+    pub fn include(fields: &[&str]) -> Self {
+        Self::default().and_include(fields)
+    }
 
-/// Drop index request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DropIndexRequest {
-    pub collection: String,
-    pub name: String,
-}
+    /// Return every field except the given ones
+    pub fn exclude(fields: &[&str]) -> Self {
+        Self::default().and_exclude(fields)
+    }
 
-/// Document insertion request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct InsertDocRequest {
-    pub collection: String,
-    pub document: Document,
-}
+    /// Include more fields
+    pub fn and_include(mut self, fields: &[&str]) -> Self {
+        for field in fields {
+            self.fields.insert(field.to_string(), 1);
+        }
+        self
+    }
 
-/// Document update request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UpdateDocRequest {
-    pub collection: String,
-    pub filter: Value,
-    pub update: Value,
-    pub upsert: bool,
-}
+    /// Exclude more fields
+    pub fn and_exclude(mut self, fields: &[&str]) -> Self {
+        for field in fields {
+            self.fields.insert(field.to_string(), 0);
+        }
+        self
+    }
 
-/// Document deletion request
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DeleteDocRequest {
+    /// Leave `_id` out of the returned documents
+    pub fn without_id(self) -> Self {
+        self.and_exclude(&["_id"])
+    }
+
+    /// Whether no fields have been listed
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Build the projection object
+    ///
+    /// Fails with [`Error::InvalidArgument`](crate::Error::InvalidArgument) if
+    /// it mixes included and excluded fields other than `_id`.
+    pub fn build(self) -> crate::Result<Value> {
+        let included = self.fields.values().any(|&v| v == 1);
+        let excluded = self.fields.iter().any(|(field, &v)| v == 0 && field != "_id");
+        if included && excluded {
+            return Err(crate::Error::InvalidArgument(
+                "Projection cannot mix included and excluded fields (other than _id)".to_string(),
+            ));
+        }
+        Ok(Value::Object(
+            self.fields.into_iter().map(|(field, v)| (field, Value::Int32(v))).collect(),
+        ))
+    }
+}
+
+/// A document filter, for [`QueryRequest::filter`], updates and deletes
+///
+/// Each constructor produces the operator object the server expects, e.g.
+/// `Filter::gt("age", 18)` is `{"age": {"$gt": 18}}`. Filters convert into a
+/// [`Value`] wherever one is taken.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter(Value);
+
+impl Filter {
+    /// `{field: {op: value}}`
+    fn compare(field: &str, op: &str, value: Value) -> Self {
+        let mut condition = BTreeMap::new();
+        condition.insert(op.to_string(), value);
+        let mut filter = BTreeMap::new();
+        filter.insert(field.to_string(), Value::Object(condition));
+        Self(Value::Object(filter))
+    }
+
+    /// `{op: [filters]}`
+    fn combine(op: &str, filters: impl IntoIterator<Item = Filter>) -> Self {
+        let mut filter = BTreeMap::new();
+        filter.insert(op.to_string(), Value::Array(filters.into_iter().map(|f| f.0).collect()));
+        Self(Value::Object(filter))
+    }
+
+    /// Match documents where `field` equals `value`
+    pub fn eq(field: &str, value: impl Into<Value>) -> Self {
+        Self::compare(field, "$eq", value.into())
+    }
+
+    /// Match documents where `field` doesn't equal `value`
+    pub fn ne(field: &str, value: impl Into<Value>) -> Self {
+        Self::compare(field, "$ne", value.into())
+    }
+
+    /// Match documents where `field` is greater than `value`
+    pub fn gt(field: &str, value: impl Into<Value>) -> Self {
+        Self::compare(field, "$gt", value.into())
+    }
+
+    /// Match documents where `field` is at least `value`
+    pub fn gte(field: &str, value: impl Into<Value>) -> Self {
+        Self::compare(field, "$gte", value.into())
+    }
+
+    /// Match documents where `field` is less than `value`
+    pub fn lt(field: &str, value: impl Into<Value>) -> Self {
+        Self::compare(field, "$lt", value.into())
+    }
+
+    /// Match documents where `field` is at most `value`
+    pub fn lte(field: &str, value: impl Into<Value>) -> Self {
+        Self::compare(field, "$lte", value.into())
+    }
+
+    /// Match documents where `field` is one of `values`
+    pub fn in_<T: Into<Value>>(field: &str, values: impl IntoIterator<Item = T>) -> Self {
+        Self::compare(field, "$in", Value::Array(values.into_iter().map(Into::into).collect()))
+    }
+
+    /// Match documents where `field` is none of `values`
+    pub fn nin<T: Into<Value>>(field: &str, values: impl IntoIterator<Item = T>) -> Self {
+        Self::compare(field, "$nin", Value::Array(values.into_iter().map(Into::into).collect()))
+    }
+
+    /// Match documents that have (or, with `false`, lack) `field`
+    pub fn exists(field: &str, exists: bool) -> Self {
+        Self::compare(field, "$exists", Value::Bool(exists))
+    }
+
+    /// Match documents matching every one of `filters`
+    pub fn and(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Self::combine("$and", filters)
+    }
+
+    /// Match documents matching any of `filters`
+    pub fn or(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Self::combine("$or", filters)
+    }
+
+    /// The filter as a [`Value`]
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+}
+
+impl From<Filter> for Value {
+    fn from(filter: Filter) -> Self {
+        filter.0
+    }
+}
+
+/// Update operators for [`UpdateDocRequest::update`]
+///
+/// `Update::set("name", "Bob").and_inc("age", 1).and_unset("temp")` builds
+/// `{"$set": {"name": "Bob"}, "$inc": {"age": 1}, "$unset": {"temp": ""}}`.
+/// Setting a field twice with the same operator keeps the last value;
+/// targeting it with two different operators, or targeting both a field and
+/// one of its subfields, is rejected by [`build`](Update::build).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Update {
+    /// Fields and values for each operator
+    operators: BTreeMap<&'static str, BTreeMap<String, Value>>,
+}
+
+impl Update {
+    /// Set `field` to `value`
+    pub fn set(field: &str, value: impl Into<Value>) -> Self {
+        Self::default().and_set(field, value)
+    }
+
+    /// Add `amount` to the number in `field`
+    pub fn inc(field: &str, amount: impl Into<Value>) -> Self {
+        Self::default().and_inc(field, amount)
+    }
+
+    /// Remove `field`
+    pub fn unset(field: &str) -> Self {
+        Self::default().and_unset(field)
+    }
+
+    /// Set another field
+    pub fn and_set(self, field: &str, value: impl Into<Value>) -> Self {
+        self.with("$set", field, value.into())
+    }
+
+    /// Increment another field
+    pub fn and_inc(self, field: &str, amount: impl Into<Value>) -> Self {
+        self.with("$inc", field, amount.into())
+    }
+
+    /// Remove another field
+    pub fn and_unset(self, field: &str) -> Self {
+        self.with("$unset", field, Value::String(String::new()))
+    }
+
+    /// Add `field: value` under `operator`
+    fn with(mut self, operator: &'static str, field: &str, value: Value) -> Self {
+        self.operators.entry(operator).or_default().insert(field.to_string(), value);
+        self
+    }
+
+    /// Whether no operators have been added
+    pub fn is_empty(&self) -> bool {
+        self.operators.is_empty()
+    }
+
+    /// Build the update document
+    ///
+    /// Fails with [`Error::InvalidArgument`](crate::Error::InvalidArgument)
+    /// if the update is empty, or if a field is targeted by more than one
+    /// operator or together with one of its subfields.
+    pub fn build(self) -> crate::Result<Value> {
+        if self.is_empty() {
+            return Err(crate::Error::InvalidArgument("Update has no operators".to_string()));
+        }
+
+        let targets: Vec<(&str, &str)> = self
+            .operators
+            .iter()
+            .flat_map(|(op, fields)| fields.keys().map(move |field| (*op, field.as_str())))
+            .collect();
+        for (i, &(op, field)) in targets.iter().enumerate() {
+            for &(other_op, other) in &targets[i + 1..] {
+                let overlaps = field == other || is_subfield(field, other) || is_subfield(other, field);
+                if overlaps {
+                    return Err(crate::Error::InvalidArgument(format!(
+                        "Update targets '{}' with {} and '{}' with {}",
+                        field, op, other, other_op
+                    )));
+                }
+            }
+        }
+
+        Ok(Value::Object(
+            self.operators
+                .into_iter()
+                .map(|(op, fields)| (op.to_string(), Value::Object(fields)))
+                .collect(),
+        ))
+    }
+}
+
+/// Whether `field` is a dotted subfield of `parent`, e.g. `a.b` of `a`
+fn is_subfield(field: &str, parent: &str) -> bool {
+    field.strip_prefix(parent).is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Builder for a [`QueryRequest`]
+///
+/// By default every document in the collection is returned, unsorted and
+/// without a limit. Projections follow the rules of [`Projection`].
+#[derive(Debug, Clone)]
+pub struct QueryBuilder {
+    /// Collection to query
+    collection: String,
+    /// Filter documents must match
+    filter: Option<Value>,
+    /// Projection built field by field
+    projection: Projection,
+    /// Projection given as a whole
+    raw_projection: Option<Value>,
+    /// Sort specification
+    sort: Option<Value>,
+    /// Number of documents to skip
+    skip: Option<u64>,
+    /// Maximum number of documents to return
+    limit: Option<u64>,
+}
+
+impl QueryBuilder {
+    /// Start a query over the whole of `collection`
+    pub fn new(collection: impl Into<String>) -> Self {
+        Self {
+            collection: collection.into(),
+            filter: None,
+            projection: Projection::default(),
+            raw_projection: None,
+            sort: None,
+            skip: None,
+            limit: None,
+        }
+    }
+
+    /// Return only documents matching `filter`
+    pub fn filter(mut self, filter: impl Into<Value>) -> Self {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Return only the fields selected by `projection`
+    pub fn select(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Set the projection as a raw value, instead of with
+    /// [`select`](QueryBuilder::select), [`include`](QueryBuilder::include) or
+    /// [`exclude`](QueryBuilder::exclude)
+    pub fn projection(mut self, projection: impl Into<Value>) -> Self {
+        self.raw_projection = Some(projection.into());
+        self
+    }
+
+    /// Sort the results, e.g. by `{"age": -1}`
+    pub fn sort(mut self, sort: impl Into<Value>) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    /// Skip the first `n` matching documents
+    pub fn skip(mut self, n: u64) -> Self {
+        self.skip = Some(n);
+        self
+    }
+
+    /// Return at most `n` documents
+    pub fn limit(mut self, n: u64) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Return only the given fields (plus `_id`, unless suppressed)
+    pub fn include(mut self, fields: &[&str]) -> Self {
+        self.projection = self.projection.and_include(fields);
+        self
+    }
+
+    /// Return every field except the given ones
+    pub fn exclude(mut self, fields: &[&str]) -> Self {
+        self.projection = self.projection.and_exclude(fields);
+        self
+    }
+
+    /// Leave `_id` out of the returned documents
+    pub fn without_id(self) -> Self {
+        self.exclude(&["_id"])
+    }
+
+    /// Build the request
+    ///
+    /// Fails with [`Error::InvalidArgument`](crate::Error::InvalidArgument) if
+    /// the projection mixes included and excluded fields other than `_id`, or
+    /// is set both as a whole and field by field.
+    pub fn build(self) -> crate::Result<QueryRequest> {
+        if self.raw_projection.is_some() && !self.projection.is_empty() {
+            return Err(crate::Error::InvalidArgument(
+                "Projection cannot be set both as a whole and with include/exclude".to_string(),
+            ));
+        }
+
+        let projection = if self.projection.is_empty() {
+            self.raw_projection
+        } else {
+            Some(self.projection.build()?)
+        };
+
+        Ok(QueryRequest {
+            collection: self.collection,
+            filter: self.filter,
+            projection,
+            sort: self.sort,
+            skip: self.skip,
+            limit: self.limit,
+        })
+    }
+}
+
+/// List collections request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListCollectionsRequest {
+    pub filter: Option<Value>,
+}
+
+/// Drop collection request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropCollectionRequest {
+    pub name: String,
+}
+
+/// List indexes request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListIndexesRequest {
+    pub collection: String,
+}
+
+/// Distinct field values request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistinctRequest {
+    /// Collection to read from
+    pub collection: String,
+    /// Field whose values to collect
+    pub field: String,
+    /// Only consider documents matching this filter
+    pub filter: Option<Value>,
+}
+
+/// Drop index request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropIndexRequest {
+    pub collection: String,
+    pub name: String,
+}
+
+/// Document insertion request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsertDocRequest {
+    pub collection: String,
+    pub document: Document,
+}
+
+/// Bulk document insertion request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsertManyRequest {
+    /// Collection to insert into
+    pub collection: String,
+    /// Documents to insert, in order
+    pub documents: Vec<Document>,
+}
+
+/// Touch keys request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TouchRequest {
+    /// Keys to touch
+    pub keys: Vec<String>,
+}
+
+/// Switch logical database request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectRequest {
+    /// Logical database to switch to
+    pub db: u32,
+}
+
+/// Collection schema lookup request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetSchemaRequest {
+    /// Collection whose schema to get
+    pub collection: String,
+}
+
+/// Pub/sub message acknowledgment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AckRequest {
+    /// Delivery ids of the messages being acknowledged
+    pub delivery_ids: Vec<u64>,
+}
+
+/// Data structure type of a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyType {
+    /// Plain key-value string
+    String,
+    /// List
+    List,
+    /// Set
+    Set,
+    /// Sorted set
+    SortedSet,
+    /// Hash
+    Hash,
+}
+
+/// Keyspace scan request
+///
+/// `cursor` starts at 0; the server returns the cursor for the next page, and
+/// 0 again once the scan is complete.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRequest {
+    /// Where to resume the scan, or 0 to start
+    pub cursor: u64,
+    /// Only return keys matching this glob pattern
+    pub pattern: Option<String>,
+    /// Roughly how many keys to return per page
+    pub count: Option<u64>,
+    /// Only return keys of this type
+    pub key_type: Option<KeyType>,
+}
+
+/// Document update request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateDocRequest {
     pub collection: String,
     pub filter: Value,
+    pub update: Value,
+    pub upsert: bool,
+}
+
+/// Outcome of a document update
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpdateResult {
+    /// Documents matching the filter
+    pub matched: u64,
+    /// Documents actually changed
+    pub modified: u64,
+    /// Id of the document an upsert inserted because nothing matched
+    pub upserted_id: Option<DocumentId>,
+}
+
+impl UpdateResult {
+    /// Read the result from an update response
+    ///
+    /// Servers that report only an affected count have it used for both
+    /// `matched` and `modified`.
+    pub(crate) fn from_response(data: Option<&Value>, affected_count: Option<u64>) -> crate::Result<Self> {
+        let affected = affected_count.unwrap_or(0);
+        let Some(fields) = data.and_then(Value::as_object) else {
+            return Ok(Self {
+                matched: affected,
+                modified: affected,
+                upserted_id: None,
+            });
+        };
+
+        let count = |name: &str| match fields.get(name) {
+            Some(value) => value
+                .as_u64()
+                .ok_or_else(|| crate::Error::InvalidResponse(format!("Invalid update {} count: {:?}", name, value))),
+            None => Ok(affected),
+        };
+        let upserted_id = match fields.get("upserted_id") {
+            Some(Value::String(id)) => Some(DocumentId::parse_str(id)?),
+            Some(Value::Null) | None => None,
+            Some(other) => {
+                return Err(crate::Error::InvalidResponse(format!(
+                    "Invalid upserted id: {:?}",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            matched: count("matched")?,
+            modified: count("modified")?,
+            upserted_id,
+        })
+    }
+}
+
+/// Document deletion request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteDocRequest {
+    pub collection: String,
+    pub filter: Value,
+    /// Delete at most this many matching documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+/// A write queued in a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransactionOp {
+    /// Insert a document
+    Insert(InsertDocRequest),
+    /// Update matching documents
+    Update(UpdateDocRequest),
+    /// Delete matching documents
+    Delete(DeleteDocRequest),
+}
+
+/// A write in a [`BulkWriteRequest`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", content = "args", rename_all = "snake_case")]
+pub enum WriteOp {
+    /// Insert a document
+    Insert(Document),
+    /// Update matching documents
+    Update {
+        /// Documents to update
+        filter: Value,
+        /// Update operators, see [`Update`]
+        update: Value,
+        /// Insert a document if nothing matches
+        upsert: bool,
+    },
+    /// Delete matching documents
+    Delete {
+        /// Documents to delete
+        filter: Value,
+    },
+}
+
+/// Mixed writes to one collection, sent in a single request
+///
+/// With `ordered` set the server applies the writes in order and stops at
+/// the first failure; otherwise it attempts every write, in any order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkWriteRequest {
+    /// Collection to write to
+    pub collection: String,
+    /// Writes to apply
+    pub operations: Vec<WriteOp>,
+    /// Apply the writes in order, stopping at the first failure
+    pub ordered: bool,
+}
+
+/// Outcome of one write in a bulk write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteOpResult {
+    /// The document was inserted with this id
+    Inserted(DocumentId),
+    /// Documents were updated
+    Updated(UpdateResult),
+    /// This many documents were deleted
+    Deleted(u64),
+    /// The write failed with this message
+    Failed(String),
+}
+
+/// Outcomes of a bulk write, one per write the server attempted
+///
+/// In ordered mode the writes after the first failure are not attempted and
+/// have no outcome.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BulkWriteResult {
+    /// Outcome of each attempted write, in request order
+    pub results: Vec<WriteOpResult>,
+}
+
+impl BulkWriteResult {
+    /// Ids of the inserted documents
+    pub fn inserted_ids(&self) -> Vec<DocumentId> {
+        self.results
+            .iter()
+            .filter_map(|result| match result {
+                WriteOpResult::Inserted(id) => Some(*id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Documents matched by the updates
+    pub fn matched(&self) -> u64 {
+        self.updates().map(|update| update.matched).sum()
+    }
+
+    /// Documents changed by the updates
+    pub fn modified(&self) -> u64 {
+        self.updates().map(|update| update.modified).sum()
+    }
+
+    /// Documents removed by the deletes
+    pub fn deleted(&self) -> u64 {
+        self.results
+            .iter()
+            .map(|result| match result {
+                WriteOpResult::Deleted(n) => *n,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Index and message of the first failed write
+    pub fn first_failure(&self) -> Option<(usize, &str)> {
+        self.results.iter().enumerate().find_map(|(i, result)| match result {
+            WriteOpResult::Failed(message) => Some((i, message.as_str())),
+            _ => None,
+        })
+    }
+
+    fn updates(&self) -> impl Iterator<Item = &UpdateResult> {
+        self.results.iter().filter_map(|result| match result {
+            WriteOpResult::Updated(update) => Some(update),
+            _ => None,
+        })
+    }
+
+    /// Read the outcomes from a bulk write response, whose data holds one
+    /// entry per attempted write: an object with an `error` message if it
+    /// failed, otherwise the update's counts, a `deleted` count, or for
+    /// inserts anything at all
+    pub(crate) fn from_response(operations: &[WriteOp], data: Option<Value>) -> crate::Result<Self> {
+        let entries = match data {
+            Some(Value::Array(entries)) => entries,
+            None | Some(Value::Null) => Vec::new(),
+            Some(other) => {
+                return Err(crate::Error::InvalidResponse(format!(
+                    "Expected an array of bulk write results, got {:?}",
+                    other
+                )))
+            }
+        };
+        if entries.len() > operations.len() {
+            return Err(crate::Error::InvalidResponse(format!(
+                "Bulk write returned {} results for {} operations",
+                entries.len(),
+                operations.len()
+            )));
+        }
+
+        let results = operations
+            .iter()
+            .zip(entries)
+            .map(|(op, entry)| {
+                if let Some(Value::String(message)) = entry.as_object().and_then(|fields| fields.get("error")) {
+                    return Ok(WriteOpResult::Failed(message.clone()));
+                }
+                Ok(match op {
+                    WriteOp::Insert(document) => WriteOpResult::Inserted(document.id),
+                    WriteOp::Update { .. } => WriteOpResult::Updated(match entry.as_u64() {
+                        Some(n) => UpdateResult::from_response(None, Some(n))?,
+                        None => UpdateResult::from_response(Some(&entry), None)?,
+                    }),
+                    WriteOp::Delete { .. } => {
+                        let count = match entry.as_object() {
+                            Some(fields) => fields.get("deleted").and_then(Value::as_u64),
+                            None => entry.as_u64(),
+                        };
+                        match count {
+                            Some(n) => WriteOpResult::Deleted(n),
+                            None => {
+                                return Err(crate::Error::InvalidResponse(format!(
+                                    "Invalid bulk delete result: {:?}",
+                                    entry
+                                )))
+                            }
+                        }
+                    }
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(Self { results })
+    }
+}
+
+/// Transaction commit request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionRequest {
+    /// Writes to apply, in order
+    pub operations: Vec<TransactionOp>,
 }
 
 /// Collection creation request
@@ -887,12 +2278,48 @@ pub struct CreateIndexRequest {
     pub unique: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IndexField {
     pub field: String,
     pub direction: i32, // 1 for ascending, -1 for descending
 }
 
+/// Index description returned by the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexInfo {
+    /// Index name
+    pub name: String,
+    /// Indexed fields in key order
+    pub fields: Vec<IndexField>,
+    /// Whether the index enforces uniqueness
+    pub unique: bool,
+}
+
+impl IndexInfo {
+    /// Parse an index from a `Value::Object` returned by the server
+    pub(crate) fn from_value(value: &Value) -> Option<Self> {
+        let obj = value.as_object()?;
+        let fields = match obj.get("fields") {
+            Some(Value::Array(fields)) => fields
+                .iter()
+                .map(|field| {
+                    let field = field.as_object()?;
+                    Some(IndexField {
+                        field: field.get("field").and_then(|v| v.as_str())?.to_string(),
+                        direction: field.get("direction").and_then(|v| v.as_i32()).unwrap_or(1),
+                    })
+                })
+                .collect::<Option<Vec<_>>>()?,
+            _ => Vec::new(),
+        };
+        Some(Self {
+            name: obj.get("name").and_then(|v| v.as_str())?.to_string(),
+            fields,
+            unique: obj.get("unique").and_then(|v| v.as_bool()).unwrap_or(false),
+        })
+    }
+}
+
 /// List operation request (for Redis-like data structures)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListOpRequest {
@@ -908,6 +2335,25 @@ pub enum ListOperation {
     Len,
 }
 
+impl ListOperation {
+    /// Whether the operation only reads the list
+    pub fn is_read_only(&self) -> bool {
+        self.opcode().is_read()
+    }
+
+    /// Get the opcode the operation is sent with
+    pub fn opcode(&self) -> OpCode {
+        match self {
+            ListOperation::Push { left: true, .. } => OpCode::LPush,
+            ListOperation::Push { left: false, .. } => OpCode::RPush,
+            ListOperation::Pop { left: true } => OpCode::LPop,
+            ListOperation::Pop { left: false } => OpCode::RPop,
+            ListOperation::Range { .. } => OpCode::LRange,
+            ListOperation::Len => OpCode::LLen,
+        }
+    }
+}
+
 /// Set operation request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SetOpRequest {
@@ -927,6 +2373,27 @@ pub enum SetOperation {
     Diff { other_keys: Vec<String> },
 }
 
+impl SetOperation {
+    /// Whether the operation only reads the set
+    pub fn is_read_only(&self) -> bool {
+        self.opcode().is_read()
+    }
+
+    /// Get the opcode the operation is sent with
+    pub fn opcode(&self) -> OpCode {
+        match self {
+            SetOperation::Add { .. } => OpCode::SAdd,
+            SetOperation::Remove { .. } => OpCode::SRem,
+            SetOperation::Members => OpCode::SMembers,
+            SetOperation::IsMember { .. } => OpCode::SIsMember,
+            SetOperation::Card => OpCode::SCard,
+            SetOperation::Union { .. } => OpCode::SUnion,
+            SetOperation::Inter { .. } => OpCode::SInter,
+            SetOperation::Diff { .. } => OpCode::SDiff,
+        }
+    }
+}
+
 /// Sorted set operation request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SortedSetOpRequest {
@@ -944,12 +2411,47 @@ pub enum SortedSetOperation {
     Score { member: Value },
 }
 
+impl SortedSetOperation {
+    /// Whether the operation only reads the sorted set
+    pub fn is_read_only(&self) -> bool {
+        self.opcode().is_read()
+    }
+
+    /// Get the opcode the operation is sent with
+    pub fn opcode(&self) -> OpCode {
+        match self {
+            SortedSetOperation::Add { .. } => OpCode::ZAdd,
+            SortedSetOperation::Remove { .. } => OpCode::ZRem,
+            SortedSetOperation::Range { .. } => OpCode::ZRange,
+            SortedSetOperation::RangeByScore { .. } => OpCode::ZRangeByScore,
+            SortedSetOperation::Card => OpCode::ZCard,
+            SortedSetOperation::Score { .. } => OpCode::ZScore,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScoredMember {
     pub score: f64,
     pub member: Value,
 }
 
+impl ScoredMember {
+    /// Create a member with a score
+    pub fn new(score: f64, member: impl Into<Value>) -> Self {
+        Self {
+            score,
+            member: member.into(),
+        }
+    }
+}
+
+impl<V: Into<Value>> From<(f64, V)> for ScoredMember {
+    fn from((score, member): (f64, V)) -> Self {
+        Self::new(score, member)
+    }
+}
+
 /// Hash operation request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HashOpRequest {
@@ -968,6 +2470,26 @@ pub enum HashOperation {
     Len,
 }
 
+impl HashOperation {
+    /// Whether the operation only reads the hash
+    pub fn is_read_only(&self) -> bool {
+        self.opcode().is_read()
+    }
+
+    /// Get the opcode the operation is sent with
+    pub fn opcode(&self) -> OpCode {
+        match self {
+            HashOperation::Set { .. } => OpCode::HSet,
+            HashOperation::Get { .. } => OpCode::HGet,
+            HashOperation::Del { .. } => OpCode::HDel,
+            HashOperation::GetAll => OpCode::HGetAll,
+            HashOperation::Keys => OpCode::HKeys,
+            HashOperation::Vals => OpCode::HVals,
+            HashOperation::Len => OpCode::HLen,
+        }
+    }
+}
+
 /// Generic operation response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OperationResponse {
@@ -1025,6 +2547,34 @@ impl From<f64> for Value {
     }
 }
 
+impl From<f32> for Value {
+    fn from(f: f32) -> Self {
+        Value::Float64(f as f64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(i: u32) -> Self {
+        Value::Int64(i as i64)
+    }
+}
+
+/// Values above `i64::MAX` become `Float64`, as when converting JSON
+impl From<u64> for Value {
+    fn from(i: u64) -> Self {
+        match i64::try_from(i) {
+            Ok(i) => Value::Int64(i),
+            Err(_) => Value::Float64(i as f64),
+        }
+    }
+}
+
+impl From<usize> for Value {
+    fn from(i: usize) -> Self {
+        Value::from(i as u64)
+    }
+}
+
 impl From<String> for Value {
     fn from(s: String) -> Self {
         Value::String(s)
@@ -1049,6 +2599,25 @@ impl From<Vec<Value>> for Value {
     }
 }
 
+impl<T: Into<Value>, const N: usize> From<[T; N]> for Value {
+    fn from(arr: [T; N]) -> Self {
+        Value::Array(arr.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    fn from(items: &[T]) -> Self {
+        Value::Array(items.iter().cloned().map(Into::into).collect())
+    }
+}
+
+/// `None` becomes `Null`
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(opt: Option<T>) -> Self {
+        opt.map_or(Value::Null, Into::into)
+    }
+}
+
 impl From<BTreeMap<String, Value>> for Value {
     fn from(obj: BTreeMap<String, Value>) -> Self {
         Value::Object(obj)
@@ -1067,16 +2636,177 @@ impl From<DateTime<Utc>> for Value {
     }
 }
 
+impl TryFrom<Value> for bool {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.as_bool().ok_or_else(|| value.mismatch("Bool"))
+    }
+}
+
+/// Accepts `Int32` and `Int64`
+impl TryFrom<Value> for i64 {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.as_i64().ok_or_else(|| value.mismatch("Int64"))
+    }
+}
+
+/// Accepts `Float64` and integers
+impl TryFrom<Value> for f64 {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.as_f64().ok_or_else(|| value.mismatch("Float64"))
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => Err(other.mismatch("String")),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Array(items) => Ok(items),
+            other => Err(other.mismatch("Array")),
+        }
+    }
+}
+
+/// Convert plain (untagged) JSON, e.g. as typed on a command line
+///
+/// Integers become `Int64`, other numbers `Float64`.
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::Int64(i),
+                None => Value::Float64(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(items) => Value::Array(items.into_iter().map(Value::from).collect()),
+            serde_json::Value::Object(fields) => {
+                Value::Object(fields.into_iter().map(|(k, v)| (k, Value::from(v))).collect())
+            }
+        }
+    }
+}
+
 // ============================================================================
 // User Management Request/Response Types
 // ============================================================================
 
+/// A user's role
+///
+/// The built-in roles have their own variants; any other role the server
+/// reports is carried as [`Role::Other`], so roles read from the server
+/// always round-trip. Parsing a string with [`str::parse`] only accepts the
+/// built-in roles, catching typos before the round trip.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(into = "String")]
+pub enum Role {
+    /// Full access, including user management
+    Admin,
+    /// Read and write access to data
+    ReadWrite,
+    /// Read-only access to data
+    ReadOnly,
+    /// A role the client has no variant for, in the server's string form;
+    /// never produced by parsing, so it must be built explicitly
+    Other(String),
+}
+
+impl Role {
+    /// The built-in roles, in order of decreasing privilege
+    pub const ALL: [Role; 3] = [Role::Admin, Role::ReadWrite, Role::ReadOnly];
+
+    /// Get the server's string form of the role
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::Admin => "admin",
+            Role::ReadWrite => "read-write",
+            Role::ReadOnly => "read-only",
+            Role::Other(role) => role,
+        }
+    }
+
+    /// Find the built-in role with the given string form
+    fn builtin(role: &str) -> Option<Role> {
+        Role::ALL.into_iter().find(|builtin| builtin.as_str() == role)
+    }
+
+    /// Interpret a role reported by the server, keeping unknown ones
+    fn from_server(role: String) -> Role {
+        Role::builtin(&role).unwrap_or(Role::Other(role))
+    }
+}
+
+impl<'de> Deserialize<'de> for Role {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Role::from_server)
+    }
+}
+
+impl From<Role> for String {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Other(role) => role,
+            role => role.as_str().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Role::builtin(s).ok_or_else(|| crate::Error::invalid_argument(format!(
+            "Unknown role '{}' (expected one of: admin, read-write, read-only)",
+            s
+        )))
+    }
+}
+
 /// Request to create a new user
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateUserRequest {
     pub username: String,
     pub password: String,
-    pub role: String,
+    pub role: Role,
+}
+
+impl CreateUserRequest {
+    /// Create a request for a user with the given credentials and role
+    ///
+    /// Roles given as strings can be validated first with
+    /// `"admin".parse::<Role>()?`, which fails with
+    /// [`Error::InvalidArgument`](crate::Error::InvalidArgument).
+    pub fn new(username: impl Into<String>, password: impl Into<String>, role: Role) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+            role,
+        }
+    }
 }
 
 /// Request to delete a user
@@ -1085,23 +2815,92 @@ pub struct DeleteUserRequest {
     pub username: String,
 }
 
+/// Request to fetch a single user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetUserRequest {
+    /// User to fetch
+    pub username: String,
+}
+
 /// Request to update a user's role
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateUserRoleRequest {
     pub username: String,
-    pub role: String,
+    pub role: Role,
 }
 
 /// User information response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserInfo {
     pub username: String,
-    pub role: String,
+    pub role: Role,
     pub created_at: String,
     pub last_login: Option<String>,
     pub enabled: bool,
 }
 
+impl UserInfo {
+    /// Parse a user from a `Value::Object` returned by the server
+    pub(crate) fn from_value(value: &Value) -> Option<Self> {
+        let obj = value.as_object()?;
+        Some(Self {
+            username: obj.get("username").and_then(|v| v.as_str())?.to_string(),
+            role: Role::from_server(obj.get("role").and_then(|v| v.as_str()).unwrap_or("unknown").to_string()),
+            created_at: obj.get("created_at").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+            last_login: obj.get("last_login").and_then(|v| v.as_str()).map(|s| s.to_string()),
+            enabled: obj.get("enabled").and_then(|v| v.as_bool()).unwrap_or(false),
+        })
+    }
+}
+
+/// Overall server health, as reported by [`Client::health`](crate::Client::health)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthState {
+    /// Live and ready to serve requests
+    Healthy,
+    /// Serving requests, but impaired (e.g. a replica lagging behind)
+    Degraded,
+    /// Not ready to serve requests
+    Unavailable,
+}
+
+/// Server liveness and readiness
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthStatus {
+    /// Overall health, for branching on degraded vs healthy
+    pub state: HealthState,
+    /// The server process is up and responding
+    pub live: bool,
+    /// The server is ready to serve document and key-value requests
+    pub ready: bool,
+    /// How far this node's replica is behind its primary, if replicated
+    pub replication_lag: Option<std::time::Duration>,
+}
+
+impl HealthStatus {
+    /// Parse a health report from a `Value::Object` returned by the server
+    ///
+    /// The state is taken from `status` if the server sends one, and
+    /// otherwise derived from liveness and readiness.
+    pub(crate) fn from_value(value: &Value) -> Option<Self> {
+        let obj = value.as_object()?;
+        let live = obj.get("live").and_then(|v| v.as_bool()).unwrap_or(true);
+        let ready = obj.get("ready").and_then(|v| v.as_bool()).unwrap_or(live);
+        let state = match obj.get("status").and_then(|v| v.as_str()) {
+            Some("healthy") => HealthState::Healthy,
+            Some("degraded") => HealthState::Degraded,
+            Some("unavailable") | Some("unhealthy") => HealthState::Unavailable,
+            _ if live && ready => HealthState::Healthy,
+            _ => HealthState::Unavailable,
+        };
+        let replication_lag = obj
+            .get("replication_lag_ms")
+            .and_then(|v| v.as_i64())
+            .map(|ms| std::time::Duration::from_millis(ms.max(0) as u64));
+        Some(Self { state, live, ready, replication_lag })
+    }
+}
+
 /// Server information/metrics response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerInfo {
@@ -1114,6 +2913,49 @@ pub struct ServerInfo {
     pub version: String,
 }
 
+impl ServerInfo {
+    /// Memory usage in human-readable units (e.g. `1.5 MiB`)
+    pub fn memory_usage_human(&self) -> String {
+        const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut size = self.memory_usage_bytes as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} B", self.memory_usage_bytes)
+        } else {
+            format!("{:.1} {}", size, UNITS[unit])
+        }
+    }
+
+    /// Uptime as days, hours and minutes (e.g. `1d 3h 4m`)
+    pub fn uptime_human(&self) -> String {
+        let minutes = self.uptime_seconds / 60;
+        let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+        if days > 0 {
+            format!("{}d {}h {}m", days, hours, minutes)
+        } else if hours > 0 {
+            format!("{}h {}m", hours, minutes)
+        } else {
+            format!("{}m", minutes)
+        }
+    }
+}
+
+impl std::fmt::Display for ServerInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Version:        {}", self.version)?;
+        writeln!(f, "Uptime:         {}", self.uptime_human())?;
+        writeln!(f, "Connections:    {}", self.connection_count)?;
+        writeln!(f, "Collections:    {}", self.total_collections)?;
+        writeln!(f, "Memory:         {}", self.memory_usage_human())?;
+        writeln!(f, "Ops/sec:        {:.1}", self.ops_per_second)?;
+        write!(f, "Cache hit rate: {:.1}%", self.cache_hit_rate * 100.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1130,6 +2972,87 @@ mod tests {
         // Header is 24 bytes, then key, then value
         assert_eq!(&bytes[24..27], b"key");
         assert_eq!(&bytes[27..32], b"value");
+        assert_eq!(cmd.encoded_len(), bytes.len());
+    }
+
+    #[test]
+    fn test_command_header_wire_format() {
+        // Distinct, non-symmetric values so a swapped or mis-ordered field shows up
+        let header = CommandHeader {
+            opcode: 0x13,
+            flags: 0x0A,
+            version: PROTOCOL_V2,
+            reserved: 0x7E,
+            seq: 0x0403_0201,
+            key_len: 3,
+            value_len: 5,
+            extra: 0x1817_1615_1413_1211,
+        };
+        let cmd = Command {
+            header,
+            key: Bytes::from_static(b"key"),
+            value: Bytes::from_static(b"value"),
+        };
+        let bytes = cmd.to_bytes();
+
+        assert_eq!(bytes.len(), CommandHeader::SIZE + 8);
+        assert_eq!(bytes[0], 0x13); // opcode
+        assert_eq!(bytes[1], 0x0A); // flags
+        assert_eq!(bytes[2], PROTOCOL_V2); // version
+        assert_eq!(bytes[3], 0x7E); // reserved
+        assert_eq!(&bytes[4..8], &[0x01, 0x02, 0x03, 0x04]); // seq
+        assert_eq!(&bytes[8..12], &[3, 0, 0, 0]); // key_len
+        assert_eq!(&bytes[12..16], &[5, 0, 0, 0]); // value_len
+        assert_eq!(
+            &bytes[16..24],
+            &[0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18]
+        ); // extra
+        assert_eq!(&bytes[24..27], b"key");
+        assert_eq!(&bytes[27..32], b"value");
+
+        let parsed = Command::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.header, header);
+        assert_eq!(&parsed.key[..], b"key");
+        assert_eq!(&parsed.value[..], b"value");
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(&[b"123456789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xCBF4_3926);
+
+        let mut cmd = Command::set(1, "key", "value");
+        cmd.header.flags |= flags::CHECKSUM;
+        let mut bytes = cmd.to_bytes().to_vec();
+        assert_eq!(bytes.len(), cmd.encoded_len());
+        assert_eq!(&Command::from_bytes(&bytes).unwrap().value[..], b"value");
+
+        let last = bytes.len() - 5;
+        bytes[last] ^= 0xFF;
+        assert!(matches!(Command::from_bytes(&bytes), Err(ProtocolError::ChecksumMismatch { .. })));
+
+        let mut buf = BytesMut::new();
+        buf.put_u8(StatusCode::Ok as u8);
+        buf.put_u8(response_flags::CHECKSUM);
+        buf.put_u16_le(0);
+        buf.put_u32_le(7);
+        buf.put_u32_le(2);
+        buf.put_u32_le(0);
+        buf.extend_from_slice(b"ok");
+        buf.put_u32_le(crc32(&[b"ok"]));
+        assert_eq!(&Response::from_bytes(&buf, PROTOCOL_V2).unwrap().payload[..], b"ok");
+
+        let last = buf.len() - 1;
+        buf[last] ^= 0xFF;
+        let err = Response::from_bytes(&buf, PROTOCOL_V2).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn test_command_from_bytes_rejects_truncated_input() {
+        let bytes = Command::set(1, "key", "value").to_bytes();
+        assert!(CommandHeader::from_bytes(&bytes[..CommandHeader::SIZE - 1]).is_err());
+        assert!(Command::from_bytes(&bytes[..bytes.len() - 1]).is_err());
     }
 
     #[test]
@@ -1143,12 +3066,42 @@ mod tests {
         buf.put_u64_le(0); // extra
         buf.extend_from_slice(b"hello"); // payload
 
-        let resp = Response::from_bytes(&buf).unwrap();
+        let resp = Response::from_bytes(&buf, PROTOCOL_V1).unwrap();
         assert!(resp.is_ok());
         assert_eq!(resp.header.seq, 42);
         assert_eq!(&resp.payload[..], b"hello");
     }
 
+    #[test]
+    fn test_response_parsing_follows_protocol_version() {
+        // A v0.2.0 frame followed by at least 4 more bytes used to be taken
+        // for a v0.1.x frame
+        let mut buf = BytesMut::new();
+        buf.put_u8(StatusCode::Ok as u8);
+        buf.put_u8(0);
+        buf.put_u16_le(0);
+        buf.put_u32_le(7);
+        buf.put_u32_le(2);
+        buf.put_u32_le(0); // padding
+        buf.extend_from_slice(b"hi");
+        buf.extend_from_slice(&[0x01, 0, 0, 0, 9, 0, 0, 0]); // start of the next frame
+
+        let resp = Response::from_bytes(&buf, PROTOCOL_V2).unwrap();
+        assert_eq!(resp.header.seq, 7);
+        assert_eq!(resp.header.extra, 0);
+        assert_eq!(&resp.payload[..], b"hi");
+
+        // The same length read as v0.1.x takes the padding and payload as
+        // the extra field
+        let resp = Response::from_bytes(&buf, PROTOCOL_V1).unwrap();
+        assert_eq!(resp.header.extra, u64::from_le_bytes(*b"\0\0\0\0hi\x01\0"));
+
+        // A v0.1.x header needs all 20 bytes
+        assert!(Response::from_bytes(&buf[..18], PROTOCOL_V1).is_err());
+        assert!(Response::from_bytes(&buf[..18], PROTOCOL_V2).is_ok());
+        assert!(Response::from_bytes(&buf, 0x07).is_err());
+    }
+
     #[test]
     fn test_document_creation() {
         let mut doc = Document::new();
@@ -1161,6 +3114,66 @@ mod tests {
         assert_eq!(doc.get("active").unwrap().as_bool().unwrap(), true);
     }
 
+    #[test]
+    fn test_document_fields() {
+        let mut doc = Document::new();
+        assert!(doc.is_empty());
+        doc.extend([("name", "Alice"), ("city", "Pune")]);
+        assert_eq!(doc.len(), 2);
+        assert!(doc.contains_key("city"));
+
+        *doc.get_mut("city").unwrap() = Value::from("Mumbai");
+        assert_eq!(doc.remove("city"), Some(Value::from("Mumbai")));
+        assert_eq!(doc.remove("city"), None);
+
+        let mut other = Document::new();
+        other.insert("name", "Bob");
+        other.insert("age", 30i32);
+        let id = doc.id;
+        doc.merge(other);
+        assert_eq!(doc.id, id);
+        let fields: Vec<(&str, &Value)> = doc.iter().collect();
+        assert_eq!(fields, [("age", &Value::Int32(30)), ("name", &Value::from("Bob"))]);
+
+        let mut extra = BTreeMap::new();
+        extra.insert("active".to_string(), Value::Bool(true));
+        doc.extend(extra);
+        assert_eq!(doc.get("active"), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_document_value_round_trip() {
+        let mut doc = Document::new();
+        doc.insert("name", "Alice");
+
+        let value = doc.to_value();
+        let obj = value.as_object().unwrap();
+        assert_eq!(obj.get("_id").unwrap().as_str().unwrap(), doc.id.as_uuid().to_string());
+        assert_eq!(obj.get("name").unwrap().as_str().unwrap(), "Alice");
+
+        let back = Document::try_from(value).unwrap();
+        assert_eq!(back, doc);
+
+        assert!(Document::try_from(Value::Int32(1)).is_err());
+        let mut bad_id = BTreeMap::new();
+        bad_id.insert("_id".to_string(), Value::String("not-a-uuid".to_string()));
+        assert!(Document::try_from(Value::Object(bad_id)).is_err());
+    }
+
+    #[test]
+    fn test_value_integer_accessors() {
+        assert_eq!(Value::Int32(7).as_u64(), Some(7));
+        assert_eq!(Value::Int64(7).as_usize(), Some(7));
+        assert_eq!(Value::Int64(-1).as_u64(), None);
+        assert_eq!(Value::Int32(-1).as_usize(), None);
+        assert_eq!(Value::Int64(-1).as_i32(), Some(-1));
+        assert_eq!(Value::Int64(i64::from(i32::MAX) + 1).as_i32(), None);
+        assert_eq!(Value::Int64(i64::from(u32::MAX)).as_u32(), Some(u32::MAX));
+        assert_eq!(Value::Int64(i64::from(u32::MAX) + 1).as_u32(), None);
+        assert_eq!(Value::Float64(1.0).as_u64(), None);
+        assert_eq!(Value::from("7").as_i32(), None);
+    }
+
     #[test]
     fn test_value_conversions() {
         let val_bool: Value = true.into();
@@ -1173,6 +3186,16 @@ mod tests {
         assert!(matches!(val_int, Value::Int64(42)));
     }
 
+    #[test]
+    fn test_scored_member_construction() {
+        let member: ScoredMember = (1.5, "alice").into();
+        assert_eq!(member.score, 1.5);
+        assert_eq!(member.member, Value::String("alice".to_string()));
+
+        let member = ScoredMember::new(2.0, Value::Int32(7));
+        assert_eq!(member.member, Value::Int32(7));
+    }
+
     #[test]
     fn test_protocol_version() {
         let header = CommandHeader::new(OpCode::Ping, 1);
@@ -1190,6 +3213,137 @@ mod tests {
         assert_eq!(oid, oid2);
     }
 
+    #[test]
+    fn test_object_id_hex() {
+        let oid: ObjectId = "507f1f77bcf86cd799439011".parse().unwrap();
+        assert_eq!(oid.timestamp(), 0x507f1f77);
+        assert_eq!(oid.to_string(), "507f1f77bcf86cd799439011");
+        assert_eq!(ObjectId::from_hex("507F1F77BCF86CD799439011").unwrap(), oid);
+
+        let fresh = ObjectId::new();
+        assert_eq!(ObjectId::from_hex(&fresh.to_hex()).unwrap(), fresh);
+
+        assert!(ObjectId::from_hex("507f1f77bcf86cd79943901").is_err());
+        assert!(ObjectId::from_hex("507f1f77bcf86cd79943901z").is_err());
+    }
+
+    #[test]
+    fn test_user_info_from_value() {
+        let mut obj = BTreeMap::new();
+        obj.insert("username".to_string(), Value::String("alice".to_string()));
+        obj.insert("role".to_string(), Value::String("admin".to_string()));
+        obj.insert("enabled".to_string(), Value::Bool(true));
+
+        let user = UserInfo::from_value(&Value::Object(obj)).unwrap();
+        assert_eq!(user.username, "alice");
+        assert_eq!(user.role, Role::Admin);
+        assert!(user.last_login.is_none());
+        assert!(user.enabled);
+
+        // A user without a username is not a valid user
+        assert!(UserInfo::from_value(&Value::Object(BTreeMap::new())).is_none());
+        assert!(UserInfo::from_value(&Value::Null).is_none());
+    }
+
+    #[test]
+    fn test_fields_in_order() {
+        let mut doc = Document::new();
+        doc.insert("name", "Alice");
+        doc.insert("age", 30);
+        doc.insert("city", "Paris");
+        doc.insert("email", "alice@example.com");
+
+        let names: Vec<_> = doc.fields_in_order(&["name", "missing", "email", "name"]).map(|(k, _)| k).collect();
+        assert_eq!(names, ["name", "email", "age", "city"]);
+
+        let names: Vec<_> = doc.fields_in_order(&[]).map(|(k, _)| k).collect();
+        assert_eq!(names, ["age", "city", "email", "name"]);
+    }
+
+    #[test]
+    fn test_tabulate() {
+        let mut alice = Document::new();
+        alice.insert("name", "Alice");
+        alice.insert("age", 30);
+        let mut bob = Document::new();
+        bob.insert("name", "Bob");
+        bob.insert("email", "bob@example.com");
+
+        let (columns, rows) = tabulate(&[alice.clone(), bob.clone()]);
+        assert_eq!(columns, ["_id", "age", "name", "email"]);
+        assert_eq!(rows[0], [alice.id.to_string(), "30".into(), "Alice".into(), String::new()]);
+        assert_eq!(rows[1], [bob.id.to_string(), String::new(), "Bob".into(), "bob@example.com".into()]);
+
+        let (columns, rows) = tabulate(&[]);
+        assert_eq!(columns, ["_id"]);
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn test_health_status_from_value() {
+        let mut obj = BTreeMap::new();
+        obj.insert("live".to_string(), Value::Bool(true));
+        obj.insert("ready".to_string(), Value::Bool(true));
+        obj.insert("status".to_string(), Value::String("degraded".to_string()));
+        obj.insert("replication_lag_ms".to_string(), Value::Int64(1500));
+        let health = HealthStatus::from_value(&Value::Object(obj.clone())).unwrap();
+        assert_eq!(health.state, HealthState::Degraded);
+        assert!(health.live && health.ready);
+        assert_eq!(health.replication_lag, Some(std::time::Duration::from_millis(1500)));
+
+        // Without a status, the state follows readiness
+        obj.remove("status");
+        obj.insert("ready".to_string(), Value::Bool(false));
+        let health = HealthStatus::from_value(&Value::Object(obj)).unwrap();
+        assert_eq!(health.state, HealthState::Unavailable);
+        assert!(health.live && !health.ready);
+
+        let health = HealthStatus::from_value(&Value::Object(BTreeMap::new())).unwrap();
+        assert_eq!((health.state, health.replication_lag), (HealthState::Healthy, None));
+        assert!(HealthStatus::from_value(&Value::Null).is_none());
+    }
+
+    #[test]
+    fn test_index_info_from_value() {
+        let mut field = BTreeMap::new();
+        field.insert("field".to_string(), Value::String("age".to_string()));
+        field.insert("direction".to_string(), Value::Int32(-1));
+        let mut obj = BTreeMap::new();
+        obj.insert("name".to_string(), Value::String("age_idx".to_string()));
+        obj.insert("fields".to_string(), Value::Array(vec![Value::Object(field)]));
+        obj.insert("unique".to_string(), Value::Bool(true));
+
+        let index = IndexInfo::from_value(&Value::Object(obj)).unwrap();
+        assert_eq!(index.name, "age_idx");
+        assert_eq!(index.fields, vec![IndexField { field: "age".to_string(), direction: -1 }]);
+        assert!(index.unique);
+
+        // An index without a name is not a valid index
+        assert!(IndexInfo::from_value(&Value::Object(BTreeMap::new())).is_none());
+        assert!(IndexInfo::from_value(&Value::Null).is_none());
+    }
+
+    #[test]
+    fn test_role_parsing() {
+        for role in Role::ALL {
+            assert_eq!(role.as_str().parse::<Role>().unwrap(), role);
+            assert_eq!(
+                serde_json::to_string(&role).unwrap(),
+                format!("\"{}\"", role)
+            );
+        }
+
+        let err = "admn".parse::<Role>().unwrap_err();
+        assert!(matches!(err, crate::Error::InvalidArgument(_)));
+
+        // Roles only the server knows are kept as they are
+        assert!("auditor".parse::<Role>().is_err());
+        let auditor = Role::Other("auditor".to_string());
+        assert_eq!(serde_json::to_string(&auditor).unwrap(), "\"auditor\"");
+        assert_eq!(serde_json::from_str::<Role>("\"auditor\"").unwrap(), auditor);
+        assert_eq!(serde_json::from_str::<Role>("\"read-only\"").unwrap(), Role::ReadOnly);
+    }
+
     #[test]
     fn test_document_id() {
         let doc_id = DocumentId::new();
@@ -1197,4 +3351,266 @@ mod tests {
         let doc_id2 = DocumentId::from_bytes(bytes);
         assert_eq!(doc_id, doc_id2);
     }
+
+    #[test]
+    fn test_value_from_more_types() {
+        assert_eq!(Value::from(7u32), Value::Int64(7));
+        assert_eq!(Value::from(7usize), Value::Int64(7));
+        assert_eq!(Value::from(u64::MAX), Value::Float64(u64::MAX as f64));
+        assert_eq!(Value::from(0.5f32), Value::Float64(0.5));
+        assert_eq!(Value::from(None::<i32>), Value::Null);
+        assert_eq!(Value::from(Some("x")), Value::String("x".to_string()));
+        assert_eq!(
+            Value::from(["a", "b"]),
+            Value::Array(vec![Value::from("a"), Value::from("b")])
+        );
+        assert_eq!(Value::from(&[1i64, 2][..]), Value::Array(vec![Value::Int64(1), Value::Int64(2)]));
+        // Byte vectors stay binary
+        assert_eq!(Value::from(vec![1u8, 2]), Value::Binary(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_value_try_into() {
+        let age: i64 = Value::Int32(30).try_into().unwrap();
+        assert_eq!(age, 30);
+        assert_eq!(Value::String("x".to_string()).try_into::<String>().unwrap(), "x");
+        assert!(matches!(
+            Value::String("x".to_string()).try_into::<bool>(),
+            Err(crate::Error::TypeMismatch { expected: "Bool", actual: "String" })
+        ));
+
+        let mut doc = Document::new();
+        doc.insert("age", 30i32);
+        doc.insert("tags", vec![Value::from("a")]);
+        assert_eq!(doc.get_as::<i64>("age").unwrap(), 30);
+        assert_eq!(doc.get_as::<f64>("age").unwrap(), 30.0);
+        assert_eq!(doc.get_as::<Vec<Value>>("tags").unwrap().len(), 1);
+        assert!(doc.get_as::<String>("age").is_err());
+        assert!(doc.get_as::<String>("missing").is_err());
+    }
+
+    #[test]
+    fn test_value_display() {
+        let mut obj = BTreeMap::new();
+        obj.insert("name".to_string(), Value::String("Al \"x\"".to_string()));
+        obj.insert(
+            "tags".to_string(),
+            Value::Array(vec![Value::Int32(5), Value::Float64(1.0), Value::Null]),
+        );
+        obj.insert("raw".to_string(), Value::Binary(vec![0xde, 0xad]));
+        assert_eq!(
+            Value::Object(obj).to_string(),
+            r#"{"name": "Al \"x\"", "raw": 0xdead, "tags": [5, 1.0, null]}"#
+        );
+
+        let oid = ObjectId::from_hex("507f1f77bcf86cd799439011").unwrap();
+        assert_eq!(Value::ObjectId(oid).to_string(), r#"ObjectId("507f1f77bcf86cd799439011")"#);
+        assert_eq!(Value::Bool(true).to_string(), "true");
+    }
+
+    #[test]
+    fn test_value_total_cmp() {
+        let mut values = vec![
+            Value::String("b".to_string()),
+            Value::Float64(1.5),
+            Value::Null,
+            Value::Int64(2),
+            Value::String("a".to_string()),
+            Value::Int32(1),
+            Value::Bool(true),
+        ];
+        values.sort_by(Value::total_cmp);
+        assert_eq!(
+            values,
+            vec![
+                Value::Null,
+                Value::Bool(true),
+                Value::Int32(1),
+                Value::Float64(1.5),
+                Value::Int64(2),
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+            ]
+        );
+        assert!(Value::Int32(3).total_cmp(&Value::Int64(3)).is_eq());
+    }
+
+    #[test]
+    fn test_document_id_string_round_trip() {
+        let id: DocumentId = "67e55044-10b1-426f-9247-bb680e5fe0c8".parse().unwrap();
+        assert_eq!(id.to_string(), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+        assert_eq!(DocumentId::parse_str(&id.to_string()).unwrap(), id);
+        assert!(DocumentId::parse_str("not-a-uuid").is_err());
+    }
+
+    #[test]
+    fn test_query_builder_projection() {
+        let projection = |request: QueryRequest| match request.projection {
+            Some(Value::Object(fields)) => fields,
+            other => panic!("unexpected projection {:?}", other),
+        };
+
+        let fields = projection(QueryBuilder::new("users").exclude(&["password"]).without_id().build().unwrap());
+        assert_eq!(fields.get("password"), Some(&Value::Int32(0)));
+        assert_eq!(fields.get("_id"), Some(&Value::Int32(0)));
+
+        // Suppressing _id is allowed alongside an inclusive projection
+        let fields = projection(QueryBuilder::new("users").include(&["name"]).without_id().build().unwrap());
+        assert_eq!(fields.get("name"), Some(&Value::Int32(1)));
+        assert_eq!(fields.get("_id"), Some(&Value::Int32(0)));
+
+        assert!(QueryBuilder::new("users").build().unwrap().projection.is_none());
+        assert!(matches!(
+            QueryBuilder::new("users").include(&["name"]).exclude(&["password"]).build(),
+            Err(crate::Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_projection() {
+        let fields = |projection: Projection| match projection.build().unwrap() {
+            Value::Object(fields) => fields,
+            other => panic!("unexpected projection {:?}", other),
+        };
+
+        let included = fields(Projection::include(&["name", "age"]));
+        assert_eq!(included.len(), 2);
+        assert_eq!(included.get("age"), Some(&Value::Int32(1)));
+        let excluded = fields(Projection::exclude(&["password"]).without_id());
+        assert_eq!(excluded.get("password"), Some(&Value::Int32(0)));
+        assert_eq!(excluded.get("_id"), Some(&Value::Int32(0)));
+
+        assert!(Projection::include(&["name"]).without_id().build().is_ok());
+        assert!(matches!(
+            Projection::exclude(&["password"]).and_include(&["name"]).build(),
+            Err(crate::Error::InvalidArgument(_))
+        ));
+
+        let request = QueryBuilder::new("users").select(Projection::include(&["name"])).build().unwrap();
+        assert_eq!(request.projection, Some(Projection::include(&["name"]).build().unwrap()));
+    }
+
+    #[test]
+    fn test_filter() {
+        // JSON numbers convert to Int64, so compare with i64 operands
+        let check = |filter: Filter, expected: serde_json::Value| assert_eq!(filter.into_value(), Value::from(expected));
+        check(Filter::eq("age", 30i64), serde_json::json!({"age": {"$eq": 30}}));
+        check(Filter::gt("age", 18i64), serde_json::json!({"age": {"$gt": 18}}));
+        check(
+            Filter::in_("status", ["active", "pending"]),
+            serde_json::json!({"status": {"$in": ["active", "pending"]}}),
+        );
+        check(
+            Filter::or([Filter::lt("age", 13i64), Filter::and([Filter::gte("age", 65i64), Filter::exists("retired", true)])]),
+            serde_json::json!({"$or": [
+                {"age": {"$lt": 13}},
+                {"$and": [{"age": {"$gte": 65}}, {"retired": {"$exists": true}}]}
+            ]}),
+        );
+
+        let request = QueryBuilder::new("users").filter(Filter::ne("name", "bob")).build().unwrap();
+        assert_eq!(request.filter, Some(Filter::ne("name", "bob").into()));
+    }
+
+    #[test]
+    fn test_update() {
+        let update = Update::set("name", "Bob").and_inc("age", 1i64).and_unset("temp").and_set("address.city", "Pune");
+        assert_eq!(
+            update.build().unwrap(),
+            Value::from(serde_json::json!({
+                "$set": {"name": "Bob", "address.city": "Pune"},
+                "$inc": {"age": 1},
+                "$unset": {"temp": ""}
+            }))
+        );
+
+        // The last value set for a field wins
+        let update = Update::set("name", "Bob").and_set("name", "Eve").build().unwrap();
+        assert_eq!(update, Value::from(serde_json::json!({"$set": {"name": "Eve"}})));
+
+        for conflicting in [
+            Update::set("age", 30i64).and_inc("age", 1i64),
+            Update::set("address", "Pune").and_unset("address.city"),
+            Update::inc("stats.views", 1i64).and_set("stats", Value::Null),
+            Update::default(),
+        ] {
+            assert!(matches!(conflicting.build(), Err(crate::Error::InvalidArgument(_))));
+        }
+        // A shared prefix isn't a subfield
+        assert!(Update::set("name", "Bob").and_unset("names").build().is_ok());
+    }
+
+    #[test]
+    fn test_query_builder_options() {
+        let mut sort = BTreeMap::new();
+        sort.insert("age".to_string(), Value::Int32(-1));
+        let request = QueryRequest::builder("users")
+            .filter(Value::Object(BTreeMap::new()))
+            .sort(sort)
+            .skip(20)
+            .limit(10)
+            .build()
+            .unwrap();
+        assert_eq!(request.collection, "users");
+        assert!(request.filter.is_some() && request.sort.is_some());
+        assert_eq!((request.skip, request.limit), (Some(20), Some(10)));
+
+        let request = QueryBuilder::new("users").build().unwrap();
+        assert!(request.filter.is_none() && request.limit.is_none());
+
+        let raw = Value::Object(BTreeMap::new());
+        assert_eq!(QueryBuilder::new("users").projection(raw.clone()).build().unwrap().projection, Some(raw.clone()));
+        assert!(QueryBuilder::new("users").projection(raw).include(&["name"]).build().is_err());
+    }
+
+    #[test]
+    fn test_update_result_from_response() {
+        let id = DocumentId::new();
+        let mut data = BTreeMap::new();
+        data.insert("matched".to_string(), Value::Int64(0));
+        data.insert("modified".to_string(), Value::Int64(0));
+        data.insert("upserted_id".to_string(), Value::String(id.to_string()));
+        let result = UpdateResult::from_response(Some(&Value::Object(data)), Some(1)).unwrap();
+        assert_eq!(result, UpdateResult { matched: 0, modified: 0, upserted_id: Some(id) });
+
+        // Older servers only report how many documents were affected
+        let result = UpdateResult::from_response(None, Some(3)).unwrap();
+        assert_eq!(result, UpdateResult { matched: 3, modified: 3, upserted_id: None });
+    }
+
+    #[test]
+    fn test_server_info_humanized() {
+        let info = ServerInfo {
+            uptime_seconds: 86_400 + 3 * 3600 + 4 * 60 + 59,
+            connection_count: 3,
+            total_collections: 2,
+            memory_usage_bytes: 1536 * 1024,
+            ops_per_second: 120.0,
+            cache_hit_rate: 0.95,
+            version: "0.2.0".to_string(),
+        };
+        assert_eq!(info.uptime_human(), "1d 3h 4m");
+        assert_eq!(info.memory_usage_human(), "1.5 MiB");
+        assert!(info.to_string().contains("Cache hit rate: 95.0%"));
+
+        let info = ServerInfo { uptime_seconds: 59, memory_usage_bytes: 512, ..info };
+        assert_eq!(info.uptime_human(), "0m");
+        assert_eq!(info.memory_usage_human(), "512 B");
+    }
+
+    #[test]
+    fn test_document_from_json() {
+        let doc = Document::from_json(r#"{"name": "Alice", "age": 30, "score": 9.5, "tags": ["a"]}"#).unwrap();
+        assert_eq!(doc.get("name"), Some(&Value::String("Alice".to_string())));
+        assert_eq!(doc.get("age"), Some(&Value::Int64(30)));
+        assert_eq!(doc.get("score"), Some(&Value::Float64(9.5)));
+        assert_eq!(doc.get("tags"), Some(&Value::Array(vec![Value::String("a".to_string())])));
+
+        let id = DocumentId::new();
+        let doc = Document::from_json(&format!(r#"{{"_id": "{}"}}"#, id.as_uuid())).unwrap();
+        assert_eq!(doc.id, id);
+
+        assert!(matches!(Document::from_json("[1, 2]"), Err(crate::Error::InvalidArgument(_))));
+        assert!(matches!(Document::from_json("{"), Err(crate::Error::InvalidArgument(_))));
+    }
 }
@@ -5,7 +5,7 @@ use veddb_client::{Client, Result};
 #[tokio::test]
 async fn test_basic_operations() -> Result<()> {
     // Create a test client
-    let addr = "127.0.0.1:50051".parse().unwrap();
+    let addr = "127.0.0.1:50051";
     let client = Client::connect(addr).await?;
 
     // Test set and get
@@ -24,7 +24,7 @@ async fn test_connection_pooling() -> Result<()> {
     use std::sync::Arc;
     use tokio::task;
 
-    let addr = "127.0.0.1:50051".parse().unwrap();
+    let addr = "127.0.0.1:50051";
     let client = Arc::new(Client::with_pool_size(addr, 5).await?);
     let mut handles = vec![];
 